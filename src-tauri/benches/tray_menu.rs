@@ -0,0 +1,83 @@
+//! Measures tray layout generation for a large vault.
+//!
+//! Native menu construction needs a running event loop, so this covers the
+//! layout pass that decides which items get built.
+//!
+//! Run with `cargo bench --bench tray_menu`.
+
+use serde_json::json;
+use sklad_lib::models::{AppSettings, Node};
+use sklad_lib::tray_generator::TrayGenerator;
+use std::time::{Duration, Instant};
+
+const FOLDERS: usize = 100;
+const SNIPPETS_PER_FOLDER: usize = 99;
+const ITERATIONS: u32 = 50;
+
+/// Builds 100 folders holding 99 snippets each (10 000 nodes in total).
+fn large_vault() -> Vec<Node> {
+    let folders: Vec<_> = (0..FOLDERS)
+        .map(|f| {
+            let folder_id = format!("folder-{}", f);
+            let children: Vec<_> = (0..SNIPPETS_PER_FOLDER)
+                .map(|s| {
+                    json!({
+                        "id": format!("snippet-{}-{}", f, s),
+                        "type": "snippet",
+                        "label": format!("Snippet {}", s),
+                        "parentId": folder_id,
+                        "createdAt": 0,
+                        "value": "echo hello",
+                        "isSecret": s % 10 == 0,
+                    })
+                })
+                .collect();
+            json!({
+                "id": folder_id,
+                "type": "folder",
+                "label": format!("Folder {}", f),
+                "parentId": null,
+                "createdAt": 0,
+                "children": children,
+            })
+        })
+        .collect();
+
+    serde_json::from_value(json!(folders)).expect("valid nodes")
+}
+
+fn bench(name: &str, nodes: &[Node], settings: &AppSettings) {
+    let mut total = Duration::ZERO;
+    let mut entries = 0;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        let layout = TrayGenerator::layout(nodes, settings, true);
+        total += start.elapsed();
+        entries = layout.entries.len();
+    }
+    println!(
+        "{:<28} {:>10.3?} / iter ({} root entries)",
+        name,
+        total / ITERATIONS,
+        entries
+    );
+}
+
+fn main() {
+    let nodes = large_vault();
+
+    let unlimited = AppSettings {
+        tray_max_depth: 0,
+        tray_max_items_per_folder: 0,
+        ..AppSettings::default()
+    };
+    bench("10k nodes, unlimited", &nodes, &unlimited);
+    bench("10k nodes, default limits", &nodes, &AppSettings::default());
+
+    let shallow = AppSettings {
+        tray_max_depth: 1,
+        tray_max_items_per_folder: 20,
+        ..AppSettings::default()
+    };
+    bench("10k nodes, depth 1 / 20 items", &nodes, &shallow);
+}
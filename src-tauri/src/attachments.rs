@@ -0,0 +1,168 @@
+use crate::data_manager::DataManager;
+use crate::models::{Attachment, Node, NodeType};
+use crate::security::{self, Key};
+use crate::vault;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Largest file that can be attached.
+pub const MAX_SIZE: u64 = 10 * 1024 * 1024;
+/// Larger files get a blob file instead of growing sklad.json, which every save rewrites.
+const INLINE_LIMIT: u64 = 64 * 1024;
+/// Next to sklad.json, so the blob files sync along with it.
+pub const BLOB_DIR_NAME: &str = "attachments";
+/// Associated data of a blob file, followed by the node id.
+const BLOB_AAD: &str = "sklad attachment:";
+
+fn blob_path(data_manager: &DataManager, id: &str) -> PathBuf {
+    blob_dir(data_manager).join(blob_file_name(id))
+}
+
+fn blob_file_name(id: &str) -> String {
+    format!("{}.bin", id)
+}
+
+fn blob_dir(data_manager: &DataManager) -> PathBuf {
+    data_manager
+        .file_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(BLOB_DIR_NAME)
+}
+
+fn blob_aad(id: &str) -> Vec<u8> {
+    format!("{}{}", BLOB_AAD, id).into_bytes()
+}
+
+/// A new attachment node under `parent_id` with the file at `path`, encrypted with `key`.
+/// The node still has to be saved; a blob file it needs is written right away.
+pub fn attach(
+    data_manager: &DataManager,
+    path: &Path,
+    parent_id: Option<String>,
+    key: &Key,
+) -> Result<Node, String> {
+    let metadata =
+        fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if !metadata.is_file() {
+        return Err(format!("{} is not a file", path.display()));
+    }
+    if metadata.len() > MAX_SIZE {
+        return Err(format!(
+            "{} is larger than {} MB, the limit for attachments",
+            path.display(),
+            MAX_SIZE / 1024 / 1024
+        ));
+    }
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "attachment".into());
+
+    let blob = data.len() as u64 > INLINE_LIMIT;
+    let mut node = Node::new_attachment(
+        file_name.clone(),
+        parent_id,
+        Attachment {
+            file_name,
+            size: data.len() as u64,
+            blob,
+        },
+    );
+    let content = if blob {
+        // The blob gets a key of its own, so rotating the vault key only rewraps that
+        let file_key: Key = security::random_bytes();
+        let (ciphertext, nonce) = security::encrypt_bytes(&data, &file_key, &blob_aad(&node.id))?;
+        let dir = blob_dir(data_manager);
+        fs::create_dir_all(&dir)
+            .and_then(|()| {
+                fs::write(
+                    blob_path(data_manager, &node.id),
+                    [nonce, ciphertext].concat(),
+                )
+            })
+            .map_err(|e| format!("Failed to store the attachment: {}", e))?;
+        file_key.to_vec()
+    } else {
+        data
+    };
+    node.encrypted_value = Some(vault::encrypt_value_bytes(&node.id, &content, key)?);
+    Ok(node)
+}
+
+/// The decrypted file of the attachment `node`.
+pub fn read(data_manager: &DataManager, node: &Node, key: &Key) -> Result<Vec<u8>, String> {
+    let attachment = node
+        .attachment
+        .as_ref()
+        .filter(|_| node.node_type == NodeType::Attachment)
+        .ok_or_else(|| format!("'{}' is not an attachment", node.label))?;
+    let encrypted = node
+        .encrypted_value
+        .as_deref()
+        .ok_or("The attachment is empty")?;
+    let content = vault::try_decrypt_value_bytes(&node.id, encrypted, key, false)
+        .ok_or("Failed to decrypt the attachment")?;
+    if !attachment.blob {
+        return Ok(content);
+    }
+
+    let file_key = Key::try_from(content).map_err(|_| "Failed to decrypt the attachment")?;
+    let blob = fs::read(blob_path(data_manager, &node.id))
+        .map_err(|e| format!("The file of the attachment is missing: {}", e))?;
+    if blob.len() < security::NONCE_SIZE {
+        return Err("The file of the attachment is damaged".into());
+    }
+    let (nonce, ciphertext) = blob.split_at(security::NONCE_SIZE);
+    security::decrypt_bytes(ciphertext, nonce, &file_key, &blob_aad(&node.id))
+        .map_err(|_| "The file of the attachment is damaged".into())
+}
+
+fn blob_ids(nodes: &[Node], out: &mut HashSet<String>) {
+    for node in nodes {
+        if node
+            .attachment
+            .as_ref()
+            .is_some_and(|attachment| attachment.blob)
+        {
+            out.insert(node.id.clone());
+        }
+        blob_ids(node.children.as_deref().unwrap_or_default(), out);
+    }
+}
+
+/// Deletes the blob files of attachments that were in `previous` but are gone from `nodes`.
+pub fn prune(data_manager: &DataManager, previous: &[Node], nodes: &[Node]) -> io::Result<()> {
+    let (mut removed, mut kept) = (HashSet::new(), HashSet::new());
+    blob_ids(previous, &mut removed);
+    blob_ids(nodes, &mut kept);
+    for id in removed.difference(&kept) {
+        match fs::remove_file(blob_path(data_manager, id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Blob files the attachments in `nodes` need that are missing, as paths relative to
+/// the folder of sklad.json with `/` separators, the way git names them.
+pub fn missing_blobs(data_manager: &DataManager, nodes: &[Node]) -> Vec<String> {
+    let mut ids = HashSet::new();
+    blob_ids(nodes, &mut ids);
+    ids.into_iter()
+        .filter(|id| !blob_path(data_manager, id).exists())
+        .map(|id| format!("{}/{}", BLOB_DIR_NAME, blob_file_name(&id)))
+        .collect()
+}
+
+/// Deletes all blob files, for a vault whose secrets are reset.
+pub fn remove_all(data_manager: &DataManager) -> io::Result<()> {
+    match fs::remove_dir_all(blob_dir(data_manager)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,189 @@
+use crate::models::{Node, NodeType};
+use crate::security::Key;
+use crate::strength;
+use crate::vault;
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// Secrets scoring below this are reported as weak.
+const WEAK_SCORE: u8 = 3;
+/// Length of the hash prefix a range file of a k-anonymity breach list is named after.
+const PREFIX_LENGTH: usize = 5;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WeakSecret {
+    pub id: String,
+    pub path: String,
+    pub score: u8,
+    pub warning: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BreachedSecret {
+    pub id: String,
+    pub path: String,
+    /// Times the value appears in the breaches.
+    pub count: u64,
+}
+
+/// What `audit` found wrong with the secrets of a vault.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AuditReport {
+    /// Secrets that were checked.
+    pub checked: usize,
+    pub weak: Vec<WeakSecret>,
+    /// Groups of secrets with the same value.
+    pub reused: Vec<Vec<AuditEntry>>,
+    pub breached: Vec<BreachedSecret>,
+    /// Whether a breach list was given, so `breached` means anything.
+    #[serde(rename = "breachListChecked")]
+    pub breach_list_checked: bool,
+}
+
+/// Checks the secrets in `nodes`, decrypted with `key` (in the older format too if
+/// `legacy`), for weak and reused values, and for values in the SHA-1 breach list at
+/// `breach_list`, if given. One-time code seeds
+/// are left out, being random keys rather than passwords.
+pub fn audit(
+    nodes: &[Node],
+    key: &Key,
+    legacy: bool,
+    breach_list: Option<&Path>,
+) -> Result<AuditReport, String> {
+    let mut secrets = Vec::new();
+    collect_secrets(nodes, "", key, legacy, &mut secrets);
+
+    let mut report = AuditReport {
+        checked: secrets.len(),
+        breach_list_checked: breach_list.is_some(),
+        ..Default::default()
+    };
+    let mut by_value: BTreeMap<&str, Vec<AuditEntry>> = BTreeMap::new();
+    for (entry, value) in &secrets {
+        let strength = strength::estimate(value);
+        if strength.score < WEAK_SCORE {
+            report.weak.push(WeakSecret {
+                id: entry.id.clone(),
+                path: entry.path.clone(),
+                score: strength.score,
+                warning: strength.warning,
+            });
+        }
+        by_value.entry(value).or_default().push(entry.clone());
+    }
+    report.reused = by_value
+        .into_values()
+        .filter(|entries| entries.len() > 1)
+        .collect();
+
+    if let Some(breach_list) = breach_list {
+        let hashes: HashSet<String> = secrets.iter().map(|(_, value)| sha1_hex(value)).collect();
+        let counts = breach_counts(breach_list, &hashes)
+            .map_err(|e| format!("Failed to read the breach list: {}", e))?;
+        for (entry, value) in &secrets {
+            if let Some(count) = counts.get(&sha1_hex(value)) {
+                report.breached.push(BreachedSecret {
+                    id: entry.id.clone(),
+                    path: entry.path.clone(),
+                    count: *count,
+                });
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn collect_secrets(
+    nodes: &[Node],
+    prefix: &str,
+    key: &Key,
+    legacy: bool,
+    out: &mut Vec<(AuditEntry, String)>,
+) {
+    for node in nodes {
+        let path = if prefix.is_empty() {
+            node.label.clone()
+        } else {
+            format!("{}/{}", prefix, node.label)
+        };
+        let is_password = node.node_type == NodeType::Snippet
+            && node.is_secret.unwrap_or(false)
+            && node.totp.is_none();
+        let value = node
+            .encrypted_value
+            .as_deref()
+            .filter(|_| is_password)
+            .and_then(|encrypted| vault::try_decrypt_value(&node.id, encrypted, key, legacy))
+            .filter(|value| !value.is_empty());
+        if let Some(value) = value {
+            out.push((
+                AuditEntry {
+                    id: node.id.clone(),
+                    path: path.clone(),
+                },
+                value,
+            ));
+        }
+        collect_secrets(
+            node.children.as_deref().unwrap_or_default(),
+            &path,
+            key,
+            legacy,
+            out,
+        );
+    }
+}
+
+fn sha1_hex(value: &str) -> String {
+    hex::encode_upper(Sha1::digest(value.as_bytes()))
+}
+
+/// Breach counts of those of `hashes` that are in the list at `path`. The list is either
+/// a file of "HASH:COUNT" lines, or a folder of k-anonymity range files named after the
+/// first five characters of the hashes they hold, with "SUFFIX:COUNT" lines. Only the
+/// range files of `hashes` are read.
+fn breach_counts(path: &Path, hashes: &HashSet<String>) -> io::Result<HashMap<String, u64>> {
+    let mut counts = HashMap::new();
+    if !path.is_dir() {
+        read_hash_lines(File::open(path)?, "", hashes, &mut counts)?;
+        return Ok(counts);
+    }
+
+    let prefixes: HashSet<&str> = hashes.iter().map(|hash| &hash[..PREFIX_LENGTH]).collect();
+    for prefix in prefixes {
+        let file = [prefix.to_string(), format!("{}.txt", prefix)]
+            .iter()
+            .find_map(|name| File::open(path.join(name)).ok());
+        if let Some(file) = file {
+            read_hash_lines(file, prefix, hashes, &mut counts)?;
+        }
+    }
+    Ok(counts)
+}
+
+fn read_hash_lines(
+    file: File,
+    prefix: &str,
+    hashes: &HashSet<String>,
+    counts: &mut HashMap<String, u64>,
+) -> io::Result<()> {
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        let (hash, count) = line.trim().split_once(':').unwrap_or((line.trim(), "1"));
+        let hash = format!("{}{}", prefix, hash.to_ascii_uppercase());
+        if hashes.contains(&hash) {
+            counts.insert(hash, count.trim().parse().unwrap_or(1));
+        }
+    }
+    Ok(())
+}
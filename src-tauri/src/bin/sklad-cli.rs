@@ -0,0 +1,3 @@
+fn main() {
+    sklad_lib::cli::run()
+}
@@ -0,0 +1,782 @@
+use crate::audit;
+use crate::data_manager::DataManager;
+use crate::export::{
+    self,
+    bundle::{self, BundlePayload, BundleSettings},
+    ExportFormat,
+};
+use crate::import::{self, ConflictStrategy, ImportFormat, ImportPreview};
+use crate::integrity::ChangeKind;
+use crate::models::{self, AppSettings, Node, NodeType};
+use crate::security::Key;
+use crate::sync::{ConflictKind, MergeSide};
+use crate::totp;
+use crate::unlock_guard;
+use crate::vault;
+use clap::{Parser, Subcommand};
+use serde::de::DeserializeOwned;
+use std::io::{IsTerminal, Read};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Same delay as the app uses when `clearClipboard` is on.
+const CLIPBOARD_CLEAR_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Parser)]
+#[command(
+    name = "sklad",
+    version,
+    about = "Command-line access to the Sklad vault"
+)]
+struct Cli {
+    /// Data directory to use instead of the one shared with the app
+    #[arg(long, global = true, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
+
+    /// Name or id of the vault to use instead of the active one
+    #[arg(long, global = true, value_name = "NAME")]
+    vault: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List folders and snippets, optionally below a folder
+    List { path: Option<String> },
+    /// Print the value of a snippet
+    Get { path: String },
+    /// Copy the value of a snippet to the clipboard
+    Copy { path: String },
+    /// Print the earlier values of a secret, newest first
+    History { path: String },
+    /// Add a snippet (value from --value, a prompt or stdin) or a folder
+    Add {
+        path: String,
+        #[arg(long)]
+        value: Option<String>,
+        #[arg(long)]
+        secret: bool,
+        #[arg(long, conflicts_with_all = ["value", "secret"])]
+        folder: bool,
+        /// Add a one-time code snippet for an otpauth:// URI or base32 secret
+        #[arg(long, value_name = "URI", conflicts_with_all = ["value", "secret", "folder"])]
+        totp: Option<String>,
+    },
+    /// Change the label, value or secret flag of a node
+    Edit {
+        path: String,
+        #[arg(long)]
+        label: Option<String>,
+        #[arg(long)]
+        value: Option<String>,
+        #[arg(long)]
+        secret: Option<bool>,
+    },
+    /// Remove a snippet or folder
+    Rm {
+        path: String,
+        /// Allow removing a folder that is not empty
+        #[arg(short, long)]
+        recursive: bool,
+    },
+    /// Search labels and non-secret values
+    Search { query: String },
+    /// Report weak, reused and breached secrets
+    Audit {
+        /// SHA-1 breach list to use instead of the configured one
+        #[arg(long, value_name = "PATH")]
+        breach_list: Option<PathBuf>,
+    },
+    /// Export the vault, see `export_data`
+    Export {
+        file: PathBuf,
+        #[arg(long, value_parser = parse_enum::<ExportFormat>)]
+        format: ExportFormat,
+        /// Include secrets in a plain-text format
+        #[arg(long)]
+        include_secrets: bool,
+    },
+    /// Import from another tool or a .sklad bundle, see `import_data`
+    Import {
+        file: PathBuf,
+        #[arg(long, value_parser = parse_enum::<ImportFormat>)]
+        format: Option<ImportFormat>,
+        #[arg(long, value_parser = parse_enum::<ConflictStrategy>, default_value = "skip")]
+        conflict: ConflictStrategy,
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Pull the vault history from the git remote and merge it
+    Pull,
+    /// Push the vault history to the git remote
+    Push,
+}
+
+/// Parses the snake_case serde name of one of the shared enums.
+fn parse_enum<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_string()))
+        .map_err(|_| format!("unknown value '{}'", value))
+}
+
+/// Entry point of the `sklad-cli` binary.
+pub fn run() {
+    let cli = Cli::parse();
+    if let Err(e) = execute(cli) {
+        eprintln!("sklad: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn execute(cli: Cli) -> Result<(), String> {
+    let mut session = Session::open(cli.data_dir, cli.vault.as_deref())?;
+
+    match cli.command {
+        Command::List { path } => {
+            let nodes = match path {
+                Some(path) => session
+                    .find_folder(&path)?
+                    .children
+                    .as_deref()
+                    .unwrap_or_default(),
+                None => &session.nodes,
+            };
+            print_tree(nodes, 0);
+        }
+        Command::Get { path } => {
+            let node = session.find_snippet(&path)?.clone();
+            session.unlock_for(&node)?;
+            println!("{}", totp::resolve(&node, session.reveal(&node)?)?);
+        }
+        Command::Copy { path } => {
+            let node = session.find_snippet(&path)?.clone();
+            session.unlock_for(&node)?;
+            let value = totp::resolve(&node, session.reveal(&node)?)?;
+            let secret = node.is_secret.unwrap_or(false);
+
+            session
+                .data_manager
+                .record_use(&node.id)
+                .map_err(|e| format!("Failed to record the use: {}", e))?;
+
+            let clear_after = secret && session.settings.security.clear_clipboard;
+            copy_to_clipboard(&value, clear_after)?;
+        }
+        Command::History { path } => {
+            let node = session.find_snippet(&path)?.clone();
+            if !node.is_secret.unwrap_or(false) {
+                return Err(format!("'{}' is not a secret", path));
+            }
+            session.unlock()?;
+            let key = session.key.as_ref().ok_or("Vault is locked")?;
+            let now = models::timestamp_millis();
+            let legacy = vault::accepts_legacy(&session.settings);
+            for past in vault::reveal_history(&node, key, legacy)?.iter().rev() {
+                println!("{} ago\t{}", format_age(now - past.replaced_at), past.value);
+            }
+        }
+        Command::Add {
+            path,
+            value,
+            secret,
+            folder,
+            totp,
+        } => {
+            let (parent_path, label) = split_last(&path)?;
+            if secret || totp.is_some() {
+                session.require_master_password()?;
+                session.unlock()?;
+            }
+            let node = if folder {
+                Node::new_folder(label, None)
+            } else if let Some(uri) = totp {
+                totp::new_snippet(&uri, None, Some(label))?
+            } else {
+                let value = match value {
+                    Some(value) => value,
+                    None => read_value(secret)?,
+                };
+                Node::new_snippet(label, None, value, secret)
+            };
+
+            session.lock()?;
+            session.insert(&parent_path, node)?;
+            session.save()?;
+        }
+        Command::Edit {
+            path,
+            label,
+            value,
+            secret,
+        } => {
+            let node = session.find(&path)?.clone();
+            let was_secret = node.is_secret.unwrap_or(false);
+            let becomes_secret = secret.unwrap_or(was_secret);
+            if becomes_secret && !was_secret {
+                session.require_master_password()?;
+            }
+            // Changing the value or the flag of a secret goes through the key
+            if (value.is_some() && becomes_secret) || secret.is_some_and(|s| s != was_secret) {
+                session.unlock()?;
+            }
+            let revealed = match secret {
+                Some(false) if was_secret && value.is_none() => Some(session.reveal(&node)?),
+                _ => None,
+            };
+
+            session.lock()?;
+            let parent_path = split_last(&path)?.0;
+            if let Some(label) = &label {
+                if label != &node.label && session.sibling_exists(&parent_path, label)? {
+                    return Err(format!("'{}' already exists", label));
+                }
+            }
+            let target = DataManager::find_node_by_id_mut(&mut session.nodes, &node.id)
+                .ok_or_else(|| format!("'{}' was removed meanwhile", path))?;
+
+            if let Some(label) = label {
+                target.label = label;
+            }
+            if matches!(target.node_type, NodeType::Snippet) {
+                if let Some(secret) = secret {
+                    target.is_secret = Some(secret);
+                }
+                // Plain values of secrets get encrypted on save, the old one kept in
+                // the history
+                let edited = value.is_some();
+                if let Some(value) = value.or(revealed) {
+                    if was_secret && edited {
+                        vault::push_history(target);
+                    }
+                    target.value = Some(value);
+                    target.encrypted_value = None;
+                }
+            } else if value.is_some() || secret.is_some() {
+                return Err("Folders have no value".into());
+            }
+            session.save()?;
+        }
+        Command::Rm { path, recursive } => {
+            let node = session.find(&path)?;
+            let has_children = node.children.as_ref().is_some_and(|c| !c.is_empty());
+            if has_children && !recursive {
+                return Err(format!("'{}' is not empty, use --recursive", path));
+            }
+            let id = node.id.clone();
+
+            session.lock()?;
+            if !remove_node(&mut session.nodes, &id) {
+                return Err(format!("'{}' was removed meanwhile", path));
+            }
+            session.save()?;
+        }
+        Command::Search { query } => {
+            for path in DataManager::search_paths(&session.nodes, &query) {
+                println!("{}", path);
+            }
+        }
+        Command::Audit { breach_list } => {
+            session.require_master_password()?;
+            session.unlock()?;
+            let key = session.key.as_ref().ok_or("Vault is locked")?;
+            let breach_list = breach_list.or_else(|| {
+                session
+                    .settings
+                    .security
+                    .breach_list_path
+                    .as_ref()
+                    .filter(|path| !path.trim().is_empty())
+                    .map(PathBuf::from)
+            });
+            let legacy = vault::accepts_legacy(&session.settings);
+            let report = audit::audit(&session.nodes, key, legacy, breach_list.as_deref())?;
+
+            println!("{} secrets checked", report.checked);
+            for weak in &report.weak {
+                let warning = weak.warning.as_deref().unwrap_or("easy to guess");
+                println!("weak ({}/4)\t{}\t{}", weak.score, weak.path, warning);
+            }
+            for group in &report.reused {
+                let paths: Vec<&str> = group.iter().map(|entry| entry.path.as_str()).collect();
+                println!("reused\t{}", paths.join(", "));
+            }
+            for breached in &report.breached {
+                println!("breached ({} times)\t{}", breached.count, breached.path);
+            }
+            if !report.breach_list_checked {
+                println!("No breach list configured, breached secrets were not checked");
+            }
+        }
+        Command::Export {
+            file,
+            format,
+            include_secrets,
+        } => {
+            let is_bundle = format == ExportFormat::SkladBundle;
+            let with_secrets = is_bundle || include_secrets;
+            let mut nodes = session.nodes.clone();
+            if with_secrets && vault::has_encrypted_secrets(&nodes) {
+                session.unlock()?;
+                if let Some(key) = &session.key {
+                    let legacy = vault::accepts_legacy(&session.settings);
+                    vault::decrypt_nodes_recursive(&mut nodes, key, legacy);
+                }
+            }
+            let nodes = export::prepare_nodes(&nodes, with_secrets)?;
+
+            if is_bundle {
+                let passphrase = prompt_password("Bundle passphrase: ")?;
+                if prompt_password("Repeat passphrase: ")? != passphrase {
+                    return Err("Passphrases do not match".into());
+                }
+                let payload = BundlePayload {
+                    nodes,
+                    settings: BundleSettings::from_settings(&session.settings),
+                    exported_at: models::timestamp_millis(),
+                };
+                export::write_file(&file, &bundle::seal(&payload, &passphrase)?, false)?;
+            } else {
+                let content = export::render(format, &nodes)?;
+                export::write_file(&file, &content, include_secrets)?;
+            }
+        }
+        Command::Import {
+            file,
+            format,
+            conflict,
+            dry_run,
+        } => {
+            let is_bundle = match format {
+                Some(format) => format == ImportFormat::SkladBundle,
+                None => bundle::is_bundle(&file),
+            };
+            let parsed = if is_bundle {
+                let content = std::fs::read(&file)
+                    .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+                let payload = bundle::open(&content, &prompt_password("Bundle passphrase: ")?)?;
+                totp::check_new_snippets(&payload.nodes)?;
+                Parsed::Bundle(payload)
+            } else {
+                let (format, items) = import::parse(&file, format)?;
+                Parsed::Items(format, items)
+            };
+
+            if !dry_run {
+                let has_secrets = match &parsed {
+                    Parsed::Bundle(payload) => vault::has_plain_secrets(&payload.nodes),
+                    Parsed::Items(_, items) => items
+                        .iter()
+                        .any(|item| item.fields.iter().any(|field| field.secret)),
+                };
+                if has_secrets {
+                    session.unlock()?;
+                }
+                session.lock()?;
+            }
+            let (preview, bundle_settings) = match parsed {
+                Parsed::Bundle(payload) => (
+                    import::sklad_bundle::merge(&mut session.nodes, payload.nodes, conflict),
+                    Some(payload.settings),
+                ),
+                Parsed::Items(format, items) => (
+                    import::merge(&mut session.nodes, format, &items, conflict),
+                    None,
+                ),
+            };
+            print_preview(&preview);
+
+            if !dry_run {
+                session.save()?;
+                if let Some(bundle_settings) = bundle_settings {
+                    bundle_settings.apply_to(&mut session.settings);
+                    session
+                        .data_manager
+                        .save_settings(&session.settings)
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+        }
+        Command::Pull => {
+            let report = session
+                .data_manager
+                .git_pull(&|_, _, _| false)
+                .map_err(|e| format!("Failed to pull: {}", e))?;
+            if report.merged_files.is_empty() {
+                println!("Already up to date");
+            }
+            for conflict in &report.conflicts {
+                println!(
+                    "{}: {}, kept the {} version",
+                    conflict.label,
+                    match conflict.kind {
+                        ConflictKind::BothChanged => "changed on both sides",
+                        ConflictKind::ChangedAndDeleted =>
+                            "changed on one side, deleted on the other",
+                    },
+                    match conflict.kept {
+                        MergeSide::Local => "local",
+                        MergeSide::Remote => "remote",
+                    }
+                );
+            }
+        }
+        Command::Push => session
+            .data_manager
+            .git_push()
+            .map_err(|e| format!("Failed to push: {}", e))?,
+    }
+
+    Ok(())
+}
+
+enum Parsed {
+    Bundle(BundlePayload),
+    Items(ImportFormat, Vec<import::ImportedItem>),
+}
+
+/// A rough "3 hours" for a duration in milliseconds.
+fn format_age(ms: i64) -> String {
+    let secs = ms.max(0) / 1000;
+    let (value, unit) = match secs {
+        0..60 => (secs, "second"),
+        60..3600 => (secs / 60, "minute"),
+        3600..86400 => (secs / 3600, "hour"),
+        _ => (secs / 86400, "day"),
+    };
+    format!("{} {}{}", value, unit, if value == 1 { "" } else { "s" })
+}
+
+/// The vault as seen by one CLI invocation.
+struct Session {
+    data_manager: DataManager,
+    settings: AppSettings,
+    nodes: Vec<Node>,
+    key: Option<Key>,
+}
+
+impl Session {
+    fn open(data_dir: Option<PathBuf>, vault: Option<&str>) -> Result<Self, String> {
+        let dir = data_dir
+            .or_else(DataManager::default_dir)
+            .ok_or("Could not determine the data directory, use --data-dir")?;
+        let mut data_manager = DataManager::with_dir(dir.clone());
+        if let Some(vault) = vault {
+            let settings = data_manager.load_settings();
+            let id = settings
+                .vault_names()
+                .into_iter()
+                .find(|(id, name)| *id == vault || name.eq_ignore_ascii_case(vault))
+                .map(|(id, _)| id.to_string())
+                .ok_or_else(|| format!("Vault '{}' not found", vault))?;
+            data_manager = DataManager::with_vault(dir, &id);
+        }
+        Ok(Self {
+            settings: data_manager.load_settings(),
+            nodes: data_manager.load_data(),
+            data_manager,
+            key: None,
+        })
+    }
+
+    /// Takes the vault lock and reloads the nodes, so the following `save` can't
+    /// overwrite what the app or another CLI wrote since `open`. Prompt for the
+    /// master password before this, the app blocks on the lock while it is held.
+    fn lock(&mut self) -> Result<(), String> {
+        self.data_manager
+            .lock()
+            .map_err(|e| format!("Failed to lock the vault: {}", e))?;
+        self.nodes = self.data_manager.load_data();
+        Ok(())
+    }
+
+    fn require_master_password(&self) -> Result<(), String> {
+        if self.settings.security.master_password_enabled {
+            Ok(())
+        } else {
+            Err("Set a master password in Sklad before adding secrets".into())
+        }
+    }
+
+    /// Asks for the master password, if one is set and it wasn't entered yet. Shared
+    /// vaults open with this device's identity instead.
+    fn unlock(&mut self) -> Result<(), String> {
+        if self.key.is_some() || !self.settings.security.master_password_enabled {
+            return Ok(());
+        }
+        if self.settings.security.shared {
+            let key = self
+                .data_manager
+                .shared_key()
+                .map_err(|e| e.to_string())?
+                .ok_or("This device is not a member of the shared vault")?;
+            return self.set_key(key);
+        }
+        let password = prompt_password("Master password: ")?;
+        let key = unlock_guard::derive_vault_key(
+            &self.data_manager,
+            &self.settings,
+            &password,
+            None,
+            "cli",
+        )?
+        .ok_or("Incorrect master password")?;
+
+        let failures = unlock_guard::take_failures(&self.data_manager);
+        if let Some(last) = failures.last() {
+            eprintln!(
+                "Warning: {} failed unlock attempt(s) since the last unlock, the last one {} ago from {}",
+                failures.len(),
+                format_age(models::timestamp_millis() - last.at),
+                last.source
+            );
+        }
+        self.set_key(key)
+    }
+
+    /// Takes `key` once the vault file checks out against its MAC. Saves sign with it.
+    fn set_key(&mut self, key: Key) -> Result<(), String> {
+        self.data_manager.set_integrity_key(Some(key));
+        let report = self
+            .data_manager
+            .verify_integrity()
+            .map_err(|e| format!("Failed to verify the vault: {}", e))?;
+        if let Some(report) = report {
+            self.data_manager.set_integrity_key(None);
+            eprintln!("Warning: the vault file changed outside Sklad since it was last verified:");
+            for change in &report.changes {
+                let kind = match change.kind {
+                    ChangeKind::Added => "added",
+                    ChangeKind::Removed => "removed",
+                    ChangeKind::Changed => "changed",
+                };
+                if change.fields.is_empty() {
+                    eprintln!("  {:<8} {}", kind, change.path);
+                } else {
+                    eprintln!(
+                        "  {:<8} {} ({})",
+                        kind,
+                        change.path,
+                        change.fields.join(", ")
+                    );
+                }
+            }
+            return Err("Trust or undo the changes in Sklad before using the vault".into());
+        }
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn unlock_for(&mut self, node: &Node) -> Result<(), String> {
+        if node.is_secret.unwrap_or(false) {
+            self.unlock()?;
+        }
+        Ok(())
+    }
+
+    /// The plain value of a snippet, decrypting secrets with the unlocked key.
+    fn reveal(&self, node: &Node) -> Result<String, String> {
+        match (&node.encrypted_value, node.is_secret.unwrap_or(false)) {
+            (Some(encrypted), true) => {
+                let key = self.key.as_ref().ok_or("Vault is locked")?;
+                let legacy = vault::accepts_legacy(&self.settings);
+                vault::try_decrypt_value(&node.id, encrypted, key, legacy)
+                    .ok_or_else(|| "Failed to decrypt".into())
+            }
+            _ => Ok(node.value.clone().unwrap_or_default()),
+        }
+    }
+
+    /// Encrypts new plain secrets, and older ciphertexts while unlocked, and writes the nodes.
+    fn save(&self) -> Result<(), String> {
+        let mut nodes = self.nodes.clone();
+        let legacy = vault::accepts_legacy(&self.settings);
+        if vault::has_plain_secrets(&nodes) {
+            self.require_master_password()?;
+            let key = self.key.as_ref().ok_or("Vault is locked")?;
+            vault::encrypt_nodes_recursive(&mut nodes, key, legacy)?;
+        } else if let Some(key) = self
+            .key
+            .as_ref()
+            .filter(|_| legacy && vault::has_legacy_ciphertexts(&nodes))
+        {
+            vault::encrypt_nodes_recursive(&mut nodes, key, legacy)?;
+        }
+        self.data_manager
+            .save_data(&nodes)
+            .map_err(|e| format!("Failed to save: {}", e))
+    }
+
+    fn find(&self, path: &str) -> Result<&Node, String> {
+        DataManager::find_node_by_path(&self.nodes, &DataManager::split_path(path))
+            .ok_or_else(|| format!("'{}' not found", path))
+    }
+
+    fn find_snippet(&self, path: &str) -> Result<&Node, String> {
+        let node = self.find(path)?;
+        match node.node_type {
+            NodeType::Snippet => Ok(node),
+            _ => Err(format!("'{}' is not a snippet", path)),
+        }
+    }
+
+    fn find_folder(&self, path: &str) -> Result<&Node, String> {
+        let node = self.find(path)?;
+        match node.node_type {
+            NodeType::Folder => Ok(node),
+            _ => Err(format!("'{}' is not a folder", path)),
+        }
+    }
+
+    fn siblings_mut(
+        &mut self,
+        parent_path: &[String],
+    ) -> Result<(&mut Vec<Node>, Option<String>), String> {
+        if parent_path.is_empty() {
+            return Ok((&mut self.nodes, None));
+        }
+        let segments: Vec<&str> = parent_path.iter().map(String::as_str).collect();
+        let id = DataManager::find_node_by_path(&self.nodes, &segments)
+            .filter(|node| matches!(node.node_type, NodeType::Folder))
+            .map(|node| node.id.clone())
+            .ok_or_else(|| format!("Folder '{}' not found", parent_path.join("/")))?;
+        let folder = DataManager::find_node_by_id_mut(&mut self.nodes, &id).unwrap();
+        Ok((folder.children.get_or_insert_with(Vec::new), Some(id)))
+    }
+
+    fn sibling_exists(&mut self, parent_path: &[String], label: &str) -> Result<bool, String> {
+        let (siblings, _) = self.siblings_mut(parent_path)?;
+        Ok(siblings.iter().any(|n| n.label == label))
+    }
+
+    fn insert(&mut self, parent_path: &[String], mut node: Node) -> Result<(), String> {
+        let (siblings, parent_id) = self.siblings_mut(parent_path)?;
+        if siblings.iter().any(|n| n.label == node.label) {
+            return Err(format!("'{}' already exists", node.label));
+        }
+        node.parent_id = parent_id;
+        siblings.push(node);
+        Ok(())
+    }
+}
+
+fn split_last(path: &str) -> Result<(Vec<String>, String), String> {
+    let mut segments: Vec<String> = DataManager::split_path(path)
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let label = segments.pop().ok_or("Path is empty")?;
+    Ok((segments, label))
+}
+
+fn remove_node(nodes: &mut Vec<Node>, id: &str) -> bool {
+    if let Some(index) = nodes.iter().position(|n| n.id == id) {
+        nodes.remove(index);
+        return true;
+    }
+    nodes
+        .iter_mut()
+        .filter_map(|n| n.children.as_mut())
+        .any(|children| remove_node(children, id))
+}
+
+fn print_tree(nodes: &[Node], depth: usize) {
+    let indent = "  ".repeat(depth);
+    for node in nodes {
+        match node.node_type {
+            NodeType::Folder => {
+                println!("{}{}/", indent, node.label);
+                print_tree(node.children.as_deref().unwrap_or_default(), depth + 1);
+            }
+            NodeType::Snippet if node.totp.is_some() => {
+                println!("{}{} (one-time code)", indent, node.label)
+            }
+            NodeType::Snippet if node.is_secret.unwrap_or(false) => {
+                println!("{}{} (secret)", indent, node.label)
+            }
+            NodeType::Snippet => println!("{}{}", indent, node.label),
+            NodeType::Separator => println!("{}---", indent),
+            NodeType::Attachment => println!(
+                "{}{} (attachment, {} bytes)",
+                indent,
+                node.label,
+                node.attachment
+                    .as_ref()
+                    .map_or(0, |attachment| attachment.size)
+            ),
+        }
+    }
+}
+
+fn print_preview(preview: &ImportPreview) {
+    for entry in &preview.entries {
+        let mut path = entry.path.clone();
+        path.push(entry.label.clone());
+        println!(
+            "{}{}{}",
+            path.join("/"),
+            if entry.is_secret { " (secret)" } else { "" },
+            if entry.conflict { " [exists]" } else { "" }
+        );
+    }
+    println!(
+        "{} snippets, {} secrets, {} already existing",
+        preview.snippet_count, preview.secret_count, preview.conflict_count
+    );
+}
+
+fn prompt_password(prompt: &str) -> Result<String, String> {
+    rpassword::prompt_password(prompt).map_err(|e| format!("Failed to read password: {}", e))
+}
+
+/// Reads a new snippet value, hidden for secrets and from stdin otherwise.
+fn read_value(secret: bool) -> Result<String, String> {
+    if secret && std::io::stdin().is_terminal() {
+        return prompt_password("Value: ");
+    }
+    if std::io::stdin().is_terminal() {
+        eprintln!("Enter the value, then press Ctrl-D:");
+    }
+    let mut value = String::new();
+    std::io::stdin()
+        .read_to_string(&mut value)
+        .map_err(|e| e.to_string())?;
+    Ok(value.strip_suffix('\n').unwrap_or(&value).to_string())
+}
+
+/// Puts `value` on the clipboard. On Linux the clipboard is served by this process, so it
+/// stays running until another app takes over the clipboard (or until the clear delay).
+fn copy_to_clipboard(value: &str, clear_after_delay: bool) -> Result<(), String> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+    let clear_at = clear_after_delay.then(|| Instant::now() + CLIPBOARD_CLEAR_DELAY);
+    if clear_at.is_some() {
+        eprintln!(
+            "Clipboard clears in {} seconds",
+            CLIPBOARD_CLEAR_DELAY.as_secs()
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        use arboard::SetExtLinux;
+        let set = clipboard.set();
+        let set = match clear_at {
+            Some(deadline) => set.wait_until(deadline),
+            None => set.wait(),
+        };
+        set.text(value).map_err(|e| e.to_string())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        clipboard.set_text(value).map_err(|e| e.to_string())?;
+        if let Some(deadline) = clear_at {
+            std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+            // Leave the clipboard alone if the user copied something else meanwhile
+            if clipboard.get_text().ok().as_deref() == Some(value) {
+                clipboard.clear().map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,139 +1,439 @@
+use crate::attachments;
+use crate::audit::{self, AuditReport};
 use crate::data_manager::DataManager;
-use crate::models::{Node, NodeType};
-use crate::security::{self, Key, VaultManager, VaultState};
-use aes_gcm::aead::rand_core::RngCore;
-use tauri::{AppHandle, Runtime, State};
+use crate::export::{
+    self,
+    bundle::{self, BundlePayload, BundleSettings},
+    ExportFormat,
+};
+use crate::generator::{self, GeneratedPassword, PasswordPolicy};
+use crate::import::{self, ConflictStrategy, ImportFormat, ImportPreview};
+use crate::keychain::{self, PlatformStore};
+use crate::models::{AppSettings, Node, NodeType, PastValue, VaultInfo, VaultProfile};
+use crate::recovery;
+use crate::security::{self, ClipboardWipe, VaultManager, VaultState};
+use crate::sharing::{self, Recipient};
+use crate::strength::{self, Strength};
+use crate::sync::MergeReport;
+use crate::totp::{self, TotpCode};
+use crate::unlock_guard::{self, UnlockAttempts};
+use crate::vault::{
+    accepts_legacy, composite_password, decrypt_nodes_recursive, encrypt_nodes_recursive,
+    has_encrypted_secrets, has_legacy_ciphertexts, has_plain_secrets, keep_unchanged_ciphertexts,
+    key_check, push_history, read_keyfile, record_history, reencrypt_nodes, reveal_history,
+    try_decrypt_value, try_decrypt_value_bytes, wrap_data_key, CIPHERTEXT_VERSION,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Runtime, State};
 use tauri_plugin_clipboard_manager::ClipboardExt;
 use tauri_plugin_notification::NotificationExt;
 
-const SALT_SIZE: usize = 16;
-const ENCRYPTED_VALUE_SEPARATOR: char = ':';
-
 #[tauri::command]
 pub fn get_data(app: AppHandle, vault_manager: State<'_, VaultManager>) -> Vec<Node> {
-    let data_manager = DataManager::new(&app);
+    let mut data_manager = DataManager::new(&app);
     let mut nodes = data_manager.load_data();
+    data_manager.mark_shown();
 
-    if let VaultState::Unlocked(key) = &*vault_manager.state.lock().unwrap() {
-        decrypt_nodes_recursive(&mut nodes, key);
+    refresh_shared_key(&mut data_manager, &vault_manager);
+    let settings = data_manager.load_settings();
+    if let VaultState::Unlocked(key) = vault_manager.state(data_manager.vault_id()) {
+        match data_manager.verify_integrity() {
+            Ok(Some(report)) => {
+                let _ = app.emit("vault-tampered", report);
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to verify the vault: {}", e),
+        }
+        decrypt_nodes_recursive(&mut nodes, &key, accepts_legacy(&settings));
     }
 
+    crate::sorting::sort_tree(&mut nodes, settings.root_sort_mode);
+
     nodes
 }
 
+/// Wraps the data key for `password` and the keyfile at `keyfile`, if any, in `settings`.
+fn set_master_key(
+    settings: &mut AppSettings,
+    key: &security::Key,
+    password: &str,
+    keyfile: Option<String>,
+) -> Result<(), String> {
+    let content = keyfile
+        .as_deref()
+        .map(|path| read_keyfile(Path::new(path)))
+        .transpose()?;
+    let (wrapped_key, salt) =
+        wrap_data_key(key, &composite_password(password, content.as_deref()))?;
+
+    settings.security.password_hash = None;
+    settings.security.key_check = Some(key_check(key)?);
+    settings.security.wrapped_key = Some(wrapped_key);
+    settings.security.derivation_salt = Some(salt);
+    settings.security.keyfile_path = keyfile;
+    Ok(())
+}
+
+/// Refuses a new master password that is empty or scores below the vault's minimum.
+fn check_master_password(settings: &AppSettings, password: &str) -> Result<(), String> {
+    if password.is_empty() {
+        return Err("The master password can't be empty".into());
+    }
+    let strength = strength::estimate(password);
+    let minimum = settings.security.min_password_score;
+    if strength.score < minimum {
+        let mut message = format!(
+            "The master password is too weak: it scores {} of 4, and at least {} is needed",
+            strength.score, minimum
+        );
+        if let Some(warning) = strength.warning {
+            message = format!("{}. {}", message, warning);
+        }
+        return Err(message);
+    }
+    Ok(())
+}
+
+/// How guessable `password` is, for a strength meter.
+#[tauri::command]
+pub fn estimate_strength(password: String) -> Strength {
+    strength::estimate(&password)
+}
+
+/// Checks the secrets of the active vault for weak, reused and breached values.
+#[tauri::command]
+pub fn audit_vault(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<AuditReport, String> {
+    let data_manager = DataManager::new(&app);
+    let VaultState::Unlocked(key) = vault_manager.state(data_manager.vault_id()) else {
+        return Err("Unlock the vault to audit it".into());
+    };
+    let settings = data_manager.load_settings();
+    let breach_list = settings
+        .security
+        .breach_list_path
+        .as_deref()
+        .filter(|path| !path.trim().is_empty())
+        .map(Path::new);
+    audit::audit(
+        &data_manager.load_data(),
+        &key,
+        accepts_legacy(&settings),
+        breach_list,
+    )
+}
+
+/// Sets the master password, along with a required keyfile if `keyfile` is given.
+/// Returns the recovery key if `with_recovery_key` asks for one; it is shown only now.
 #[tauri::command]
 pub fn init_vault(
     app: AppHandle,
     vault_manager: State<'_, VaultManager>,
     password: String,
-) -> Result<(), String> {
-    let hash = security::hash_password(&password);
-
-    let mut salt_bytes = [0u8; SALT_SIZE];
-    aes_gcm::aead::rand_core::OsRng.fill_bytes(&mut salt_bytes);
-    let salt = hex::encode(salt_bytes);
-
-    let key = security::derive_key_from_password(&password, &salt);
-
-    *vault_manager.state.lock().unwrap() = VaultState::Unlocked(key);
+    keyfile: Option<String>,
+    with_recovery_key: bool,
+) -> Result<Option<String>, String> {
+    let keyfile = keyfile.filter(|path| !path.trim().is_empty());
+    let key = sharing::random_key();
 
     let data_manager = DataManager::new(&app);
     let mut settings = data_manager.load_settings();
+    check_master_password(&settings, &password)?;
+    set_master_key(&mut settings, &key, &password, keyfile)?;
+    let recovery_key = with_recovery_key.then(recovery::generate);
+    settings.security.recovery_wrapped_key = recovery_key
+        .as_deref()
+        .map(|recovery_key| recovery::wrap(&key, recovery_key))
+        .transpose()?;
     settings.security.master_password_enabled = true;
-    settings.security.password_hash = Some(hash);
-    settings.security.derivation_salt = Some(salt);
+    // Nothing is encrypted with the new key yet, let alone in the older format
+    settings.security.ciphertext_version = CIPHERTEXT_VERSION;
+    vault_manager.unlock(data_manager.vault_id(), key, settings.security.lock_timeout);
 
     data_manager
         .save_settings(&settings)
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+
+    refresh_tray(&app);
+    Ok(recovery_key)
 }
 
+/// Unlocks the active vault. `keyfile` is only needed where the configured keyfile has
+/// moved, e.g. to another drive letter.
 #[tauri::command]
 pub fn unlock_vault(
     app: AppHandle,
     vault_manager: State<'_, VaultManager>,
     password: String,
+    keyfile: Option<String>,
 ) -> Result<bool, String> {
     let data_manager = DataManager::new(&app);
     let settings = data_manager.load_settings();
 
-    if let Some(hash) = &settings.security.password_hash {
-        if !security::verify_password(&password, hash) {
-            return Ok(false);
+    let key = if settings.security.shared {
+        data_manager.shared_key().map_err(|e| e.to_string())?
+    } else {
+        let keyfile = keyfile.filter(|path| !path.trim().is_empty());
+        let key = unlock_guard::derive_vault_key(
+            &data_manager,
+            &settings,
+            &password,
+            keyfile.as_deref().map(Path::new),
+            "app",
+        );
+        if key.is_err()
+            && !data_manager
+                .load_settings()
+                .security
+                .master_password_enabled
+        {
+            // Too many failures made the vault wipe its secrets
+            refresh_tray(&app);
+            let _ = app.emit("vault-reset", ());
         }
-    } else if settings.security.master_password_enabled {
-        return Err("Security enabled but no password hash found. Please reset vault.".into());
+        key?
+    };
+    let Some(key) = key else {
+        return Ok(false);
+    };
+
+    vault_manager.unlock(data_manager.vault_id(), key, settings.security.lock_timeout);
+
+    let failures = unlock_guard::take_failures(&data_manager);
+    if !failures.is_empty() {
+        let _ = app.emit("failed-unlocks", failures);
     }
+    refresh_tray(&app);
+    Ok(true)
+}
 
-    let salt = settings
-        .security
-        .derivation_salt
-        .as_deref()
-        .unwrap_or("default-salt");
-    let key = security::derive_key_from_password(&password, salt);
+/// Unlocks the active vault with the key remembered on this device. Returns false if
+/// there is none, e.g. because the secret store entry was deleted, or it is out of date.
+/// Counts against the same attempt limits as passwords.
+#[tauri::command]
+pub fn unlock_with_keychain(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<bool, String> {
+    let data_manager = DataManager::new(&app);
+    let settings = data_manager.load_settings();
+    let key = unlock_guard::recall_vault_key(&data_manager, &settings, &PlatformStore, "keychain");
+    if key.is_err()
+        && !data_manager
+            .load_settings()
+            .security
+            .master_password_enabled
+    {
+        // Too many failures made the vault wipe its secrets
+        refresh_tray(&app);
+        let _ = app.emit("vault-reset", ());
+    }
+    let Some(key) = key? else {
+        return Ok(false);
+    };
 
-    *vault_manager.state.lock().unwrap() = VaultState::Unlocked(key);
+    vault_manager.unlock(data_manager.vault_id(), key, settings.security.lock_timeout);
 
+    let failures = unlock_guard::take_failures(&data_manager);
+    if !failures.is_empty() {
+        let _ = app.emit("failed-unlocks", failures);
+    }
+    refresh_tray(&app);
     Ok(true)
 }
 
+/// Remembers the key of the unlocked active vault in the OS secret store, or forgets it.
+#[tauri::command]
+pub fn set_remember_key(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    remember: bool,
+) -> Result<(), String> {
+    let data_manager = DataManager::new(&app);
+    let mut settings = data_manager.load_settings();
+    let vault = data_manager.vault_id();
+    if remember {
+        if settings.security.shared {
+            return Err("Shared vaults already open with this device's identity".into());
+        }
+        let VaultState::Unlocked(key) = vault_manager.state(vault) else {
+            return Err("Unlock the vault first".into());
+        };
+        settings.security.remembered_key = Some(keychain::remember(&PlatformStore, vault, &key)?);
+    } else {
+        keychain::forget(&PlatformStore, vault)?;
+        settings.security.remembered_key = None;
+    }
+    data_manager
+        .save_settings(&settings)
+        .map_err(|e| e.to_string())
+}
+
+/// Requires `keyfile` along with the master password from now on, or no keyfile if it is
+/// `None`. Only the wrapping of the data key changes, the secrets stay as they are.
 #[tauri::command]
-pub fn lock_vault(vault_manager: State<'_, VaultManager>) -> Result<(), String> {
-    *vault_manager.state.lock().unwrap() = VaultState::Locked;
+pub fn set_keyfile(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    password: String,
+    keyfile: Option<String>,
+) -> Result<(), String> {
+    let mut data_manager = DataManager::new(&app);
+    data_manager.lock().map_err(|e| e.to_string())?;
+    let settings = data_manager.load_settings();
+    if !settings.security.master_password_enabled {
+        return Err("Set a master password first".into());
+    }
+    let key = unlock_guard::derive_vault_key(&data_manager, &settings, &password, None, "app")?
+        .ok_or("Incorrect master password")?;
+
+    // The unlock may have moved an older vault onto a data key
+    let mut settings = data_manager.load_settings();
+    let keyfile = keyfile.filter(|path| !path.trim().is_empty());
+    set_master_key(&mut settings, &key, &password, keyfile)?;
+    data_manager
+        .save_settings(&settings)
+        .map_err(|e| e.to_string())?;
+
+    vault_manager.unlock(data_manager.vault_id(), key, settings.security.lock_timeout);
+    refresh_tray(&app);
     Ok(())
 }
 
-fn encrypt_nodes_recursive(nodes: &mut [Node], key: &Key) -> Result<(), String> {
-    for node in nodes {
-        if matches!(node.node_type, NodeType::Snippet) && node.is_secret.unwrap_or(false) {
-            if let Some(plain_text) = &node.value {
-                if !plain_text.is_empty() {
-                    let (ciphertext, nonce) = security::encrypt(plain_text, key)?;
-                    node.encrypted_value = Some(format!(
-                        "{}{}{}",
-                        nonce, ENCRYPTED_VALUE_SEPARATOR, ciphertext
-                    ));
-                    node.value = None;
-                }
-            }
-        }
-        if let Some(children) = &mut node.children {
-            encrypt_nodes_recursive(children, key)?;
-        }
+/// Replaces the master password, keeping the configured keyfile. Only the wrapping of the
+/// data key changes, the secrets stay as they are.
+#[tauri::command]
+pub fn change_master_password(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    password: String,
+    new_password: String,
+) -> Result<(), String> {
+    let mut data_manager = DataManager::new(&app);
+    data_manager.lock().map_err(|e| e.to_string())?;
+    let settings = data_manager.load_settings();
+    if !settings.security.master_password_enabled {
+        return Err("Set a master password first".into());
     }
+    check_master_password(&settings, &new_password)?;
+    let key = unlock_guard::derive_vault_key(&data_manager, &settings, &password, None, "app")?
+        .ok_or("Incorrect master password")?;
+
+    let mut settings = data_manager.load_settings();
+    let keyfile = settings.security.keyfile_path.clone();
+    set_master_key(&mut settings, &key, &new_password, keyfile)?;
+    data_manager
+        .save_settings(&settings)
+        .map_err(|e| e.to_string())?;
+
+    vault_manager.unlock(data_manager.vault_id(), key, settings.security.lock_timeout);
+    refresh_tray(&app);
     Ok(())
 }
 
-fn decrypt_nodes_recursive(nodes: &mut [Node], key: &Key) {
-    for node in nodes {
-        if matches!(node.node_type, NodeType::Snippet) && node.is_secret.unwrap_or(false) {
-            if let Some(encrypted) = &node.encrypted_value {
-                if let Some(decrypted) = try_decrypt_value(encrypted, key) {
-                    node.value = Some(decrypted);
-                }
-            }
-        }
-        if let Some(children) = &mut node.children {
-            decrypt_nodes_recursive(children, key);
-        }
+/// Makes a new recovery key for the active vault, replacing the old one.
+#[tauri::command]
+pub fn create_recovery_key(app: AppHandle, password: String) -> Result<String, String> {
+    let mut data_manager = DataManager::new(&app);
+    data_manager.lock().map_err(|e| e.to_string())?;
+    let settings = data_manager.load_settings();
+    if !settings.security.master_password_enabled || settings.security.shared {
+        return Err("Only vaults with a master password have a recovery key".into());
     }
+    let key = unlock_guard::derive_vault_key(&data_manager, &settings, &password, None, "app")?
+        .ok_or("Incorrect master password")?;
+
+    let recovery_key = recovery::generate();
+    let mut settings = data_manager.load_settings();
+    settings.security.recovery_wrapped_key = Some(recovery::wrap(&key, &recovery_key)?);
+    data_manager
+        .save_settings(&settings)
+        .map_err(|e| e.to_string())?;
+    Ok(recovery_key)
+}
+
+/// Writes the printable emergency kit for `recovery_key` to `path`.
+#[tauri::command]
+pub fn save_emergency_kit(
+    app: AppHandle,
+    recovery_key: String,
+    path: String,
+) -> Result<(), String> {
+    let data_manager = DataManager::new(&app);
+    let settings = data_manager.load_settings();
+    let kit = recovery::emergency_kit(
+        &settings.vault_name,
+        &data_manager.file_path.to_string_lossy(),
+        &recovery_key,
+    );
+    export::write_file(Path::new(&path), kit.as_bytes(), true)
+}
+
+/// Sets `new_password` on a vault whose master password is lost, unwrapping the data key
+/// with the recovery key. A required keyfile is dropped along with the password.
+#[tauri::command]
+pub fn recover_vault(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    recovery_key: String,
+    new_password: String,
+) -> Result<(), String> {
+    let mut data_manager = DataManager::new(&app);
+    data_manager.lock().map_err(|e| e.to_string())?;
+    let mut settings = data_manager.load_settings();
+    check_master_password(&settings, &new_password)?;
+    let wrapped = settings
+        .security
+        .recovery_wrapped_key
+        .as_deref()
+        .filter(|_| settings.security.master_password_enabled)
+        .ok_or("This vault has no recovery key")?;
+    let key = recovery::unwrap(wrapped, &recovery_key)?.ok_or("Incorrect recovery key")?;
+
+    set_master_key(&mut settings, &key, &new_password, None)?;
+    data_manager
+        .save_settings(&settings)
+        .map_err(|e| e.to_string())?;
+    // Lifts a lockout from the forgotten password
+    let _ = UnlockAttempts::default().save(data_manager.vault_dir());
+
+    vault_manager.unlock(data_manager.vault_id(), key, settings.security.lock_timeout);
+    refresh_tray(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn lock_vault<R: Runtime>(
+    app: AppHandle<R>,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<(), String> {
+    vault_manager.lock(DataManager::new(&app).vault_id());
+    refresh_tray(&app);
+    Ok(())
 }
 
-fn try_decrypt_value(encrypted: &str, key: &Key) -> Option<String> {
-    let parts: Vec<&str> = encrypted.split(ENCRYPTED_VALUE_SEPARATOR).collect();
-    if parts.len() != 2 {
-        return None;
+/// Locks `vaults`, as the tray does for all vaults and auto-lock for expired ones.
+pub(crate) fn lock_vaults<R: Runtime>(
+    app: &AppHandle<R>,
+    vault_manager: &VaultManager,
+    vaults: &[String],
+) {
+    for vault in vaults {
+        vault_manager.lock(vault);
     }
-    security::decrypt(parts[1], parts[0], key).ok()
+    refresh_tray(app);
 }
 
-fn has_plain_secrets(nodes: &[Node]) -> bool {
-    nodes.iter().any(|n| {
-        let is_plain_secret = matches!(n.node_type, NodeType::Snippet)
-            && n.is_secret.unwrap_or(false)
-            && n.value.is_some();
-        is_plain_secret || n.children.as_ref().map_or(false, |c| has_plain_secrets(c))
-    })
+/// Rebuilds the tray menu and icon so lock-dependent parts follow the vault state.
+fn refresh_tray<R: Runtime>(app: &AppHandle<R>) {
+    let nodes = DataManager::new(app).load_data();
+    let _ = crate::tray_generator::TrayGenerator::refresh(app, &nodes);
+    crate::tray_status::update(app);
 }
 
 #[tauri::command]
@@ -142,61 +442,121 @@ pub fn save_data(
     vault_manager: State<'_, VaultManager>,
     mut nodes: Vec<Node>,
 ) -> Result<(), String> {
-    if has_plain_secrets(&nodes) {
-        let state = vault_manager.state.lock().unwrap();
-        match &*state {
-            VaultState::Unlocked(key) => encrypt_nodes_recursive(&mut nodes, key)?,
-            VaultState::Locked => return Err("Vault is locked. Cannot encrypt new secrets.".into()),
+    let mut data_manager = DataManager::new(&app);
+
+    refresh_shared_key(&mut data_manager, &vault_manager);
+    let legacy = accepts_legacy(&data_manager.load_settings());
+    if has_plain_secrets(&nodes) || (legacy && has_legacy_ciphertexts(&nodes)) {
+        match vault_manager.state(data_manager.vault_id()) {
+            VaultState::Unlocked(key) => {
+                // Unchanged secrets keep their ciphertext so sync merges can compare them
+                let previous = DataManager::read_nodes(&data_manager.file_path).unwrap_or_default();
+                keep_unchanged_ciphertexts(&mut nodes, &previous, &key);
+                record_history(&mut nodes, &previous, &key, legacy)?;
+                encrypt_nodes_recursive(&mut nodes, &key, legacy)?
+            }
+            VaultState::Locked if has_plain_secrets(&nodes) => {
+                return Err("Vault is locked. Cannot encrypt new secrets.".into())
+            }
+            // Older ciphertexts are moved over by a save while unlocked
+            VaultState::Locked => {}
         }
     }
 
-    let data_manager = DataManager::new(&app);
-    data_manager.save_data(&nodes).map_err(|e| e.to_string())?;
+    // The nodes come from the window, so they are based on what it last loaded
+    data_manager.expect_shown();
+    if let Err(e) = data_manager.save_data(&nodes) {
+        if crate::data_manager::is_conflict(&e) {
+            let _ = app.emit("data-conflict", ());
+        }
+        return Err(e.to_string());
+    }
 
-    let menu = crate::tray_generator::TrayGenerator::generate_menu(&app, &nodes)
-        .map_err(|e| e.to_string())?;
-    if let Some(tray) = app.tray_by_id("main") {
-        let _ = tray.set_menu(Some(menu));
+    crate::tray_generator::TrayGenerator::refresh(&app, &nodes).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Follows key changes other members made to an unlocked shared vault: picks up a
+/// rotated key, or locks the vault if this device was removed. A key that wasn't rotated
+/// from the current one is refused, and the vault stays on its key, which the changed
+/// file then fails to verify with.
+fn refresh_shared_key(data_manager: &mut DataManager, vault_manager: &VaultManager) {
+    let vault = data_manager.vault_id().to_string();
+    if !vault_manager.is_unlocked(&vault) || !data_manager.load_settings().security.shared {
+        return;
+    }
+    match data_manager.shared_key() {
+        Ok(Some(key)) => vault_manager.replace_key(&vault, key),
+        Ok(None) => vault_manager.lock(&vault),
+        Err(e) => eprintln!("Kept the shared vault key: {}", e),
     }
+    // Saves sign with the key the vault has now
+    data_manager.set_integrity_key(match vault_manager.state(&vault) {
+        VaultState::Unlocked(key) => Some(key),
+        VaultState::Locked => None,
+    });
+}
+
+/// Trusts the changes a `vault-tampered` event reported and signs the vault as it is.
+#[tauri::command]
+pub fn accept_vault_changes(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<(), String> {
+    let mut data_manager = DataManager::new(&app);
+    refresh_shared_key(&mut data_manager, &vault_manager);
+    data_manager.sign().map_err(|e| e.to_string())
+}
 
+/// Undoes the changes a `vault-tampered` event reported by putting back the vault as
+/// last verified on this device.
+#[tauri::command]
+pub fn restore_verified_vault(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<(), String> {
+    let mut data_manager = DataManager::new(&app);
+    refresh_shared_key(&mut data_manager, &vault_manager);
+    data_manager.restore_verified().map_err(|e| e.to_string())?;
+    refresh_tray(&app);
     Ok(())
 }
 
+/// Copies a snippet of `vault`, or of the active vault if it is not given.
 #[tauri::command]
 pub fn copy_snippet<R: Runtime>(
     app: AppHandle<R>,
     vault_manager: State<'_, VaultManager>,
     id: String,
+    vault: Option<String>,
 ) -> Result<(), String> {
-    let data_manager = DataManager::new(&app);
-    let nodes = data_manager.load_data();
+    let data_manager = match &vault {
+        Some(vault) => DataManager::for_vault(&app, vault),
+        None => DataManager::new(&app),
+    };
+    let mut nodes = data_manager.load_data();
 
     let node = DataManager::find_node_by_id(&nodes, &id).ok_or("Snippet not found")?;
-
-    let value = if node.is_secret.unwrap_or(false) {
-        let state = vault_manager.state.lock().unwrap();
-        match &*state {
-            VaultState::Locked => return Err("Vault is Locked".into()),
-            VaultState::Unlocked(key) => {
-                let encrypted = node.encrypted_value.as_ref().ok_or("No encrypted value")?;
-                try_decrypt_value(encrypted, key).ok_or("Failed to decrypt")?
-            }
-        }
-    } else {
-        node.value.clone().unwrap_or_default()
-    };
+    let value = reveal_value(&vault_manager, &data_manager, &node)?;
 
     if value.is_empty() {
         return Err("Empty value".into());
     }
 
     app.clipboard()
-        .write_text(value)
+        .write_text(value.clone())
         .map_err(|e| e.to_string())?;
 
-    *vault_manager.last_used_id.lock().unwrap() = Some(id);
+    *vault_manager.last_used.lock().unwrap() =
+        Some((data_manager.vault_id().to_string(), id.clone()));
+    record_use(&app, &data_manager, &mut nodes, &id);
 
     let settings = data_manager.load_settings();
+    if node.is_secret.unwrap_or(false) && settings.security.clear_clipboard {
+        schedule_clipboard_wipe(&app, &vault_manager, value, None);
+    }
+
     if settings.notifications_enabled {
         let _ = app
             .notification()
@@ -209,85 +569,420 @@ pub fn copy_snippet<R: Runtime>(
     Ok(())
 }
 
-#[tauri::command]
-pub fn get_settings(app: AppHandle) -> crate::models::AppSettings {
-    DataManager::new(&app).load_settings()
-}
-
-#[tauri::command]
-pub fn save_settings(
-    app: AppHandle,
-    vault_manager: State<'_, VaultManager>,
-    settings: crate::models::AppSettings,
-) -> Result<(), String> {
-    if !settings.security.master_password_enabled {
-        *vault_manager.state.lock().unwrap() = VaultState::Locked;
+/// Has the tray clear `value` from the clipboard after a delay, and delete `temp_dir`.
+fn schedule_clipboard_wipe<R: Runtime>(
+    app: &AppHandle<R>,
+    vault_manager: &VaultManager,
+    value: String,
+    temp_dir: Option<PathBuf>,
+) {
+    let previous = vault_manager
+        .clipboard_wipe
+        .lock()
+        .unwrap()
+        .replace(ClipboardWipe {
+            at: Instant::now() + crate::tray_status::CLIPBOARD_CLEAR_DELAY,
+            value,
+            temp_dir,
+        });
+    // The clipboard no longer points at a file copied before
+    if let Some(dir) = previous.and_then(|wipe| wipe.temp_dir) {
+        let _ = fs::remove_dir_all(dir);
     }
+    crate::tray_status::update(app);
+}
 
-    use tauri_plugin_global_shortcut::GlobalShortcutExt;
-    let _ = app.global_shortcut().unregister_all();
-
-    // Register Search Shortcut
-    if !settings.global_search_shortcut.is_empty() {
-        match settings
-            .global_search_shortcut
-            .parse::<tauri_plugin_global_shortcut::Shortcut>()
-        {
-            Ok(shortcut) => {
-                if let Err(e) = app.global_shortcut().register(shortcut) {
-                    eprintln!("Failed to register search shortcut: {}", e);
-                }
-            }
-            Err(e) => {
-                eprintln!(
-                    "Failed to parse search shortcut string '{}': {}",
-                    settings.global_search_shortcut, e
-                );
-            }
-        }
+/// The plain value of a snippet of the vault of `data_manager`, decrypting it when it is
+/// a secret. TOTP snippets give their current code instead.
+pub(crate) fn reveal_value(
+    vault_manager: &VaultManager,
+    data_manager: &DataManager,
+    node: &Node,
+) -> Result<String, String> {
+    if node.node_type == NodeType::Attachment {
+        return Err(format!(
+            "'{}' is an attachment, save it to a file instead",
+            node.label
+        ));
     }
-
-    // Register Create Shortcut
-    if !settings.global_create_shortcut.is_empty() {
-        match settings
-            .global_create_shortcut
-            .parse::<tauri_plugin_global_shortcut::Shortcut>()
-        {
-            Ok(shortcut) => {
-                if let Err(e) = app.global_shortcut().register(shortcut) {
-                    eprintln!("Failed to register create shortcut: {}", e);
-                }
-            }
-            Err(e) => {
-                eprintln!(
-                    "Failed to parse create shortcut string '{}': {}",
-                    settings.global_create_shortcut, e
-                );
+    let value = if !node.is_secret.unwrap_or(false) {
+        node.value.clone().unwrap_or_default()
+    } else {
+        match vault_manager.state(data_manager.vault_id()) {
+            VaultState::Locked => return Err("Vault is Locked".into()),
+            VaultState::Unlocked(key) => {
+                let encrypted = node.encrypted_value.as_ref().ok_or("No encrypted value")?;
+                let legacy = accepts_legacy(&data_manager.load_settings());
+                try_decrypt_value(&node.id, encrypted, &key, legacy).ok_or("Failed to decrypt")?
             }
         }
-    }
+    };
+    totp::resolve(node, value)
+}
 
-    let data_manager = DataManager::new(&app);
-    data_manager
-        .save_settings(&settings)
-        .map_err(|e| format!("Failed to save settings: {}", e))?;
+/// Updates the usage stats the "last used" and "use count" sort modes rely on.
+fn record_use<R: Runtime>(
+    app: &AppHandle<R>,
+    data_manager: &DataManager,
+    nodes: &mut [Node],
+    id: &str,
+) {
+    let Some(node) = DataManager::find_node_by_id_mut(nodes, id) else {
+        return;
+    };
+    node.last_used_at = Some(crate::models::timestamp_millis());
+    node.use_count = Some(node.use_count.unwrap_or(0).saturating_add(1));
 
-    let nodes = data_manager.load_data();
-    if let Ok(menu) = crate::tray_generator::TrayGenerator::generate_menu(&app, &nodes) {
-        if let Some(tray) = app.tray_by_id("main") {
-            let _ = tray.set_menu(Some(menu));
+    if data_manager.record_use(id).is_ok() {
+        if data_manager.is_active() {
+            let _ = crate::tray_generator::TrayGenerator::refresh(app, nodes);
+        } else {
+            refresh_tray(app);
         }
     }
+}
 
-    Ok(())
+/// A random password or passphrase following `policy`.
+#[tauri::command]
+pub fn generate_password(policy: PasswordPolicy) -> Result<GeneratedPassword, String> {
+    generator::generate(&policy)
 }
 
+/// Gives secret snippet `id` of the active vault a new password following `policy`, and
+/// moves the old one to its history. Returns the node for the window to put in the tree
+/// and save.
 #[tauri::command]
-pub fn is_vault_unlocked(vault_manager: State<'_, VaultManager>) -> bool {
-    matches!(
-        *vault_manager.state.lock().unwrap(),
+pub fn regenerate_secret(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    id: String,
+    policy: PasswordPolicy,
+) -> Result<Node, String> {
+    let data_manager = DataManager::new(&app);
+    if !matches!(
+        vault_manager.state(data_manager.vault_id()),
         VaultState::Unlocked(_)
-    )
+    ) {
+        return Err("Unlock the vault to regenerate secrets".into());
+    }
+    let nodes = data_manager.load_data();
+    let mut node = DataManager::find_node_by_id(&nodes, &id).ok_or("Snippet not found")?;
+    if node.node_type != NodeType::Snippet || !node.is_secret.unwrap_or(false) {
+        return Err(format!("'{}' is not a secret snippet", node.label));
+    }
+    if node.totp.is_some() {
+        return Err("One-time codes can't be regenerated".into());
+    }
+    push_history(&mut node);
+    node.value = Some(generator::generate(&policy)?.password);
+    Ok(node)
+}
+
+/// The earlier values of secret snippet `id` of the active vault, oldest first.
+#[tauri::command]
+pub fn get_secret_history(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    id: String,
+) -> Result<Vec<PastValue>, String> {
+    let data_manager = DataManager::new(&app);
+    let VaultState::Unlocked(key) = vault_manager.state(data_manager.vault_id()) else {
+        return Err("Vault is Locked".into());
+    };
+    let nodes = data_manager.load_data();
+    let node = DataManager::find_node_by_id(&nodes, &id).ok_or("Snippet not found")?;
+    reveal_history(&node, &key, accepts_legacy(&data_manager.load_settings()))
+}
+
+/// A TOTP snippet under `parent_id` for an `otpauth://` URI or base32 secret. Returns the
+/// node for the window to add to the tree and save.
+#[tauri::command]
+pub fn add_totp(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    uri: String,
+    parent_id: Option<String>,
+) -> Result<Node, String> {
+    let data_manager = DataManager::new(&app);
+    if !matches!(
+        vault_manager.state(data_manager.vault_id()),
+        VaultState::Unlocked(_)
+    ) {
+        return Err("Unlock the vault to add one-time codes".into());
+    }
+    totp::new_snippet(&uri, parent_id, None)
+}
+
+/// The current code of TOTP snippet `id` of the active vault.
+#[tauri::command]
+pub fn get_totp_code(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    id: String,
+) -> Result<TotpCode, String> {
+    let data_manager = DataManager::new(&app);
+    let nodes = data_manager.load_data();
+    let node = DataManager::find_node_by_id(&nodes, &id).ok_or("Snippet not found")?;
+    let totp = node.totp.ok_or("Not a one-time code")?;
+    Ok(TotpCode {
+        code: reveal_value(&vault_manager, &data_manager, &node)?,
+        remaining: totp::remaining_secs(totp.period, totp::unix_secs())?,
+    })
+}
+
+/// Encrypts the file at `path` into a new attachment under `parent_id`. Returns the node
+/// for the window to add to the tree and save.
+#[tauri::command]
+pub fn add_attachment(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    path: String,
+    parent_id: Option<String>,
+) -> Result<Node, String> {
+    let data_manager = DataManager::new(&app);
+    let VaultState::Unlocked(key) = vault_manager.state(data_manager.vault_id()) else {
+        return Err("Unlock the vault to add attachments".into());
+    };
+    attachments::attach(&data_manager, Path::new(&path), parent_id, &key)
+}
+
+/// The attachment `id` of the active vault and its decrypted file.
+fn read_attachment(
+    app: &AppHandle,
+    vault_manager: &VaultManager,
+    id: &str,
+) -> Result<(Node, Vec<u8>), String> {
+    let data_manager = DataManager::new(app);
+    let VaultState::Unlocked(key) = vault_manager.state(data_manager.vault_id()) else {
+        return Err("Vault is Locked".into());
+    };
+    let nodes = data_manager.load_data();
+    let node = DataManager::find_node_by_id(&nodes, id).ok_or("Attachment not found")?;
+    let content = attachments::read(&data_manager, &node, &key)?;
+    Ok((node, content))
+}
+
+/// Saves the file of attachment `id` to `path`, readable only by the owner.
+#[tauri::command]
+pub fn save_attachment(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    id: String,
+    path: String,
+) -> Result<(), String> {
+    let (_, content) = read_attachment(&app, &vault_manager, &id)?;
+    export::write_file(Path::new(&path), &content, true)
+}
+
+/// Copies the file of attachment `id` as base64.
+#[tauri::command]
+pub fn copy_attachment(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    id: String,
+) -> Result<(), String> {
+    let (_, content) = read_attachment(&app, &vault_manager, &id)?;
+    let value = BASE64.encode(content);
+    app.clipboard()
+        .write_text(value.clone())
+        .map_err(|e| e.to_string())?;
+    if DataManager::new(&app)
+        .load_settings()
+        .security
+        .clear_clipboard
+    {
+        schedule_clipboard_wipe(&app, &vault_manager, value, None);
+    }
+    Ok(())
+}
+
+/// Leads the names of the private temporary folders attachments copied by path are
+/// decrypted to, one per copy.
+const DECRYPTED_DIR_PREFIX: &str = "sklad-decrypted-";
+
+/// Deletes the attachments copied by path that are still around, e.g. from before a crash.
+pub(crate) fn remove_decrypted_files() {
+    let temp_dir = std::env::temp_dir();
+    let Ok(entries) = fs::read_dir(&temp_dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with(DECRYPTED_DIR_PREFIX)
+        {
+            // Fails on other users' folders, which are theirs to delete
+            let _ = fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+/// Decrypts attachment `id` into a private temporary folder and copies the path of the
+/// file as text, for pasting into a file dialog; the clipboard has no file format that
+/// works on every platform. The file is deleted when the clipboard is cleared, or at the
+/// latest when the app quits. Returns the path.
+#[tauri::command]
+pub fn copy_attachment_path(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    id: String,
+) -> Result<String, String> {
+    let (node, content) = read_attachment(&app, &vault_manager, &id)?;
+    let file_name = node
+        .attachment
+        .as_ref()
+        .and_then(|attachment| Path::new(&attachment.file_name).file_name())
+        .map(|name| name.to_os_string())
+        .unwrap_or_else(|| "attachment".into());
+
+    let dir =
+        std::env::temp_dir().join(format!("{}{}", DECRYPTED_DIR_PREFIX, uuid::Uuid::new_v4()));
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder
+        .create(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = dir.join(file_name);
+    export::write_file(&path, &content, true)?;
+
+    let value = path.to_string_lossy().into_owned();
+    if let Err(e) = app.clipboard().write_text(value.clone()) {
+        let _ = fs::remove_dir_all(&dir);
+        return Err(e.to_string());
+    }
+    // The decrypted copy goes away even if clearing the clipboard is turned off
+    schedule_clipboard_wipe(&app, &vault_manager, value.clone(), Some(dir));
+    Ok(value)
+}
+
+#[tauri::command]
+pub fn get_settings(app: AppHandle) -> crate::models::AppSettings {
+    DataManager::new(&app).load_settings()
+}
+
+#[tauri::command]
+pub fn save_settings(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    settings: crate::models::AppSettings,
+) -> Result<(), String> {
+    let mut data_manager = DataManager::new(&app);
+    // Key material in the window's copy may be out of date, so only its preferences count
+    data_manager.lock().map_err(|e| e.to_string())?;
+    let settings = {
+        let mut current = data_manager.load_settings();
+        current.set_preferences(&settings);
+        current
+    };
+    let vault_moved = move_vault(&data_manager, &settings)?;
+    data_manager
+        .save_settings(&settings)
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+    data_manager.unlock();
+
+    if !settings.security.master_password_enabled {
+        vault_manager.lock(data_manager.vault_id());
+    }
+
+    use tauri_plugin_global_shortcut::GlobalShortcutExt;
+    let _ = app.global_shortcut().unregister_all();
+
+    // Register Search Shortcut
+    if !settings.global_search_shortcut.is_empty() {
+        match settings
+            .global_search_shortcut
+            .parse::<tauri_plugin_global_shortcut::Shortcut>()
+        {
+            Ok(shortcut) => {
+                if let Err(e) = app.global_shortcut().register(shortcut) {
+                    eprintln!("Failed to register search shortcut: {}", e);
+                }
+            }
+            Err(e) => {
+                eprintln!(
+                    "Failed to parse search shortcut string '{}': {}",
+                    settings.global_search_shortcut, e
+                );
+            }
+        }
+    }
+
+    // Register Create Shortcut
+    if !settings.global_create_shortcut.is_empty() {
+        match settings
+            .global_create_shortcut
+            .parse::<tauri_plugin_global_shortcut::Shortcut>()
+        {
+            Ok(shortcut) => {
+                if let Err(e) = app.global_shortcut().register(shortcut) {
+                    eprintln!("Failed to register create shortcut: {}", e);
+                }
+            }
+            Err(e) => {
+                eprintln!(
+                    "Failed to parse create shortcut string '{}': {}",
+                    settings.global_create_shortcut, e
+                );
+            }
+        }
+    }
+
+    if settings.git_enabled {
+        // With a fresh `DataManager`, in case the vault just moved
+        if let Err(e) = DataManager::new(&app).enable_git(settings.git_remote.as_deref()) {
+            let mut settings = settings.clone();
+            settings.git_enabled = false;
+            let _ = data_manager.save_settings(&settings);
+            return Err(format!("Failed to set up git: {}", e));
+        }
+    }
+
+    crate::ipc::service::apply_settings(&app, &settings);
+    refresh_tray(&app);
+    if vault_moved {
+        let _ = app.emit("data-updated", ());
+    }
+
+    Ok(())
+}
+
+/// Prepares the folder chosen in `settings.vault_path`. The current vault is copied there
+/// unless the folder already has one, e.g. synced from another machine.
+fn move_vault(
+    data_manager: &DataManager,
+    settings: &crate::models::AppSettings,
+) -> Result<bool, String> {
+    let dir = match settings
+        .vault_path
+        .as_deref()
+        .filter(|path| !path.is_empty())
+    {
+        Some(path) => std::path::PathBuf::from(path),
+        None => data_manager.vault_dir().to_path_buf(),
+    };
+    let target = dir.join("sklad.json");
+    if target == data_manager.file_path {
+        return Ok(false);
+    }
+
+    if !dir.is_dir() {
+        return Err(format!("Folder '{}' does not exist", dir.display()));
+    }
+    if !target.exists() && data_manager.file_path.exists() {
+        std::fs::copy(&data_manager.file_path, &target)
+            .map_err(|e| format!("Failed to copy the vault: {}", e))?;
+    }
+    Ok(true)
+}
+
+#[tauri::command]
+pub fn is_vault_unlocked(app: AppHandle, vault_manager: State<'_, VaultManager>) -> bool {
+    vault_manager.is_unlocked(DataManager::new(&app).vault_id())
 }
 
 #[tauri::command]
@@ -309,26 +1004,516 @@ pub fn open_snippets_path(app: AppHandle) -> Result<(), String> {
 
 #[tauri::command]
 pub fn reset_vault(app: AppHandle) -> Result<(Vec<Node>, crate::models::AppSettings), String> {
+    let (nodes, settings) = crate::vault::reset_secrets(&DataManager::new(&app))?;
+
+    refresh_tray(&app);
+    Ok((nodes, settings))
+}
+
+/// Imports snippets from another tool or from a `.sklad` bundle (which needs `passphrase`).
+/// With `dry_run` nothing is saved and the returned preview lists what would be added and
+/// which entries already exist. Importing a bundle also applies the settings it carries.
+#[tauri::command]
+pub fn import_data(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    path: String,
+    format: Option<ImportFormat>,
+    conflict_strategy: Option<ConflictStrategy>,
+    passphrase: Option<String>,
+    dry_run: bool,
+) -> Result<ImportPreview, String> {
+    let path = Path::new(&path);
+    let strategy = conflict_strategy.unwrap_or_default();
+    let is_bundle = match format {
+        Some(format) => format == ImportFormat::SkladBundle,
+        None => bundle::is_bundle(path),
+    };
+
     let data_manager = DataManager::new(&app);
     let mut nodes = data_manager.load_data();
-    let mut settings = data_manager.load_settings();
+    let mut bundle_settings = None;
+
+    let preview = if is_bundle {
+        let passphrase = passphrase.ok_or("A passphrase is required to open a Sklad bundle")?;
+        let content =
+            std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let payload = bundle::open(&content, &passphrase)?;
+        totp::check_new_snippets(&payload.nodes)?;
+        bundle_settings = Some(payload.settings);
+        import::sklad_bundle::merge(&mut nodes, payload.nodes, strategy)
+    } else {
+        let (format, items) = import::parse(path, format)?;
+        import::merge(&mut nodes, format, &items, strategy)
+    };
+
+    if dry_run {
+        return Ok(preview);
+    }
 
-    remove_secrets_recursive(&mut nodes);
-    settings.security.master_password_enabled = false;
+    let mut settings = data_manager.load_settings();
+    if has_plain_secrets(&nodes) {
+        if !settings.security.master_password_enabled {
+            return Err("Set a master password before importing secrets.".into());
+        }
+        match vault_manager.state(data_manager.vault_id()) {
+            VaultState::Unlocked(key) => {
+                encrypt_nodes_recursive(&mut nodes, &key, accepts_legacy(&settings))?
+            }
+            VaultState::Locked => return Err("Vault is locked. Cannot encrypt new secrets.".into()),
+        }
+    }
 
     data_manager.save_data(&nodes).map_err(|e| e.to_string())?;
+    if let Some(bundle_settings) = bundle_settings {
+        bundle_settings.apply_to(&mut settings);
+        data_manager
+            .save_settings(&settings)
+            .map_err(|e| e.to_string())?;
+    }
+    crate::tray_generator::TrayGenerator::refresh(&app, &nodes).map_err(|e| e.to_string())?;
+
+    Ok(preview)
+}
+
+/// Exports the vault to `path`. A `.sklad` bundle always carries the secrets and is
+/// encrypted with `passphrase`. The plain formats leave secrets out unless
+/// `include_secrets` is set, which requires the master password to be entered again.
+#[tauri::command]
+pub fn export_data(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    path: String,
+    format: ExportFormat,
+    passphrase: Option<String>,
+    include_secrets: bool,
+    master_password: Option<String>,
+) -> Result<(), String> {
+    let data_manager = DataManager::new(&app);
+    let mut nodes = data_manager.load_data();
+    let settings = data_manager.load_settings();
+    let is_bundle = format == ExportFormat::SkladBundle;
+    let with_secrets = is_bundle || include_secrets;
+
+    if with_secrets && has_encrypted_secrets(&nodes) {
+        let key = if is_bundle {
+            match vault_manager.state(data_manager.vault_id()) {
+                VaultState::Unlocked(key) => key,
+                VaultState::Locked => return Err("Unlock the vault to export secrets.".into()),
+            }
+        } else {
+            let password = master_password
+                .ok_or("Enter the master password to export secrets in plain text")?;
+            unlock_guard::derive_vault_key(&data_manager, &settings, &password, None, "export")?
+                .ok_or("Incorrect master password")?
+        };
+        decrypt_nodes_recursive(&mut nodes, &key, accepts_legacy(&settings));
+    }
+
+    let nodes = export::prepare_nodes(&nodes, with_secrets)?;
+    let path = Path::new(&path);
+
+    if is_bundle {
+        let passphrase = passphrase
+            .filter(|p| !p.is_empty())
+            .ok_or("A passphrase is required for an encrypted bundle")?;
+        let payload = BundlePayload {
+            nodes,
+            settings: BundleSettings::from_settings(&settings),
+            exported_at: crate::models::timestamp_millis(),
+        };
+        export::write_file(path, &bundle::seal(&payload, &passphrase)?, false)
+    } else {
+        let content = export::render(format, &nodes)?;
+        export::write_file(path, &content, include_secrets)
+    }
+}
+
+/// Answers a prompt from the `ipc-approval-request` event.
+#[tauri::command]
+pub fn respond_ipc_approval(
+    app: AppHandle,
+    request_id: u32,
+    approved: bool,
+    remember: bool,
+) -> Result<(), String> {
+    crate::ipc::service::respond(&app, request_id, approved, remember)
+}
+
+/// Socket path (or pipe name) scripts connect to, if the socket is enabled.
+#[tauri::command]
+pub fn get_ipc_endpoint(app: AppHandle) -> Option<String> {
+    crate::ipc::service::endpoint(&app)
+}
+
+/// Compares two ciphertexts of a node in the vault of `data_manager` by their plain
+/// text, which only works while unlocked.
+fn secret_comparer(
+    vault_manager: &VaultManager,
+    data_manager: &DataManager,
+) -> impl Fn(&str, &str, &str) -> bool {
+    let key = match vault_manager.state(data_manager.vault_id()) {
+        VaultState::Unlocked(key) => Some(key),
+        VaultState::Locked => None,
+    };
+    let legacy = accepts_legacy(&data_manager.load_settings());
+    move |id: &str, a: &str, b: &str| {
+        key.as_ref().is_some_and(|key| {
+            let a = try_decrypt_value_bytes(id, a, key, legacy);
+            a.is_some() && a == try_decrypt_value_bytes(id, b, key, legacy)
+        })
+    }
+}
+
+/// Conflicting copies of the vault left by a sync tool, by file name.
+#[tauri::command]
+pub fn get_sync_conflicts(app: AppHandle) -> Vec<String> {
+    DataManager::new(&app)
+        .conflict_files()
+        .iter()
+        .filter_map(|path| path.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .collect()
+}
+
+/// Merges the conflicting copies into the vault. Secrets that differ only by ciphertext
+/// are recognised as equal while the vault is unlocked.
+#[tauri::command]
+pub fn merge_sync_conflicts(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<MergeReport, String> {
+    let mut data_manager = DataManager::new(&app);
+    let same_secret = secret_comparer(&vault_manager, &data_manager);
+    let report = data_manager
+        .merge_conflict_files(&same_secret)
+        .map_err(|e| format!("Failed to merge: {}", e))?;
+
+    if !report.merged_files.is_empty() {
+        refresh_tray(&app);
+        let _ = app.emit("data-updated", ());
+    }
+    Ok(report)
+}
+
+/// Merges the vault history from the git remote into the vault.
+#[tauri::command]
+pub fn git_pull(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<MergeReport, String> {
+    let mut data_manager = DataManager::new(&app);
+    let same_secret = secret_comparer(&vault_manager, &data_manager);
+    let report = data_manager
+        .git_pull(&same_secret)
+        .map_err(|e| format!("Failed to pull: {}", e))?;
+
+    if !report.merged_files.is_empty() {
+        refresh_tray(&app);
+        let _ = app.emit("data-updated", ());
+    }
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn git_push(app: AppHandle) -> Result<(), String> {
+    DataManager::new(&app)
+        .git_push()
+        .map_err(|e| format!("Failed to push: {}", e))
+}
+
+/// All vaults, the active one first.
+#[tauri::command]
+pub fn list_vaults(app: AppHandle, vault_manager: State<'_, VaultManager>) -> Vec<VaultInfo> {
+    let settings = DataManager::new(&app).load_settings();
+    let mut vaults = vec![VaultInfo {
+        id: settings.vault_id.clone(),
+        name: settings.vault_name.clone(),
+        active: true,
+        mounted: false,
+        master_password_enabled: settings.security.master_password_enabled,
+        unlocked: vault_manager.is_unlocked(&settings.vault_id),
+    }];
+    vaults.extend(settings.vaults.iter().map(|vault| VaultInfo {
+        id: vault.id.clone(),
+        name: vault.name.clone(),
+        active: false,
+        mounted: settings.mounted_vaults.contains(&vault.id),
+        master_password_enabled: vault.security.master_password_enabled,
+        unlocked: vault_manager.is_unlocked(&vault.id),
+    }));
+    vaults
+}
+
+/// Adds an empty vault without a master password and returns its id.
+#[tauri::command]
+pub fn create_vault(app: AppHandle, name: String) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("The vault needs a name".into());
+    }
+
+    let data_manager = DataManager::new(&app);
+    let mut settings = data_manager.load_settings();
+    let profile = VaultProfile::new(name);
+    let id = profile.id.clone();
+    settings.vaults.push(profile);
     data_manager
         .save_settings(&settings)
         .map_err(|e| e.to_string())?;
 
-    Ok((nodes, settings))
+    refresh_tray(&app);
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn rename_vault(app: AppHandle, id: String, name: String) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("The vault needs a name".into());
+    }
+
+    let data_manager = DataManager::new(&app);
+    let mut settings = data_manager.load_settings();
+    if settings.vault_id == id {
+        settings.vault_name = name.to_string();
+    } else {
+        let vault = settings
+            .vaults
+            .iter_mut()
+            .find(|vault| vault.id == id)
+            .ok_or("Vault not found")?;
+        vault.name = name.to_string();
+    }
+    data_manager
+        .save_settings(&settings)
+        .map_err(|e| e.to_string())?;
+
+    refresh_tray(&app);
+    Ok(())
 }
 
-fn remove_secrets_recursive(nodes: &mut Vec<Node>) {
-    nodes.retain(|node| !node.is_secret.unwrap_or(false));
-    for node in nodes.iter_mut() {
-        if let Some(children) = &mut node.children {
-            remove_secrets_recursive(children);
+/// Removes a vault other than the active one from the list. Its files stay on disk.
+#[tauri::command]
+pub fn remove_vault(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    id: String,
+) -> Result<(), String> {
+    let data_manager = DataManager::new(&app);
+    let mut settings = data_manager.load_settings();
+    if settings.vault_id == id {
+        return Err("Switch to another vault before removing this one".into());
+    }
+    let count = settings.vaults.len();
+    settings.vaults.retain(|vault| vault.id != id);
+    if settings.vaults.len() == count {
+        return Err("Vault not found".into());
+    }
+    settings.mounted_vaults.retain(|vault| *vault != id);
+    data_manager
+        .save_settings(&settings)
+        .map_err(|e| e.to_string())?;
+
+    let _ = keychain::forget(&PlatformStore, &id);
+    vault_manager.lock(&id);
+    refresh_tray(&app);
+    Ok(())
+}
+
+/// Makes `id` the active vault. Other vaults keep their lock state.
+#[tauri::command]
+pub fn switch_vault<R: Runtime>(app: AppHandle<R>, id: String) -> Result<(), String> {
+    DataManager::new(&app)
+        .switch_vault(&id)
+        .map_err(|e| e.to_string())?;
+
+    refresh_tray(&app);
+    let _ = app.emit("vault-switched", id);
+    Ok(())
+}
+
+/// Shows or hides the snippets of vault `id` in the tray next to the active vault's.
+#[tauri::command]
+pub fn set_vault_mounted(app: AppHandle, id: String, mounted: bool) -> Result<(), String> {
+    let data_manager = DataManager::new(&app);
+    let mut settings = data_manager.load_settings();
+    settings.mounted_vaults.retain(|vault| *vault != id);
+    if mounted {
+        settings.mounted_vaults.push(id);
+    }
+    data_manager
+        .save_settings(&settings)
+        .map_err(|e| e.to_string())?;
+
+    refresh_tray(&app);
+    Ok(())
+}
+
+/// This device's public key, which other members add to share a vault with it.
+#[tauri::command]
+pub fn get_identity(app: AppHandle) -> Result<String, String> {
+    let identity = DataManager::new(&app)
+        .identity()
+        .map_err(|e| format!("Failed to load the identity: {}", e))?;
+    Ok(identity.public_key())
+}
+
+/// Adds the shared vault in `folder`, e.g. one synced from a team drive, and returns its
+/// id. An empty folder gets a new shared vault with this device as its only member.
+#[tauri::command]
+pub fn add_shared_vault(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    name: String,
+    member_name: String,
+    folder: String,
+) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("The vault needs a name".into());
+    }
+    let dir = Path::new(&folder);
+    if !dir.is_dir() {
+        return Err(format!("Folder '{}' does not exist", dir.display()));
+    }
+
+    let data_manager = DataManager::new(&app);
+    let identity = data_manager
+        .identity()
+        .map_err(|e| format!("Failed to load the identity: {}", e))?;
+    let existing = dir.join("sklad.json");
+    let new_vault = if existing.exists() {
+        let recipients = DataManager::read_recipients(&existing).map_err(|e| e.to_string())?;
+        if recipients.is_empty() {
+            return Err("The folder holds a vault that isn't shared".into());
         }
+        None
+    } else {
+        if member_name.trim().is_empty() {
+            return Err("Enter the name you are listed under as a member".into());
+        }
+        let key = sharing::random_key();
+        Some(sharing::wrap(&key, &member_name, &identity.public_key())?)
+    };
+
+    let mut settings = data_manager.load_settings();
+    let mut profile = VaultProfile::new(name);
+    profile.vault_path = Some(folder.clone());
+    profile.security.master_password_enabled = true;
+    profile.security.shared = true;
+    if new_vault.is_none() {
+        // Joined as it is, so it may hold secrets from before the current format
+        profile.security.ciphertext_version = 0;
+    }
+    let id = profile.id.clone();
+    let lock_timeout = profile.security.lock_timeout;
+    settings.vaults.push(profile);
+    data_manager
+        .save_settings(&settings)
+        .map_err(|e| e.to_string())?;
+
+    let shared = DataManager::with_vault(data_manager.data_dir().to_path_buf(), &id);
+    if let Some(recipient) = new_vault {
+        shared
+            .save_shared(&[], &[recipient])
+            .map_err(|e| format!("Failed to create the vault: {}", e))?;
+    }
+    // Also notes the key, which later rotations have to lead on from
+    if let Some(key) = shared.shared_key().map_err(|e| e.to_string())? {
+        vault_manager.unlock(&id, key, lock_timeout);
+    }
+
+    refresh_tray(&app);
+    Ok(id)
+}
+
+/// Members of the active vault, if it is shared.
+#[tauri::command]
+pub fn list_recipients(app: AppHandle) -> Result<Vec<Recipient>, String> {
+    DataManager::new(&app)
+        .recipients()
+        .map_err(|e| e.to_string())
+}
+
+/// The locked data manager and key of the active vault, for changing its members.
+fn unlocked_shared_vault(
+    app: &AppHandle,
+    vault_manager: &VaultManager,
+) -> Result<(DataManager, security::Key), String> {
+    let mut data_manager = DataManager::new(app);
+    if !data_manager.load_settings().security.shared {
+        return Err("The vault is not shared".into());
+    }
+    refresh_shared_key(&mut data_manager, vault_manager);
+    let VaultState::Unlocked(key) = vault_manager.state(data_manager.vault_id()) else {
+        return Err("Unlock the vault first".into());
+    };
+    data_manager.lock().map_err(|e| e.to_string())?;
+    Ok((data_manager, key))
+}
+
+/// Shares the active vault with the owner of `public_key` by wrapping its key for them.
+#[tauri::command]
+pub fn add_recipient(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    name: String,
+    public_key: String,
+) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("The member needs a name".into());
+    }
+    let (data_manager, key) = unlocked_shared_vault(&app, &vault_manager)?;
+    let recipient = sharing::wrap(&key, &name, &public_key)?;
+    let mut recipients = data_manager.recipients().map_err(|e| e.to_string())?;
+    if recipients
+        .iter()
+        .any(|member| member.public_key == recipient.public_key)
+    {
+        return Err("This key is already a member".into());
+    }
+    recipients.push(recipient);
+
+    let nodes = data_manager.load_data();
+    data_manager
+        .save_shared(&nodes, &recipients)
+        .map_err(|e| e.to_string())
+}
+
+/// Removes a member from the active vault. The vault gets a new key, wrapped for the
+/// remaining members only, so a copy of the old one no longer opens later saves.
+#[tauri::command]
+pub fn remove_recipient(
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+    public_key: String,
+) -> Result<(), String> {
+    let (mut data_manager, key) = unlocked_shared_vault(&app, &vault_manager)?;
+    let mut recipients = data_manager.recipients().map_err(|e| e.to_string())?;
+    let count = recipients.len();
+    recipients.retain(|member| member.public_key != public_key);
+    if recipients.len() == count {
+        return Err("Member not found".into());
+    }
+    if recipients.is_empty() {
+        return Err("A shared vault needs at least one member".into());
     }
+
+    let new_key = sharing::random_key();
+    let mut nodes = data_manager.load_data();
+    let legacy = accepts_legacy(&data_manager.load_settings());
+    reencrypt_nodes(&mut nodes, &key, &new_key, legacy)?;
+    let recipients = recipients
+        .iter()
+        .map(|member| sharing::wrap(&new_key, &member.name, &member.public_key))
+        .collect::<Result<Vec<_>, _>>()?;
+    data_manager.set_integrity_key(Some(new_key));
+    data_manager
+        .rotate_shared(&nodes, &recipients, sharing::rotation(&key, &new_key)?)
+        .map_err(|e| e.to_string())?;
+
+    refresh_shared_key(&mut data_manager, &vault_manager);
+    refresh_tray(&app);
+    Ok(())
 }
@@ -1,25 +1,409 @@
-use crate::models::Node;
-use std::fs;
-use std::path::PathBuf;
+use crate::git::{self, GitRepo, Relation};
+use crate::integrity::{self, TamperReport};
+use crate::models::{AppSettings, Node, NodeType, DEFAULT_VAULT_ID};
+use crate::security::{Key, VaultManager, VaultState};
+use crate::sharing::{self, Identity, Recipient};
+use crate::sync::{self, MergeReport};
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, OnceCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use tauri::{AppHandle, Manager, Runtime};
 
+/// Must match `identifier` in tauri.conf.json, which names the app data dir.
+const APP_IDENTIFIER: &str = "sklad";
+const LOCK_FILE_NAME: &str = "sklad.lock";
+const VAULT_FILE_NAME: &str = "sklad.json";
+const PLAIN_SECRETS_ERROR: &str =
+    "Secrets must be encrypted before they can be committed. Set a master password first.";
+const UNPROVEN_KEY_ERROR: &str =
+    "The key of the shared vault was replaced by someone who didn't have the one before. \
+     Check the members, then have one of them share the vault again.";
+const DEVICE_ID_FILE_NAME: &str = "device-id";
+/// The vault as last received from another device, see `record_ancestor`.
+const ANCESTOR_FILE_NAME: &str = "sklad.ancestor.json";
+/// Holds a folder per vault other than the default one.
+const VAULTS_DIR_NAME: &str = "vaults";
+/// Usage stats of the vault's snippets on this device, see `record_use`.
+const USAGE_FILE_NAME: &str = "usage.json";
+
+/// One saved state of sklad.json. The generation is stored in the file and bumped on
+/// every save; the hash also catches tools that rewrite the file without bumping it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Revision {
+    pub generation: u64,
+    /// Only comparable within one process, `DefaultHasher` is not stable across builds.
+    hash: u64,
+}
+
+impl Revision {
+    fn of(content: &[u8]) -> Self {
+        #[derive(Deserialize)]
+        struct Generation {
+            generation: u64,
+        }
+
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        Self {
+            // Files written before generations existed count as generation 0
+            generation: serde_json::from_slice::<Generation>(content)
+                .map(|g| g.generation)
+                .unwrap_or(0),
+            hash: hasher.finish(),
+        }
+    }
+}
+
+/// Returned (inside an `io::Error`) when sklad.json changed on disk since it was loaded.
+#[derive(Debug)]
+pub struct ConflictError;
+
+impl fmt::Display for ConflictError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The vault was changed by another program since it was loaded. Reload and try again."
+        )
+    }
+}
+
+impl std::error::Error for ConflictError {}
+
+pub fn is_conflict(error: &io::Error) -> bool {
+    error
+        .get_ref()
+        .is_some_and(|inner| inner.is::<ConflictError>())
+}
+
+/// Layout of sklad.json. Older versions stored the bare node array.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DataFile {
+    Versioned {
+        nodes: Vec<Node>,
+        #[serde(default)]
+        device: Option<String>,
+    },
+    Legacy(Vec<Node>),
+}
+
+#[derive(Serialize)]
+struct DataFileRef<'a> {
+    generation: u64,
+    device: &'a str,
+    /// Members of a shared vault, each with the vault key wrapped for them.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    recipients: &'a [Recipient],
+    /// `sharing::rotation` of each key the shared vault had, oldest first.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    rotations: &'a [String],
+    /// `integrity::mac` of the nodes, written while the vault is unlocked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mac: Option<&'a str>,
+    nodes: &'a [Node],
+}
+
+/// Writes one node per line with children indented below their folder, so that a change
+/// shows up as a one-line diff in git. Keys keep the field order of `Node`, children last.
+fn serialize_data(
+    generation: u64,
+    device: &str,
+    recipients: &[Recipient],
+    rotations: &[String],
+    mac: Option<&str>,
+    nodes: &mut [Node],
+) -> serde_json::Result<String> {
+    let header = serde_json::to_string(&DataFileRef {
+        generation,
+        device,
+        recipients,
+        rotations,
+        mac,
+        nodes: &[],
+    })?;
+    let mut out = header.strip_suffix("]}").unwrap_or(&header).to_string();
+    write_node_lines(&mut out, nodes, 0)?;
+    out.push_str("\n]}\n");
+    Ok(out)
+}
+
+fn write_node_lines(out: &mut String, nodes: &mut [Node], depth: usize) -> serde_json::Result<()> {
+    for (i, node) in nodes.iter_mut().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push('\n');
+        out.push_str(&"  ".repeat(depth));
+
+        let children = node.children.take();
+        let line = serde_json::to_string(node);
+        node.children = children;
+        let line = line?;
+
+        match &mut node.children {
+            Some(children) => {
+                out.push_str(&line[..line.len() - 1]);
+                out.push_str(",\"children\":[");
+                if !children.is_empty() {
+                    write_node_lines(out, children, depth + 1)?;
+                    out.push('\n');
+                    out.push_str(&"  ".repeat(depth));
+                }
+                out.push_str("]}");
+            }
+            None => out.push_str(&line),
+        }
+    }
+    Ok(())
+}
+
+/// How often and when a snippet was last copied on this device.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct Usage {
+    #[serde(rename = "lastUsedAt", default)]
+    last_used_at: Option<i64>,
+    #[serde(rename = "useCount", default)]
+    use_count: u32,
+}
+
+/// Usage by node id.
+type UsageStats = HashMap<String, Usage>;
+
+/// Fills in the usage stats of `nodes`. Older vault files still hold their own, so the
+/// higher numbers are kept.
+fn apply_usage(nodes: &mut [Node], usage: &UsageStats) {
+    for node in nodes {
+        if let Some(stats) = usage.get(&node.id) {
+            node.last_used_at = node.last_used_at.max(stats.last_used_at);
+            node.use_count = Some(node.use_count.unwrap_or(0).max(stats.use_count));
+        }
+        if let Some(children) = &mut node.children {
+            apply_usage(children, usage);
+        }
+    }
+}
+
+/// Moves the usage stats out of `nodes` into `usage`, keeping the higher numbers.
+/// Returns whether `usage` changed.
+fn take_usage(nodes: &mut [Node], usage: &mut UsageStats) -> bool {
+    let mut changed = false;
+    for node in nodes {
+        let last_used_at = node.last_used_at.take();
+        let use_count = node.use_count.take().unwrap_or(0);
+        if last_used_at.is_some() || use_count > 0 {
+            let stats = usage.entry(node.id.clone()).or_default();
+            if last_used_at > stats.last_used_at || use_count > stats.use_count {
+                stats.last_used_at = stats.last_used_at.max(last_used_at);
+                stats.use_count = stats.use_count.max(use_count);
+                changed = true;
+            }
+        }
+        if let Some(children) = &mut node.children {
+            changed |= take_usage(children, usage);
+        }
+    }
+    changed
+}
+
+/// The revision the main window is showing, managed by the app.
+#[derive(Default)]
+pub struct DataState {
+    shown: Arc<Mutex<Option<Revision>>>,
+}
+
 pub struct DataManager {
     pub file_path: PathBuf,
+    /// settings.json, which stays in the app data dir when the vault is moved.
+    settings_path: PathBuf,
+    /// The vault this instance works on, the active one unless chosen otherwise.
+    vault_id: String,
+    /// Folder for the vault's own files, and for sklad.json unless `vault_path` is set.
+    vault_dir: PathBuf,
+    /// Held vault lock, see `lock`.
+    lock: Option<File>,
+    /// Revision of the last load or save; `save_data` refuses to write over anything else.
+    base: Cell<Option<Revision>>,
+    /// Shared with `DataState` when running inside the app.
+    shown: Option<Arc<Mutex<Option<Revision>>>>,
+    device: OnceCell<String>,
+    /// Whether saves are committed to git, see `enable_git`.
+    git: bool,
+    /// Key of the unlocked vault, which saves sign the nodes with, see `integrity`.
+    integrity_key: Option<Key>,
 }
 
 impl DataManager {
+    /// Works on the active vault.
     pub fn new<R: Runtime>(app: &AppHandle<R>) -> Self {
-        let app_data_dir = app
-            .path()
+        let mut data_manager = Self::with_dir(Self::app_data_dir(app));
+        data_manager.shown = app
+            .try_state::<DataState>()
+            .map(|state| state.shown.clone());
+        data_manager.integrity_key = Self::unlocked_key(app, &data_manager.vault_id);
+        data_manager
+    }
+
+    /// Works on `vault`, which doesn't need to be the active one.
+    pub fn for_vault<R: Runtime>(app: &AppHandle<R>, vault: &str) -> Self {
+        let data_manager = Self::new(app);
+        if data_manager.vault_id == vault {
+            return data_manager;
+        }
+        // The main window only ever shows the active vault
+        let mut data_manager = Self::with_vault(Self::app_data_dir(app), vault);
+        data_manager.integrity_key = Self::unlocked_key(app, &data_manager.vault_id);
+        data_manager
+    }
+
+    fn unlocked_key<R: Runtime>(app: &AppHandle<R>, vault: &str) -> Option<Key> {
+        match app.try_state::<VaultManager>()?.state(vault) {
+            VaultState::Unlocked(key) => Some(key),
+            VaultState::Locked => None,
+        }
+    }
+
+    fn app_data_dir<R: Runtime>(app: &AppHandle<R>) -> PathBuf {
+        app.path()
             .app_data_dir()
-            .expect("failed to resolve app data dir");
+            .expect("failed to resolve app data dir")
+    }
+
+    /// The directory Tauri resolves as `app_data_dir`, for use without a running app.
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER))
+    }
+
+    /// Works on the active vault of the settings in `app_data_dir`.
+    pub fn with_dir(app_data_dir: PathBuf) -> Self {
+        Self::open(app_data_dir, None)
+    }
+
+    /// `with_dir` for `vault`, falling back to the active vault if there is no such vault.
+    pub fn with_vault(app_data_dir: PathBuf, vault: &str) -> Self {
+        Self::open(app_data_dir, Some(vault))
+    }
 
+    fn open(app_data_dir: PathBuf, vault: Option<&str>) -> Self {
         if !app_data_dir.exists() {
             fs::create_dir_all(&app_data_dir).expect("failed to create app data dir");
         }
 
-        Self {
-            file_path: app_data_dir.join("sklad.json"),
+        let mut data_manager = Self {
+            file_path: app_data_dir.join(VAULT_FILE_NAME),
+            settings_path: app_data_dir.join("settings.json"),
+            vault_id: DEFAULT_VAULT_ID.to_string(),
+            vault_dir: app_data_dir.clone(),
+            lock: None,
+            base: Cell::new(None),
+            shown: None,
+            device: OnceCell::new(),
+            git: false,
+            integrity_key: None,
+        };
+        let settings = data_manager.read_settings();
+        data_manager.vault_id = match vault {
+            Some(vault) if settings.vault_names().iter().any(|(id, _)| *id == vault) => {
+                vault.to_string()
+            }
+            _ => settings.vault_id,
+        };
+        if data_manager.vault_id != DEFAULT_VAULT_ID {
+            data_manager.vault_dir = app_data_dir
+                .join(VAULTS_DIR_NAME)
+                .join(&data_manager.vault_id);
+            let _ = fs::create_dir_all(&data_manager.vault_dir);
+        }
+
+        let settings = data_manager.load_settings();
+        data_manager.file_path = match settings.vault_path.filter(|path| !path.is_empty()) {
+            Some(vault_path) => PathBuf::from(vault_path).join(VAULT_FILE_NAME),
+            None => data_manager.vault_dir.join(VAULT_FILE_NAME),
+        };
+        data_manager.git = settings.git_enabled;
+        data_manager
+    }
+
+    pub fn vault_id(&self) -> &str {
+        &self.vault_id
+    }
+
+    /// Whether this instance's vault is the active one.
+    pub fn is_active(&self) -> bool {
+        self.read_settings().vault_id == self.vault_id
+    }
+
+    /// The app data dir, which holds settings and the lock even if the vault is elsewhere.
+    pub fn data_dir(&self) -> &Path {
+        self.settings_path.parent().unwrap_or(Path::new("."))
+    }
+
+    /// Where the vault is kept unless `vault_path` moves it.
+    pub fn vault_dir(&self) -> &Path {
+        &self.vault_dir
+    }
+
+    /// Holds the vault lock until this `DataManager` is dropped, so that a whole
+    /// load-modify-save cycle can't interleave with writes from another process.
+    pub fn lock(&mut self) -> io::Result<()> {
+        if self.lock.is_none() {
+            self.lock = Some(self.acquire_lock()?);
+        }
+        Ok(())
+    }
+
+    /// Releases the lock taken by `lock`.
+    pub fn unlock(&mut self) {
+        self.lock = None;
+    }
+
+    fn acquire_lock(&self) -> io::Result<File> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.settings_path.with_file_name(LOCK_FILE_NAME))?;
+        file.lock()?;
+        Ok(file)
+    }
+
+    /// The vault lock for one write, unless `lock` already holds it.
+    pub(crate) fn lock_for_write(&self) -> io::Result<Option<File>> {
+        match self.lock {
+            Some(_) => Ok(None),
+            None => self.acquire_lock().map(Some),
+        }
+    }
+
+    /// Replaces `path` through a temporary file so readers never see a partial write.
+    fn replace_file(path: &Path, content: &str) -> io::Result<()> {
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, content)?;
+        fs::rename(&tmp_path, path)
+    }
+
+    /// Revision of the last `load_data` or `save_data` through this instance.
+    pub fn revision(&self) -> Option<Revision> {
+        self.base.get()
+    }
+
+    /// Records the loaded data as what the main window shows.
+    pub fn mark_shown(&self) {
+        if let Some(shown) = &self.shown {
+            *shown.lock().unwrap() = self.base.get();
+        }
+    }
+
+    /// Makes the next `save_data` expect the revision the main window shows, for saves
+    /// of nodes that come from the window rather than from `load_data`.
+    pub fn expect_shown(&self) {
+        if let Some(shown) = &self.shown {
+            self.base.set(*shown.lock().unwrap());
         }
     }
 
@@ -31,31 +415,555 @@ impl DataManager {
             return defaults;
         }
 
-        let content = fs::read_to_string(&self.file_path).unwrap_or_else(|_| "[]".to_string());
-        serde_json::from_str(&content).unwrap_or_default()
+        let Ok(content) = fs::read(&self.file_path) else {
+            return Vec::new();
+        };
+        self.base.set(Some(Revision::of(&content)));
+        let Ok((mut nodes, device)) = Self::parse(&content) else {
+            return Vec::new();
+        };
+        if device.as_deref() != Some(self.device_id()) {
+            self.record_ancestor(&content);
+        }
+        apply_usage(&mut nodes, &self.read_usage());
+        nodes
+    }
+
+    /// The nodes and the device that wrote them.
+    fn parse(content: &[u8]) -> serde_json::Result<(Vec<Node>, Option<String>)> {
+        match serde_json::from_slice(content)? {
+            DataFile::Versioned { nodes, device } => Ok((nodes, device)),
+            DataFile::Legacy(nodes) => Ok((nodes, None)),
+        }
+    }
+
+    /// The MAC in the header, if the vault was last saved while unlocked.
+    fn parse_mac(content: &[u8]) -> Option<String> {
+        #[derive(Deserialize)]
+        struct Header {
+            mac: Option<String>,
+        }
+
+        serde_json::from_slice::<Header>(content)
+            .ok()
+            .and_then(|header| header.mac)
+    }
+
+    fn parse_nodes(content: &[u8]) -> serde_json::Result<Vec<Node>> {
+        Self::parse(content).map(|(nodes, _)| nodes)
+    }
+
+    /// The recipients of a shared vault file; none for any other vault.
+    fn parse_recipients(content: &[u8]) -> Vec<Recipient> {
+        #[derive(Deserialize)]
+        struct Header {
+            #[serde(default)]
+            recipients: Vec<Recipient>,
+        }
+
+        serde_json::from_slice::<Header>(content)
+            .map(|header| header.recipients)
+            .unwrap_or_default()
+    }
+
+    /// The key rotations of a shared vault file, see `DataFileRef::rotations`.
+    fn parse_rotations(content: &[u8]) -> Vec<String> {
+        #[derive(Deserialize)]
+        struct Header {
+            #[serde(default)]
+            rotations: Vec<String>,
+        }
+
+        serde_json::from_slice::<Header>(content)
+            .map(|header| header.rotations)
+            .unwrap_or_default()
+    }
+
+    /// Random id of this installation, stored in the vault to tell its own saves apart
+    /// from ones that arrived through a sync tool.
+    fn device_id(&self) -> &str {
+        self.device.get_or_init(|| {
+            let path = self.settings_path.with_file_name(DEVICE_ID_FILE_NAME);
+            fs::read_to_string(&path)
+                .map(|id| id.trim().to_string())
+                .ok()
+                .filter(|id| !id.is_empty())
+                .unwrap_or_else(|| {
+                    let id = uuid::Uuid::new_v4().to_string();
+                    let _ = fs::write(&path, &id);
+                    id
+                })
+        })
+    }
+
+    fn ancestor_path(&self) -> PathBuf {
+        self.vault_dir.join(ANCESTOR_FILE_NAME)
+    }
+
+    /// Keeps a copy of a vault written by another device. Local edits made since then are
+    /// not in it, so it is never newer than what two conflicting copies have in common
+    /// and can serve as their ancestor when merging.
+    fn record_ancestor(&self, content: &[u8]) {
+        let path = self.ancestor_path();
+        let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified(&path) < modified(&self.file_path) {
+            let _ = fs::write(&path, content);
+        }
+    }
+
+    fn usage_path(&self) -> PathBuf {
+        self.vault_dir.join(USAGE_FILE_NAME)
+    }
+
+    fn read_usage(&self) -> UsageStats {
+        fs::read(self.usage_path())
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Counts a copy of the snippet `id`. Usage stats are kept per device next to the
+    /// vault's own files rather than in sklad.json, so that using a snippet doesn't make
+    /// a new revision of the vault for sync and git.
+    pub fn record_use(&self, id: &str) -> io::Result<()> {
+        let _lock = self.lock_for_write()?;
+        let mut usage = self.read_usage();
+        let stats = usage.entry(id.to_string()).or_default();
+        stats.last_used_at = Some(crate::models::timestamp_millis());
+        stats.use_count = stats.use_count.saturating_add(1);
+        Self::replace_file(&self.usage_path(), &serde_json::to_string(&usage)?)
     }
 
+    /// Reads the nodes of a vault file without touching this instance's revision.
+    pub fn read_nodes(path: &Path) -> io::Result<Vec<Node>> {
+        Self::parse_nodes(&fs::read(path)?).map_err(io::Error::other)
+    }
+
+    /// Writes `nodes` as the next generation. Fails with a `ConflictError` if the file
+    /// changed since this instance loaded or saved it.
     pub fn save_data(&self, nodes: &[Node]) -> Result<(), std::io::Error> {
-        let content = serde_json::to_string_pretty(nodes)?;
-        fs::write(&self.file_path, content)
+        self.write_nodes(nodes, None, None, None)
+    }
+
+    /// `save_data` that also replaces the members of a shared vault, for when the key
+    /// is wrapped anew. The nodes must already be encrypted with the key they get.
+    pub fn save_shared(&self, nodes: &[Node], recipients: &[Recipient]) -> io::Result<()> {
+        self.write_nodes(nodes, Some(recipients), None, None)
+    }
+
+    /// `save_shared` for a new key, with the `sharing::rotation` from the key before.
+    pub fn rotate_shared(
+        &self,
+        nodes: &[Node],
+        recipients: &[Recipient],
+        rotation: String,
+    ) -> io::Result<()> {
+        self.write_nodes(nodes, Some(recipients), Some(rotation), None)
+    }
+
+    /// The members the vault file is shared with.
+    pub fn recipients(&self) -> io::Result<Vec<Recipient>> {
+        match Self::read_recipients(&self.file_path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            result => result,
+        }
+    }
+
+    /// Reads the members of a vault file, none unless it is shared.
+    pub fn read_recipients(path: &Path) -> io::Result<Vec<Recipient>> {
+        Ok(Self::parse_recipients(&fs::read(path)?))
+    }
+
+    /// This device's identity, which opens the shared vaults it is a member of.
+    pub fn identity(&self) -> io::Result<Identity> {
+        Identity::load_or_create(self.data_dir())
+    }
+
+    /// The key of a shared vault as wrapped for this device, if it is a member. Anyone who
+    /// can write the file can wrap a key, so one other than the key this device knows is
+    /// only taken if the file shows it was rotated from that key.
+    pub fn shared_key(&self) -> io::Result<Option<Key>> {
+        let Some(key) = sharing::unwrap(&self.recipients()?, &self.identity()?) else {
+            return Ok(None);
+        };
+        let _lock = self.lock_for_write()?;
+        let mut settings = self.load_settings();
+        if settings.security.key_check.is_some() {
+            if crate::vault::is_vault_key(&settings, &key) {
+                return Ok(Some(key));
+            }
+            let rotations = fs::read(&self.file_path)
+                .map(|content| Self::parse_rotations(&content))
+                .unwrap_or_default();
+            if !sharing::rotated_from(&rotations, &key, |previous| {
+                crate::vault::is_vault_key(&settings, previous)
+            }) {
+                return Err(io::Error::other(UNPROVEN_KEY_ERROR));
+            }
+        }
+        let check = crate::vault::key_check(&key).map_err(io::Error::other)?;
+        settings.security.key_check = Some(check);
+        self.write_vault_settings(&settings)?;
+        Ok(Some(key))
+    }
+
+    /// `save_data` with new recipients, if they change, and the message for the git
+    /// commit, if git is enabled.
+    fn write_nodes(
+        &self,
+        nodes: &[Node],
+        recipients: Option<&[Recipient]>,
+        rotation: Option<String>,
+        message: Option<&str>,
+    ) -> io::Result<()> {
+        let _lock = self.lock_for_write()?;
+        if self.git && crate::vault::has_plain_secrets(nodes) {
+            return Err(io::Error::other(PLAIN_SECRETS_ERROR));
+        }
+
+        let current = fs::read(&self.file_path).ok();
+        let current_revision = current.as_deref().map(Revision::of);
+        let base = self.base.get();
+        if base.is_some() && current_revision != base {
+            return Err(io::Error::other(ConflictError));
+        }
+
+        let previous = current
+            .as_deref()
+            .and_then(|content| Self::parse_nodes(content).ok())
+            .unwrap_or_default();
+        let mut nodes = nodes.to_vec();
+        let mut usage = self.read_usage();
+        if take_usage(&mut nodes, &mut usage) {
+            Self::replace_file(&self.usage_path(), &serde_json::to_string(&usage)?)?;
+        }
+        sync::stamp_revisions(&mut nodes, &previous, crate::models::timestamp_millis());
+
+        let recipients = match recipients {
+            Some(recipients) => recipients.to_vec(),
+            None => current
+                .as_deref()
+                .map(Self::parse_recipients)
+                .unwrap_or_default(),
+        };
+        let mut rotations = current
+            .as_deref()
+            .map(Self::parse_rotations)
+            .unwrap_or_default();
+        rotations.extend(rotation);
+
+        let generation = current_revision.map_or(0, |revision| revision.generation) + 1;
+        let mac = self
+            .integrity_key
+            .as_ref()
+            .map(|key| integrity::mac(&nodes, key));
+        let content = serialize_data(
+            generation,
+            self.device_id(),
+            &recipients,
+            &rotations,
+            mac.as_deref(),
+            &mut nodes,
+        )?;
+        if let Some(dir) = self.file_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        Self::replace_file(&self.file_path, &content)?;
+        let _ = crate::attachments::prune(self, &previous, &nodes);
+        if mac.is_some() {
+            let _ = integrity::record_verified(&self.vault_dir, &nodes);
+        } else if (current.as_deref().and_then(Self::parse_mac).is_some()
+            || integrity::tracks_unsigned(&self.vault_dir))
+            && integrity::is_known(&self.vault_dir, &previous)
+        {
+            // Lets `verify_integrity` tell this write from someone else's. A write over
+            // changes it can't check leaves them to be reported on unlock.
+            let _ = integrity::record_unsigned(&self.vault_dir, &nodes);
+        }
+        // Until another device's copy arrives, the vault as first saved here stands in
+        if !self.ancestor_path().exists() {
+            self.record_ancestor(content.as_bytes());
+        }
+        if !crate::vault::has_legacy_ciphertexts(&nodes) {
+            self.record_ciphertext_version();
+        }
+
+        let revision = Revision::of(content.as_bytes());
+        self.base.set(Some(revision));
+        // The window's copy is only outdated by writes it didn't make, so keep it current
+        // when this write started from the same revision
+        if let (Some(shown), Some(_)) = (&self.shown, base) {
+            let mut shown = shown.lock().unwrap();
+            if *shown == base {
+                *shown = Some(revision);
+            }
+        }
+
+        // Last, since the vault is saved either way; the next save commits it along
+        if self.git {
+            let message = message
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("Save generation {}", generation));
+            self.git_repo()
+                .and_then(|repo| repo.commit(VAULT_FILE_NAME, &message))
+                .map_err(|e| {
+                    io::Error::other(format!("Saved, but committing it to git failed: {}", e))
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Notes in the settings that no secret is left in the older ciphertext format, so
+    /// `vault::accepts_legacy` refuses it from now on. Called with the vault lock held.
+    fn record_ciphertext_version(&self) {
+        let mut settings = self.load_settings();
+        if settings.security.ciphertext_version < crate::vault::CIPHERTEXT_VERSION {
+            settings.security.ciphertext_version = crate::vault::CIPHERTEXT_VERSION;
+            let _ = self.write_vault_settings(&settings);
+        }
+    }
+
+    /// Sets the key saves sign the nodes with, for use without a running app.
+    pub fn set_integrity_key(&mut self, key: Option<Key>) {
+        self.integrity_key = key;
+    }
+
+    /// Checks sklad.json against its MAC with the key from `set_integrity_key`. A vault
+    /// that was never verified here, or that this device last wrote while locked, is
+    /// signed as it is. Otherwise returns what changed since the last verified state.
+    pub fn verify_integrity(&self) -> io::Result<Option<TamperReport>> {
+        let Some(key) = &self.integrity_key else {
+            return Ok(None);
+        };
+        let content = match fs::read(&self.file_path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            result => result?,
+        };
+        let nodes = Self::parse_nodes(&content).map_err(io::Error::other)?;
+
+        if Self::parse_mac(&content).is_some_and(|mac| integrity::verify_mac(&nodes, key, &mac))
+        {
+            integrity::record_verified(&self.vault_dir, &nodes)?;
+            return Ok(None);
+        }
+        match integrity::load_verified(&self.vault_dir) {
+            Some(verified) if !integrity::is_own_unsigned(&self.vault_dir, &nodes) => {
+                Ok(Some(TamperReport {
+                    changes: integrity::diff(&verified, &nodes),
+                }))
+            }
+            _ => self.sign().map(|()| None),
+        }
+    }
+
+    /// Saves sklad.json as it is on disk with a fresh MAC, trusting whatever changed.
+    pub fn sign(&self) -> io::Result<()> {
+        if self.integrity_key.is_none() {
+            return Err(io::Error::other("Unlock the vault first"));
+        }
+        let content = fs::read(&self.file_path)?;
+        self.base.set(Some(Revision::of(&content)));
+        let nodes = Self::parse_nodes(&content).map_err(io::Error::other)?;
+        self.write_nodes(&nodes, None, None, Some("Sign vault"))
+    }
+
+    /// Puts back the vault as last verified on this device.
+    pub fn restore_verified(&self) -> io::Result<()> {
+        if self.integrity_key.is_none() {
+            return Err(io::Error::other("Unlock the vault first"));
+        }
+        let nodes = integrity::load_verified(&self.vault_dir)
+            .ok_or_else(|| io::Error::other("No verified copy of the vault"))?;
+        self.base.set(fs::read(&self.file_path).ok().as_deref().map(Revision::of));
+        self.write_nodes(&nodes, None, None, Some("Restore verified vault"))
+    }
+
+    fn git_repo(&self) -> io::Result<GitRepo> {
+        GitRepo::init(self.file_path.parent().unwrap_or(Path::new(".")))
+    }
+
+    /// Turns the vault folder into a git repository if it isn't one and commits the
+    /// current vault. Fails while the vault holds unencrypted secrets.
+    pub fn enable_git(&self, remote: Option<&str>) -> io::Result<()> {
+        let _lock = self.lock_for_write()?;
+        let nodes = Self::read_nodes(&self.file_path).unwrap_or_default();
+        if crate::vault::has_plain_secrets(&nodes) {
+            return Err(io::Error::other(PLAIN_SECRETS_ERROR));
+        }
+
+        let repo = self.git_repo()?;
+        repo.set_remote(remote.filter(|url| !url.is_empty()))?;
+        if self.file_path.exists() {
+            repo.commit(VAULT_FILE_NAME, "Add vault")?;
+        }
+        Ok(())
+    }
+
+    /// Fetches the remote and brings its changes in, fast-forwarding when possible and
+    /// otherwise merging node by node against the common ancestor commit. Leaves the
+    /// vault locked like `lock`. See `sync::merge` for `same_secret`.
+    pub fn git_pull(
+        &mut self,
+        same_secret: &dyn Fn(&str, &str, &str) -> bool,
+    ) -> io::Result<MergeReport> {
+        if !self.git {
+            return Err(io::Error::other("Git history is not enabled"));
+        }
+        let mut report = MergeReport::default();
+        let repo = self.git_repo()?;
+        self.lock()?;
+        // Picks up changes saved while git was off or written by other tools
+        if self.file_path.exists() {
+            repo.commit(VAULT_FILE_NAME, "Save local changes")?;
+        }
+
+        let Some(remote) = repo.fetch()? else {
+            return Ok(report);
+        };
+        match repo.relation(&remote)? {
+            Relation::UpToDate => return Ok(report),
+            Relation::Behind => repo.fast_forward(&remote)?,
+            Relation::Diverged(base) => {
+                let read = |rev: &str| -> io::Result<Option<Vec<Node>>> {
+                    repo.show(rev, VAULT_FILE_NAME)?
+                        .map(|content| Self::parse_nodes(&content).map_err(io::Error::other))
+                        .transpose()
+                };
+                let base_nodes = match base {
+                    Some(base) => read(&base)?.unwrap_or_default(),
+                    None => Vec::new(),
+                };
+                let remote_nodes = read(&remote)?.unwrap_or_default();
+                let local_nodes = self.load_data();
+
+                let (merged, conflicts) =
+                    sync::merge(&base_nodes, &local_nodes, &remote_nodes, same_secret);
+                repo.start_merge(&remote)?;
+                let message = format!("Merge {}", git::REMOTE);
+                if let Err(e) = self
+                    .take_remote_blobs(&repo, &remote, &merged)
+                    .and_then(|()| self.write_nodes(&merged, None, None, Some(&message)))
+                {
+                    let _ = repo.abort_merge();
+                    return Err(e);
+                }
+                report.conflicts = conflicts;
+            }
+        }
+        report.merged_files.push(git::REMOTE.to_string());
+        Ok(report)
+    }
+
+    /// Writes the blob files of attachments in `merged` that only `remote` has, since a
+    /// merge keeps this side's files.
+    fn take_remote_blobs(&self, repo: &GitRepo, remote: &str, merged: &[Node]) -> io::Result<()> {
+        let dir = self.file_path.parent().unwrap_or(Path::new("."));
+        for blob in crate::attachments::missing_blobs(self, merged) {
+            if let Some(content) = repo.show(remote, &blob)? {
+                let path = dir.join(&blob);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, content)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn git_push(&self) -> io::Result<()> {
+        self.git_repo()?.push()
+    }
+
+    /// Conflicting copies of the vault that a sync tool left next to it.
+    pub fn conflict_files(&self) -> Vec<PathBuf> {
+        sync::conflict_files(&self.file_path)
+    }
+
+    /// Merges the conflicting copies into the vault, then deletes them. Leaves the vault
+    /// locked like `lock`. See `sync::merge` for `same_secret`.
+    pub fn merge_conflict_files(
+        &mut self,
+        same_secret: &dyn Fn(&str, &str, &str) -> bool,
+    ) -> io::Result<MergeReport> {
+        let mut report = MergeReport::default();
+        let files = self.conflict_files();
+        if files.is_empty() {
+            return Ok(report);
+        }
+
+        self.lock()?;
+        let base = Self::read_nodes(&self.ancestor_path()).unwrap_or_default();
+        let mut nodes = self.load_data();
+        for path in &files {
+            let remote = Self::read_nodes(path)?;
+            let (merged, conflicts) = sync::merge(&base, &nodes, &remote, same_secret);
+            nodes = merged;
+            report.conflicts.extend(conflicts);
+            report.merged_files.push(
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+            );
+        }
+
+        self.save_data(&nodes)?;
+        for path in &files {
+            fs::remove_file(path)?;
+        }
+        Ok(report)
     }
 
+    /// The settings with this instance's vault as the active one.
     pub fn load_settings(&self) -> crate::models::AppSettings {
-        let settings_path = self.file_path.with_file_name("settings.json");
+        let mut settings = self.read_settings();
+        if settings.vault_id != self.vault_id {
+            settings.switch_vault(&self.vault_id);
+        }
+        settings
+    }
+
+    /// Saves settings from `load_settings`, leaving the active vault as it is on disk.
+    pub fn save_settings(&self, settings: &crate::models::AppSettings) -> Result<(), std::io::Error> {
+        let _lock = self.lock_for_write()?;
+        self.write_vault_settings(settings)
+    }
+
+    /// `save_settings` for callers that hold the vault lock.
+    fn write_vault_settings(&self, settings: &AppSettings) -> io::Result<()> {
+        let active = self.read_settings().vault_id;
+        let mut settings = settings.clone();
+        if settings.vault_id != active {
+            settings.switch_vault(&active);
+        }
+        self.write_settings(&settings)
+    }
+
+    /// Makes `vault` the active vault.
+    pub fn switch_vault(&self, vault: &str) -> io::Result<()> {
+        let _lock = self.lock_for_write()?;
+        let mut settings = self.read_settings();
+        if settings.vault_id != vault && !settings.switch_vault(vault) {
+            return Err(io::Error::other(format!("Vault '{}' not found", vault)));
+        }
+        self.write_settings(&settings)
+    }
+
+    fn read_settings(&self) -> AppSettings {
+        let settings_path = &self.settings_path;
         if !settings_path.exists() {
-            return crate::models::AppSettings::default();
+            return AppSettings::default();
         }
 
-        fs::read_to_string(&settings_path)
+        fs::read_to_string(settings_path)
             .ok()
             .and_then(|content| serde_json::from_str(&content).ok())
             .unwrap_or_default()
     }
 
-    pub fn save_settings(&self, settings: &crate::models::AppSettings) -> Result<(), std::io::Error> {
-        let settings_path = self.file_path.with_file_name("settings.json");
+    fn write_settings(&self, settings: &AppSettings) -> io::Result<()> {
         let content = serde_json::to_string_pretty(settings)?;
-        fs::write(settings_path, content)
+        Self::replace_file(&self.settings_path, &content)
     }
 
     pub fn find_node_by_id(nodes: &[Node], id: &str) -> Option<Node> {
@@ -72,6 +980,83 @@ impl DataManager {
         None
     }
 
+    pub fn find_node_by_id_mut<'a>(nodes: &'a mut [Node], id: &str) -> Option<&'a mut Node> {
+        for node in nodes {
+            if node.id == id {
+                return Some(node);
+            }
+            if let Some(children) = &mut node.children {
+                if let Some(found) = Self::find_node_by_id_mut(children, id) {
+                    return Some(found);
+                }
+            }
+        }
+        None
+    }
+
+    /// Splits a `Folder/Sub/Label` path, as used by the CLI and the IPC socket, into labels.
+    pub fn split_path(path: &str) -> Vec<&str> {
+        path.split('/')
+            .map(str::trim)
+            .filter(|segment| !segment.is_empty())
+            .collect()
+    }
+
+    pub fn find_node_by_path<'a>(nodes: &'a [Node], path: &[&str]) -> Option<&'a Node> {
+        let (first, rest) = path.split_first()?;
+        let node = nodes
+            .iter()
+            .find(|n| !matches!(n.node_type, NodeType::Separator) && n.label == *first)?;
+        if rest.is_empty() {
+            Some(node)
+        } else {
+            Self::find_node_by_path(node.children.as_deref().unwrap_or_default(), rest)
+        }
+    }
+
+    /// Paths of the snippets whose label or (non-secret) value contains `query`.
+    pub fn search_paths(nodes: &[Node], query: &str) -> Vec<String> {
+        let mut matches = Vec::new();
+        Self::search_recursive(nodes, &query.to_lowercase(), &mut Vec::new(), &mut matches);
+        matches
+    }
+
+    fn search_recursive<'a>(
+        nodes: &'a [Node],
+        query: &str,
+        path: &mut Vec<&'a str>,
+        out: &mut Vec<String>,
+    ) {
+        for node in nodes {
+            path.push(&node.label);
+            match node.node_type {
+                NodeType::Folder => Self::search_recursive(
+                    node.children.as_deref().unwrap_or_default(),
+                    query,
+                    path,
+                    out,
+                ),
+                NodeType::Snippet => {
+                    let in_value = !node.is_secret.unwrap_or(false)
+                        && node
+                            .value
+                            .as_deref()
+                            .is_some_and(|v| v.to_lowercase().contains(query));
+                    if in_value || node.label.to_lowercase().contains(query) {
+                        out.push(path.join("/"));
+                    }
+                }
+                NodeType::Attachment => {
+                    if node.label.to_lowercase().contains(query) {
+                        out.push(path.join("/"));
+                    }
+                }
+                NodeType::Separator => {}
+            }
+            path.pop();
+        }
+    }
+
     fn default_nodes() -> Vec<Node> {
         vec![Node {
             id: "welcome-1".to_string(),
@@ -79,10 +1064,21 @@ impl DataManager {
             label: "Welcome to Sklad".to_string(),
             parent_id: None,
             created_at: 0,
+            show_in_tray: None,
+            icon: None,
             children: None,
+            sort_mode: None,
             value: Some("This is your first snippet.".to_string()),
             encrypted_value: None,
             is_secret: Some(false),
+            last_used_at: None,
+            use_count: None,
+            totp: None,
+            history: None,
+            expires_at: None,
+            attachment: None,
+            revision: None,
+            modified_at: None,
         }]
     }
 }
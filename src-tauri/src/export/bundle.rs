@@ -0,0 +1,140 @@
+use crate::models::{AppSettings, Node, SortMode};
+use crate::security;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const BUNDLE_FORMAT: &str = "sklad-bundle";
+const BUNDLE_VERSION: u32 = 1;
+const BUNDLE_KDF: &str = "argon2id";
+const SALT_SIZE: usize = 16;
+
+/// Settings that travel with a bundle. Machine-specific ones (shortcuts, autostart) and
+/// the master password verifier stay behind.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleSettings {
+    pub theme: String,
+    pub lock_timeout: u32,
+    pub clear_clipboard: bool,
+    pub notifications_enabled: bool,
+    pub tray_click_action: String,
+    pub tray_menu_root_position: String,
+    pub tray_max_depth: u32,
+    pub tray_max_items_per_folder: u32,
+    pub root_sort_mode: SortMode,
+}
+
+impl BundleSettings {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        Self {
+            theme: settings.theme.clone(),
+            lock_timeout: settings.security.lock_timeout,
+            clear_clipboard: settings.security.clear_clipboard,
+            notifications_enabled: settings.notifications_enabled,
+            tray_click_action: settings.tray_click_action.clone(),
+            tray_menu_root_position: settings.tray_menu_root_position.clone(),
+            tray_max_depth: settings.tray_max_depth,
+            tray_max_items_per_folder: settings.tray_max_items_per_folder,
+            root_sort_mode: settings.root_sort_mode,
+        }
+    }
+
+    pub fn apply_to(&self, settings: &mut AppSettings) {
+        settings.theme = self.theme.clone();
+        settings.security.lock_timeout = self.lock_timeout;
+        settings.security.clear_clipboard = self.clear_clipboard;
+        settings.notifications_enabled = self.notifications_enabled;
+        settings.tray_click_action = self.tray_click_action.clone();
+        settings.tray_menu_root_position = self.tray_menu_root_position.clone();
+        settings.tray_max_depth = self.tray_max_depth;
+        settings.tray_max_items_per_folder = self.tray_max_items_per_folder;
+        settings.root_sort_mode = self.root_sort_mode;
+    }
+}
+
+/// Decrypted bundle contents. Secret snippets carry their plain `value`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundlePayload {
+    pub nodes: Vec<Node>,
+    pub settings: BundleSettings,
+    #[serde(rename = "exportedAt")]
+    pub exported_at: i64,
+}
+
+/// On-disk `.sklad` file. Everything except the header is inside the AES-GCM ciphertext,
+/// and the header itself is authenticated as associated data.
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleFile {
+    format: String,
+    version: u32,
+    kdf: String,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl BundleFile {
+    fn associated_data(&self) -> Vec<u8> {
+        format!(
+            "{}:{}:{}:{}",
+            self.format, self.version, self.kdf, self.salt
+        )
+        .into_bytes()
+    }
+}
+
+pub fn is_bundle(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("sklad"))
+}
+
+/// Encrypts `payload` with a key derived from `passphrase`.
+pub fn seal(payload: &BundlePayload, passphrase: &str) -> Result<Vec<u8>, String> {
+    if passphrase.is_empty() {
+        return Err("Bundle passphrase cannot be empty".into());
+    }
+
+    let mut file = BundleFile {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        kdf: BUNDLE_KDF.to_string(),
+        salt: hex::encode(security::random_bytes::<SALT_SIZE>()),
+        nonce: String::new(),
+        ciphertext: String::new(),
+    };
+
+    let key = security::derive_key_from_password(passphrase, &file.salt);
+    let plaintext = serde_json::to_vec(payload).map_err(|e| e.to_string())?;
+    let (ciphertext, nonce) = security::encrypt_bytes(&plaintext, &key, &file.associated_data())?;
+
+    file.nonce = hex::encode(nonce);
+    file.ciphertext = hex::encode(ciphertext);
+    serde_json::to_vec_pretty(&file).map_err(|e| e.to_string())
+}
+
+/// Decrypts a bundle produced by `seal`.
+pub fn open(content: &[u8], passphrase: &str) -> Result<BundlePayload, String> {
+    let file: BundleFile =
+        serde_json::from_slice(content).map_err(|_| "Not a Sklad bundle".to_string())?;
+
+    if file.format != BUNDLE_FORMAT || file.kdf != BUNDLE_KDF {
+        return Err("Not a Sklad bundle".into());
+    }
+    if file.version > BUNDLE_VERSION {
+        return Err(format!(
+            "Bundle version {} is newer than this version of Sklad supports",
+            file.version
+        ));
+    }
+
+    let key = security::derive_key_from_password(passphrase, &file.salt);
+    let nonce = hex::decode(&file.nonce).map_err(|_| "Invalid nonce hex".to_string())?;
+    let ciphertext =
+        hex::decode(&file.ciphertext).map_err(|_| "Invalid ciphertext hex".to_string())?;
+
+    let plaintext = security::decrypt_bytes(&ciphertext, &nonce, &key, &file.associated_data())
+        .map_err(|_| "Wrong passphrase or corrupted bundle".to_string())?;
+
+    serde_json::from_slice(&plaintext).map_err(|e| format!("Invalid bundle contents: {}", e))
+}
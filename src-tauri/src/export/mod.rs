@@ -0,0 +1,233 @@
+pub mod bundle;
+
+use crate::models::{Node, NodeType};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// Encrypted `.sklad` bundle, see [`bundle`].
+    SkladBundle,
+    Json,
+    Csv,
+    Markdown,
+    Espanso,
+    VscodeSnippets,
+}
+
+/// Returns a copy of `nodes` ready for export. `nodes` must already have secret values
+/// decrypted when `include_secrets` is set; otherwise secret snippets are dropped.
+pub fn prepare_nodes(nodes: &[Node], include_secrets: bool) -> Result<Vec<Node>, String> {
+    let mut prepared = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let is_secret = node.is_secret.unwrap_or(false);
+        // Attachments stay in the vault, none of the formats has room for files
+        if (is_secret && !include_secrets) || node.node_type == NodeType::Attachment {
+            continue;
+        }
+        if is_secret && node.value.is_none() && node.encrypted_value.is_some() {
+            return Err(format!("Failed to decrypt secret \"{}\"", node.label));
+        }
+
+        let mut node = node.clone();
+        node.encrypted_value = None;
+        if let Some(children) = &node.children {
+            node.children = Some(prepare_nodes(children, include_secrets)?);
+        }
+        prepared.push(node);
+    }
+    Ok(prepared)
+}
+
+/// Renders prepared nodes in one of the plain-text formats.
+pub fn render(format: ExportFormat, nodes: &[Node]) -> Result<Vec<u8>, String> {
+    match format {
+        ExportFormat::SkladBundle => Err("Bundles are sealed with bundle::seal".into()),
+        ExportFormat::Json => serde_json::to_vec_pretty(nodes).map_err(|e| e.to_string()),
+        ExportFormat::Csv => render_csv(nodes),
+        ExportFormat::Markdown => Ok(render_markdown(nodes).into_bytes()),
+        ExportFormat::Espanso => render_espanso(nodes),
+        ExportFormat::VscodeSnippets => render_vscode(nodes),
+    }
+}
+
+/// Writes an export file. Files holding plaintext secrets are only readable by the owner.
+pub fn write_file(path: &Path, content: &[u8], private: bool) -> Result<(), String> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(content))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Snippets with the labels of their parent folders.
+fn flatten<'a>(nodes: &'a [Node], path: &mut Vec<&'a str>, out: &mut Vec<(String, &'a Node)>) {
+    for node in nodes {
+        match node.node_type {
+            NodeType::Folder => {
+                path.push(&node.label);
+                flatten(node.children.as_deref().unwrap_or_default(), path, out);
+                path.pop();
+            }
+            NodeType::Snippet => out.push((path.join(" / "), node)),
+            NodeType::Separator | NodeType::Attachment => {}
+        }
+    }
+}
+
+fn snippets(nodes: &[Node]) -> Vec<(String, &Node)> {
+    let mut out = Vec::new();
+    flatten(nodes, &mut Vec::new(), &mut out);
+    out
+}
+
+fn render_csv(nodes: &[Node]) -> Result<Vec<u8>, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(["folder", "label", "value", "secret"])
+        .map_err(|e| e.to_string())?;
+    for (folder, node) in snippets(nodes) {
+        let secret = node.is_secret.unwrap_or(false).to_string();
+        writer
+            .write_record([
+                folder.as_str(),
+                node.label.as_str(),
+                node.value.as_deref().unwrap_or(""),
+                secret.as_str(),
+            ])
+            .map_err(|e| e.to_string())?;
+    }
+    writer.into_inner().map_err(|e| e.to_string())
+}
+
+fn render_markdown(nodes: &[Node]) -> String {
+    let mut out = String::from("# Sklad\n");
+    write_markdown(nodes, 2, &mut out);
+    out
+}
+
+fn write_markdown(nodes: &[Node], level: usize, out: &mut String) {
+    let heading = "#".repeat(level.min(6));
+    for node in nodes {
+        match node.node_type {
+            NodeType::Folder => {
+                out.push_str(&format!("\n{} {}\n", heading, node.label));
+                write_markdown(node.children.as_deref().unwrap_or_default(), level + 1, out);
+            }
+            NodeType::Snippet => {
+                let value = node.value.as_deref().unwrap_or("");
+                // The fence must be longer than any backtick run inside the value
+                let longest_run = value.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+                let fence = "`".repeat(longest_run.max(2) + 1);
+                out.push_str(&format!(
+                    "\n{} {}\n\n{}\n{}\n{}\n",
+                    heading, node.label, fence, value, fence
+                ));
+            }
+            NodeType::Separator => out.push_str("\n---\n"),
+            NodeType::Attachment => {}
+        }
+    }
+}
+
+/// Lower-case, dash-separated form of a label, used for triggers and prefixes.
+fn slug(label: &str) -> String {
+    label
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn unique(candidate: String, taken: &mut HashSet<String>) -> String {
+    let mut name = candidate.clone();
+    let mut n = 2;
+    while !taken.insert(name.clone()) {
+        name = format!("{}-{}", candidate, n);
+        n += 1;
+    }
+    name
+}
+
+#[derive(Serialize)]
+struct EspansoFile {
+    matches: Vec<EspansoMatch>,
+}
+
+#[derive(Serialize)]
+struct EspansoMatch {
+    trigger: String,
+    replace: String,
+}
+
+fn render_espanso(nodes: &[Node]) -> Result<Vec<u8>, String> {
+    let mut taken = HashSet::new();
+    let matches = snippets(nodes)
+        .into_iter()
+        .map(|(_, node)| {
+            let trigger = if node.label.starts_with(':') {
+                node.label.clone()
+            } else {
+                format!(":{}", slug(&node.label))
+            };
+            EspansoMatch {
+                trigger: unique(trigger, &mut taken),
+                replace: node.value.clone().unwrap_or_default(),
+            }
+        })
+        .collect();
+
+    serde_yaml::to_string(&EspansoFile { matches })
+        .map(String::into_bytes)
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Serialize)]
+struct VscodeSnippet {
+    prefix: String,
+    body: Vec<String>,
+    description: String,
+}
+
+fn render_vscode(nodes: &[Node]) -> Result<Vec<u8>, String> {
+    let mut taken_names = HashSet::new();
+    let mut taken_prefixes = HashSet::new();
+    let snippets: BTreeMap<String, VscodeSnippet> = snippets(nodes)
+        .into_iter()
+        .map(|(folder, node)| {
+            let name = if folder.is_empty() {
+                node.label.clone()
+            } else {
+                format!("{} / {}", folder, node.label)
+            };
+            let snippet = VscodeSnippet {
+                prefix: unique(slug(&node.label), &mut taken_prefixes),
+                body: node
+                    .value
+                    .as_deref()
+                    .unwrap_or("")
+                    .lines()
+                    .map(str::to_string)
+                    .collect(),
+                description: name.clone(),
+            };
+            (unique(name, &mut taken_names), snippet)
+        })
+        .collect();
+
+    serde_json::to_vec_pretty(&snippets).map_err(|e| e.to_string())
+}
@@ -0,0 +1,170 @@
+use crate::security;
+use serde::{Deserialize, Serialize};
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!#$%&*+-=?@^_~";
+/// Characters that are easily mistaken for one another when read or typed.
+const AMBIGUOUS: &str = "0OoIl1";
+/// Common English words for passphrases, one per line.
+const WORDLIST: &str = include_str!("wordlist.txt");
+
+const LENGTH_RANGE: std::ops::RangeInclusive<usize> = 4..=256;
+const WORDS_RANGE: std::ops::RangeInclusive<usize> = 3..=20;
+
+/// What `generate` makes: a password of random characters, or a passphrase of random
+/// words if `passphrase` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordPolicy {
+    #[serde(default = "default_length")]
+    pub length: usize,
+    #[serde(default = "default_true")]
+    pub lowercase: bool,
+    #[serde(default = "default_true")]
+    pub uppercase: bool,
+    #[serde(default = "default_true")]
+    pub digits: bool,
+    #[serde(default = "default_true")]
+    pub symbols: bool,
+    #[serde(rename = "excludeAmbiguous", default)]
+    pub exclude_ambiguous: bool,
+    #[serde(default)]
+    pub passphrase: bool,
+    #[serde(default = "default_words")]
+    pub words: usize,
+    #[serde(default = "default_separator")]
+    pub separator: String,
+}
+
+fn default_length() -> usize {
+    20
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_words() -> usize {
+    5
+}
+
+fn default_separator() -> String {
+    "-".into()
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            length: default_length(),
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: true,
+            exclude_ambiguous: false,
+            passphrase: false,
+            words: default_words(),
+            separator: default_separator(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneratedPassword {
+    pub password: String,
+    /// Bits of entropy of the policy, log2 of the number of passwords it can give.
+    #[serde(rename = "entropyBits")]
+    pub entropy_bits: f64,
+}
+
+/// A random password following `policy`, drawn from the OS random number generator.
+pub fn generate(policy: &PasswordPolicy) -> Result<GeneratedPassword, String> {
+    if policy.passphrase {
+        generate_passphrase(policy)
+    } else {
+        generate_characters(policy)
+    }
+}
+
+fn generate_characters(policy: &PasswordPolicy) -> Result<GeneratedPassword, String> {
+    if !LENGTH_RANGE.contains(&policy.length) {
+        return Err(format!(
+            "Passwords must be {} to {} characters long",
+            LENGTH_RANGE.start(),
+            LENGTH_RANGE.end()
+        ));
+    }
+    let classes: Vec<Vec<char>> = [
+        (policy.lowercase, LOWERCASE),
+        (policy.uppercase, UPPERCASE),
+        (policy.digits, DIGITS),
+        (policy.symbols, SYMBOLS),
+    ]
+    .into_iter()
+    .filter(|(enabled, _)| *enabled)
+    .map(|(_, chars)| {
+        chars
+            .chars()
+            .filter(|c| !(policy.exclude_ambiguous && AMBIGUOUS.contains(*c)))
+            .collect()
+    })
+    .collect();
+    if classes.is_empty() {
+        return Err("Choose at least one kind of character".into());
+    }
+    let pool: Vec<char> = classes.concat();
+
+    // One character of each kind, so every chosen kind shows up, then any of them
+    let mut chars: Vec<char> = classes.iter().map(|class| *pick(class)).collect();
+    while chars.len() < policy.length {
+        chars.push(*pick(&pool));
+    }
+    shuffle(&mut chars);
+
+    Ok(GeneratedPassword {
+        password: chars.into_iter().collect(),
+        entropy_bits: policy.length as f64 * (pool.len() as f64).log2(),
+    })
+}
+
+fn generate_passphrase(policy: &PasswordPolicy) -> Result<GeneratedPassword, String> {
+    if !WORDS_RANGE.contains(&policy.words) {
+        return Err(format!(
+            "Passphrases must have {} to {} words",
+            WORDS_RANGE.start(),
+            WORDS_RANGE.end()
+        ));
+    }
+    let wordlist: Vec<&str> = WORDLIST.lines().collect();
+    let words: Vec<&str> = (0..policy.words).map(|_| *pick(&wordlist)).collect();
+
+    Ok(GeneratedPassword {
+        password: words.join(&policy.separator),
+        entropy_bits: policy.words as f64 * (wordlist.len() as f64).log2(),
+    })
+}
+
+/// A uniformly random item of `items`, which must not be empty.
+fn pick<T>(items: &[T]) -> &T {
+    &items[random_below(items.len())]
+}
+
+/// Fisher-Yates shuffle.
+fn shuffle<T>(items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        items.swap(i, random_below(i + 1));
+    }
+}
+
+/// A uniformly random number below `bound`, rejecting the values that would favour
+/// the smaller numbers.
+fn random_below(bound: usize) -> usize {
+    let bound = bound as u64;
+    let zone = u64::MAX - u64::MAX % bound;
+    loop {
+        let value = u64::from_le_bytes(security::random_bytes());
+        if value < zone {
+            return (value % bound) as usize;
+        }
+    }
+}
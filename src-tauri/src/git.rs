@@ -0,0 +1,211 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Name the configured remote is kept under.
+pub const REMOTE: &str = "origin";
+
+/// First line of the .gitignore Sklad writes, telling it apart from one of the user's.
+const GITIGNORE_HEADER: &str = "# Managed by Sklad: only the vault is tracked";
+/// Only the vault and its attachment blobs (`attachments::BLOB_DIR_NAME`) are tracked;
+/// settings and local state in the same folder stay out.
+const GITIGNORE: &str = "# Managed by Sklad: only the vault is tracked
+*
+!.gitignore
+!sklad.json
+!attachments/
+!attachments/**
+";
+
+/// How the local branch relates to the fetched remote one.
+pub enum Relation {
+    UpToDate,
+    Behind,
+    /// Both have new commits; holds the merge base unless the histories are unrelated.
+    Diverged(Option<String>),
+}
+
+/// A vault folder under git, driven through the `git` executable so that the user's
+/// credentials and SSH setup apply.
+pub struct GitRepo {
+    dir: PathBuf,
+}
+
+impl GitRepo {
+    /// Opens the repository in `dir`, creating it first if needed.
+    pub fn init(dir: &Path) -> io::Result<Self> {
+        let repo = Self {
+            dir: dir.to_path_buf(),
+        };
+        let exists = dir.join(".git").exists();
+        if !exists {
+            repo.run(&["init", "-q"])?;
+        }
+        // Repositories made by older versions ignore files that are tracked now
+        let gitignore = std::fs::read_to_string(dir.join(".gitignore")).ok();
+        if gitignore
+            .as_deref()
+            .is_none_or(|content| content.starts_with(GITIGNORE_HEADER) && content != GITIGNORE)
+        {
+            std::fs::write(dir.join(".gitignore"), GITIGNORE)?;
+        }
+        if exists {
+            return Ok(repo);
+        }
+        // Commits fail without an identity, so fall back to one if git has none configured
+        if repo.run(&["config", "user.email"]).is_err() {
+            repo.run(&["config", "user.name", "Sklad"])?;
+            repo.run(&["config", "user.email", "sklad@localhost"])?;
+        }
+        Ok(repo)
+    }
+
+    /// Commits `file` and the attachment blobs if they changed, or concludes a merge in
+    /// progress.
+    /// Returns whether a commit was made.
+    pub fn commit(&self, file: &str, message: &str) -> io::Result<bool> {
+        let mut add = vec!["add", "--", file];
+        if self.dir.join(".gitignore").exists() {
+            add.push(".gitignore");
+        }
+        if self.dir.join(crate::attachments::BLOB_DIR_NAME).exists() {
+            add.push(crate::attachments::BLOB_DIR_NAME);
+        }
+        self.run(&add)?;
+        let staged = !self.succeeds(&["diff", "--cached", "--quiet"])?;
+        let merging = self.succeeds(&["rev-parse", "-q", "--verify", "MERGE_HEAD"])?;
+        if !staged && !merging {
+            return Ok(false);
+        }
+        self.run(&["commit", "-q", "-m", message])?;
+        Ok(true)
+    }
+
+    /// Points `REMOTE` at `url`, or removes it.
+    pub fn set_remote(&self, url: Option<&str>) -> io::Result<()> {
+        let exists = self.succeeds(&["remote", "get-url", REMOTE])?;
+        match (url, exists) {
+            (Some(url), true) => self.run(&["remote", "set-url", REMOTE, url]).map(drop),
+            (Some(url), false) => self.run(&["remote", "add", REMOTE, url]).map(drop),
+            (None, true) => self.run(&["remote", "remove", REMOTE]).map(drop),
+            (None, false) => Ok(()),
+        }
+    }
+
+    fn has_commits(&self) -> io::Result<bool> {
+        self.succeeds(&["rev-parse", "-q", "--verify", "HEAD"])
+    }
+
+    fn branch(&self) -> io::Result<String> {
+        Ok(self
+            .run(&["symbolic-ref", "--short", "HEAD"])?
+            .trim()
+            .to_string())
+    }
+
+    /// Fetches the remote counterpart of the current branch and returns its commit,
+    /// or `None` if the remote doesn't have the branch yet.
+    pub fn fetch(&self) -> io::Result<Option<String>> {
+        let branch = self.branch()?;
+        if self
+            .run(&["ls-remote", "--heads", REMOTE, &branch])?
+            .trim()
+            .is_empty()
+        {
+            return Ok(None);
+        }
+        self.run(&["fetch", "-q", REMOTE, &branch])?;
+        Ok(Some(
+            self.run(&["rev-parse", "FETCH_HEAD"])?.trim().to_string(),
+        ))
+    }
+
+    pub fn relation(&self, remote: &str) -> io::Result<Relation> {
+        if !self.has_commits()? {
+            return Ok(Relation::Behind);
+        }
+        if self.succeeds(&["merge-base", "--is-ancestor", remote, "HEAD"])? {
+            return Ok(Relation::UpToDate);
+        }
+        if self.succeeds(&["merge-base", "--is-ancestor", "HEAD", remote])? {
+            return Ok(Relation::Behind);
+        }
+        let base = self
+            .run(&["merge-base", "HEAD", remote])
+            .ok()
+            .map(|base| base.trim().to_string());
+        Ok(Relation::Diverged(base))
+    }
+
+    pub fn fast_forward(&self, remote: &str) -> io::Result<()> {
+        if !self.has_commits()? {
+            // Only untracked files like our .gitignore can be in the way, and git would
+            // refuse to overwrite them when merging
+            return self.run(&["reset", "-q", "--hard", remote]).map(drop);
+        }
+        self.run(&["merge", "-q", "--ff-only", remote]).map(drop)
+    }
+
+    /// Starts a merge commit with `remote` as second parent, leaving the working tree
+    /// as is so the caller can write the merged vault and `commit` it.
+    pub fn start_merge(&self, remote: &str) -> io::Result<()> {
+        self.run(&[
+            "merge",
+            "-q",
+            "--no-commit",
+            "--no-ff",
+            "--allow-unrelated-histories",
+            "-s",
+            "ours",
+            remote,
+        ])
+        .map(drop)
+    }
+
+    pub fn abort_merge(&self) -> io::Result<()> {
+        self.run(&["merge", "--abort"]).map(drop)
+    }
+
+    /// Contents of `file` at `rev`, if it exists there.
+    pub fn show(&self, rev: &str, file: &str) -> io::Result<Option<Vec<u8>>> {
+        let output = self.output(&["show", &format!("{}:{}", rev, file)])?;
+        Ok(output.status.success().then_some(output.stdout))
+    }
+
+    pub fn push(&self) -> io::Result<()> {
+        self.run(&["push", "-q", "-u", REMOTE, "HEAD"]).map(drop)
+    }
+
+    fn output(&self, args: &[&str]) -> io::Result<Output> {
+        let mut command = Command::new("git");
+        command
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            // Fail instead of waiting for a password nobody can type
+            .env("GIT_TERMINAL_PROMPT", "0");
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+            command.creation_flags(CREATE_NO_WINDOW);
+        }
+        command.output()
+    }
+
+    fn succeeds(&self, args: &[&str]) -> io::Result<bool> {
+        Ok(self.output(args)?.status.success())
+    }
+
+    fn run(&self, args: &[&str]) -> io::Result<String> {
+        let output = self.output(args)?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "git {} failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
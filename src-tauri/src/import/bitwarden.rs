@@ -0,0 +1,120 @@
+use super::{
+    has_extension, login_item, read_file, split_group_path, ImportFormat, ImportedItem, Importer,
+};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Bitwarden unencrypted JSON export.
+pub struct BitwardenJson;
+
+const TYPE_LOGIN: u64 = 1;
+const TYPE_CARD: u64 = 3;
+
+impl Importer for BitwardenJson {
+    fn format(&self) -> ImportFormat {
+        ImportFormat::BitwardenJson
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        has_extension(path, &["json"])
+            && read_file(path)
+                .ok()
+                .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+                .is_some_and(|json| json.get("items").is_some_and(Value::is_array))
+    }
+
+    fn parse(&self, path: &Path) -> Result<Vec<ImportedItem>, String> {
+        let json: Value = serde_json::from_str(&read_file(path)?)
+            .map_err(|e| format!("Invalid Bitwarden JSON: {}", e))?;
+
+        if json.get("encrypted").and_then(Value::as_bool) == Some(true) {
+            return Err(
+                "Encrypted Bitwarden exports are not supported. Export as unencrypted JSON.".into(),
+            );
+        }
+
+        let folders: HashMap<&str, &str> = json
+            .get("folders")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|f| Some((f.get("id")?.as_str()?, f.get("name")?.as_str()?)))
+            .collect();
+
+        let items = json
+            .get("items")
+            .and_then(Value::as_array)
+            .ok_or("Invalid Bitwarden JSON: missing items")?;
+
+        Ok(items
+            .iter()
+            .map(|entry| {
+                let folder = entry
+                    .get("folderId")
+                    .and_then(Value::as_str)
+                    .and_then(|id| folders.get(id))
+                    .map(|name| split_group_path(name))
+                    .unwrap_or_default();
+                let name = text(entry.get("name"));
+                let notes = text(entry.get("notes"));
+
+                let mut item = match entry.get("type").and_then(Value::as_u64) {
+                    Some(TYPE_LOGIN) => {
+                        let login = entry.get("login");
+                        let field = |key: &str| text(login.and_then(|l| l.get(key)));
+                        let url = login
+                            .and_then(|l| l.get("uris"))
+                            .and_then(Value::as_array)
+                            .and_then(|uris| uris.first())
+                            .map(|uri| text(uri.get("uri")))
+                            .unwrap_or("");
+                        login_item(
+                            folder,
+                            name,
+                            field("username"),
+                            field("password"),
+                            url,
+                            notes,
+                            field("totp"),
+                        )
+                    }
+                    Some(TYPE_CARD) => {
+                        let card = entry.get("card");
+                        let field = |key: &str| text(card.and_then(|c| c.get(key)));
+                        let mut item = login_item(folder, name, "", "", "", notes, "");
+                        item.push_field("Cardholder", field("cardholderName"), false);
+                        item.push_field("Number", field("number"), true);
+                        let expiry = format!("{}/{}", field("expMonth"), field("expYear"));
+                        item.push_field("Expiry", expiry.trim_matches('/'), false);
+                        item.push_field("Code", field("code"), true);
+                        item
+                    }
+                    // Secure notes, identities and unknown types keep their notes only
+                    _ => login_item(folder, name, "", "", "", notes, ""),
+                };
+
+                for custom in entry
+                    .get("fields")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                {
+                    // Bitwarden field type 1 is "hidden"
+                    let hidden = custom.get("type").and_then(Value::as_u64) == Some(1);
+                    let label = match text(custom.get("name")) {
+                        "" => "Field",
+                        name => name,
+                    };
+                    item.push_field(label, text(custom.get("value")), hidden);
+                }
+
+                item
+            })
+            .collect())
+    }
+}
+
+fn text(value: Option<&Value>) -> &str {
+    value.and_then(Value::as_str).unwrap_or("")
+}
@@ -0,0 +1,61 @@
+use super::{file_stem, has_extension, read_file, ImportFormat, ImportedItem, Importer};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Espanso match file (`match/*.yml`).
+pub struct Espanso;
+
+#[derive(Deserialize)]
+struct MatchFile {
+    #[serde(default)]
+    matches: Vec<Match>,
+}
+
+#[derive(Deserialize)]
+struct Match {
+    trigger: Option<String>,
+    #[serde(default)]
+    triggers: Vec<String>,
+    replace: Option<String>,
+    markdown: Option<String>,
+    html: Option<String>,
+}
+
+impl Importer for Espanso {
+    fn format(&self) -> ImportFormat {
+        ImportFormat::Espanso
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        has_extension(path, &["yml", "yaml"])
+            && read_file(path).is_ok_and(|content| content.contains("matches:"))
+    }
+
+    fn parse(&self, path: &Path) -> Result<Vec<ImportedItem>, String> {
+        let file: MatchFile = serde_yaml::from_str(&read_file(path)?)
+            .map_err(|e| format!("Invalid Espanso match file: {}", e))?;
+        let folder = vec![file_stem(path)];
+
+        Ok(file
+            .matches
+            .into_iter()
+            .filter_map(|m| {
+                let value = m.replace.or(m.markdown).or(m.html)?;
+                let triggers: Vec<String> = m.trigger.into_iter().chain(m.triggers).collect();
+                let title = if triggers.is_empty() {
+                    "Untitled".to_string()
+                } else {
+                    triggers.join(", ")
+                };
+
+                let mut item = ImportedItem {
+                    folder: folder.clone(),
+                    title,
+                    fields: Vec::new(),
+                };
+                item.push_field("Replace", &value, false);
+                Some(item)
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,148 @@
+use super::{
+    csv_headers, has_extension, login_item, read_file, split_group_path, ImportFormat,
+    ImportedItem, Importer,
+};
+use std::path::Path;
+
+/// KeePass 2.x "KeePass XML (2.x)" export.
+pub struct KeepassXml;
+
+impl Importer for KeepassXml {
+    fn format(&self) -> ImportFormat {
+        ImportFormat::KeepassXml
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        has_extension(path, &["xml"])
+            && read_file(path).is_ok_and(|content| content.contains("<KeePassFile"))
+    }
+
+    fn parse(&self, path: &Path) -> Result<Vec<ImportedItem>, String> {
+        let content = read_file(path)?;
+        let doc = roxmltree::Document::parse(&content)
+            .map_err(|e| format!("Invalid KeePass XML: {}", e))?;
+
+        let root = doc
+            .descendants()
+            .find(|n| n.has_tag_name("Root"))
+            .ok_or("Invalid KeePass XML: missing Root element")?;
+
+        let mut items = Vec::new();
+        // The top-level group is the database itself, so its name is not used as a folder
+        for group in root.children().filter(|n| n.has_tag_name("Group")) {
+            collect_group(group, &[], &mut items);
+        }
+        Ok(items)
+    }
+}
+
+fn child_text<'a>(node: roxmltree::Node<'a, '_>, tag: &str) -> &'a str {
+    node.children()
+        .find(|n| n.has_tag_name(tag))
+        .and_then(|n| n.text())
+        .unwrap_or("")
+}
+
+fn collect_group(group: roxmltree::Node, path: &[String], items: &mut Vec<ImportedItem>) {
+    for child in group.children() {
+        if child.has_tag_name("Entry") {
+            items.push(parse_entry(child, path));
+        } else if child.has_tag_name("Group") {
+            let name = child_text(child, "Name");
+            if name == "Recycle Bin" {
+                continue;
+            }
+            let mut sub_path = path.to_vec();
+            sub_path.push(name.to_string());
+            collect_group(child, &sub_path, items);
+        }
+    }
+}
+
+fn parse_entry(entry: roxmltree::Node, path: &[String]) -> ImportedItem {
+    let mut title = String::new();
+    let mut item = ImportedItem {
+        folder: path.to_vec(),
+        ..Default::default()
+    };
+
+    // Only direct <String> children; <History> holds older copies of the entry
+    for string in entry.children().filter(|n| n.has_tag_name("String")) {
+        let key = child_text(string, "Key");
+        let value_node = string.children().find(|n| n.has_tag_name("Value"));
+        let value = value_node.and_then(|n| n.text()).unwrap_or("");
+        let protected = value_node.is_some_and(|n| n.attribute("ProtectInMemory") == Some("True"));
+
+        match key {
+            "Title" => title = value.to_string(),
+            "UserName" => item.push_field("Username", value, false),
+            "Password" => item.push_field("Password", value, true),
+            "URL" => item.push_field("URL", value, false),
+            "Notes" => item.push_field("Notes", value, false),
+            "otp" | "TOTP Seed" => item.push_field("TOTP", value, true),
+            other => item.push_field(other, value, protected),
+        }
+    }
+
+    item.title = if title.trim().is_empty() {
+        "Untitled".to_string()
+    } else {
+        title
+    };
+    item
+}
+
+/// KeePassXC "CSV file" export.
+pub struct KeepassxcCsv;
+
+impl Importer for KeepassxcCsv {
+    fn format(&self) -> ImportFormat {
+        ImportFormat::KeepassxcCsv
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        has_extension(path, &["csv"])
+            && csv_headers(path).is_some_and(|headers| {
+                headers.iter().any(|h| h == "group") && headers.iter().any(|h| h == "title")
+            })
+    }
+
+    fn parse(&self, path: &Path) -> Result<Vec<ImportedItem>, String> {
+        let mut reader = csv::Reader::from_path(path).map_err(|e| e.to_string())?;
+        let headers: Vec<String> = reader
+            .headers()
+            .map_err(|e| e.to_string())?
+            .iter()
+            .map(|h| h.trim().to_lowercase())
+            .collect();
+        let column = |name: &str| headers.iter().position(|h| h == name);
+        let (group, title, username, password, url, notes, totp) = (
+            column("group"),
+            column("title"),
+            column("username"),
+            column("password"),
+            column("url"),
+            column("notes"),
+            column("totp"),
+        );
+
+        let mut items = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(|e| e.to_string())?;
+            let get = |index: Option<usize>| index.and_then(|i| record.get(i)).unwrap_or("");
+
+            // KeePassXC prefixes every group with the root group name
+            let folder = split_group_path(get(group)).into_iter().skip(1).collect();
+            items.push(login_item(
+                folder,
+                get(title),
+                get(username),
+                get(password),
+                get(url),
+                get(notes),
+                get(totp),
+            ));
+        }
+        Ok(items)
+    }
+}
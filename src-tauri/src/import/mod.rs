@@ -0,0 +1,309 @@
+mod bitwarden;
+mod espanso;
+mod keepass;
+mod onepassword;
+pub mod sklad_bundle;
+mod text_folder;
+mod vscode;
+
+use crate::models::{Node, NodeType};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    KeepassXml,
+    KeepassxcCsv,
+    BitwardenJson,
+    OnepasswordCsv,
+    Espanso,
+    VscodeSnippets,
+    TextFolder,
+    /// Encrypted bundle written by `export_data`, merged by [`sklad_bundle::merge`].
+    SkladBundle,
+}
+
+/// What to do when an imported snippet has the same label as one already in the target folder.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    #[default]
+    Skip,
+    Overwrite,
+    KeepBoth,
+}
+
+/// One record from a source file, e.g. a password manager login.
+#[derive(Debug, Clone, Default)]
+pub struct ImportedItem {
+    /// Folder labels from the vault root down to the item.
+    pub folder: Vec<String>,
+    pub title: String,
+    pub fields: Vec<ImportedField>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportedField {
+    pub label: String,
+    pub value: String,
+    pub secret: bool,
+}
+
+impl ImportedItem {
+    pub fn push_field(&mut self, label: &str, value: &str, secret: bool) {
+        if !value.trim().is_empty() {
+            self.fields.push(ImportedField {
+                label: label.to_string(),
+                value: value.to_string(),
+                secret,
+            });
+        }
+    }
+}
+
+/// A parser for one external format.
+pub trait Importer {
+    fn format(&self) -> ImportFormat;
+    /// Cheap check used when the caller did not name a format.
+    fn detect(&self, path: &Path) -> bool;
+    fn parse(&self, path: &Path) -> Result<Vec<ImportedItem>, String>;
+}
+
+fn importers() -> Vec<Box<dyn Importer>> {
+    vec![
+        Box::new(keepass::KeepassXml),
+        Box::new(keepass::KeepassxcCsv),
+        Box::new(bitwarden::BitwardenJson),
+        Box::new(onepassword::OnepasswordCsv),
+        Box::new(espanso::Espanso),
+        Box::new(vscode::VscodeSnippets),
+        Box::new(text_folder::TextFolder),
+    ]
+}
+
+/// Parses `path` with the given format, or the first importer that recognises it.
+pub fn parse(
+    path: &Path,
+    format: Option<ImportFormat>,
+) -> Result<(ImportFormat, Vec<ImportedItem>), String> {
+    let importer = importers()
+        .into_iter()
+        .find(|importer| match format {
+            Some(format) => importer.format() == format,
+            None => importer.detect(path),
+        })
+        .ok_or("Unrecognised import format")?;
+
+    let items = importer.parse(path)?;
+    Ok((importer.format(), items))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportPreviewEntry {
+    pub path: Vec<String>,
+    pub label: String,
+    #[serde(rename = "isSecret")]
+    pub is_secret: bool,
+    pub conflict: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportPreview {
+    pub format: ImportFormat,
+    pub entries: Vec<ImportPreviewEntry>,
+    #[serde(rename = "snippetCount")]
+    pub snippet_count: usize,
+    #[serde(rename = "secretCount")]
+    pub secret_count: usize,
+    #[serde(rename = "conflictCount")]
+    pub conflict_count: usize,
+}
+
+/// Merges `items` into `nodes`, creating folders by label. Secrets are added in plain text
+/// (`value` set), so the caller must encrypt before saving.
+pub fn merge(
+    nodes: &mut Vec<Node>,
+    format: ImportFormat,
+    items: &[ImportedItem],
+    strategy: ConflictStrategy,
+) -> ImportPreview {
+    let mut preview = ImportPreview {
+        format,
+        entries: Vec::new(),
+        snippet_count: 0,
+        secret_count: 0,
+        conflict_count: 0,
+    };
+
+    for item in items {
+        if item.fields.is_empty() {
+            continue;
+        }
+
+        let mut path = item.folder.clone();
+        let single = item.fields.len() == 1;
+        if !single {
+            path.push(item.title.clone());
+        }
+
+        let (siblings, parent_id) = ensure_folder(nodes, None, &path);
+
+        for field in &item.fields {
+            let label = if single { &item.title } else { &field.label };
+            let conflict = insert_snippet(siblings, parent_id.clone(), label, field, strategy);
+
+            preview.snippet_count += 1;
+            preview.secret_count += usize::from(field.secret);
+            preview.conflict_count += usize::from(conflict);
+            preview.entries.push(ImportPreviewEntry {
+                path: path.clone(),
+                label: label.clone(),
+                is_secret: field.secret,
+                conflict,
+            });
+        }
+    }
+
+    preview
+}
+
+/// Walks (and creates) the folder chain `path`, returning its children and id.
+fn ensure_folder<'a>(
+    nodes: &'a mut Vec<Node>,
+    parent_id: Option<String>,
+    path: &[String],
+) -> (&'a mut Vec<Node>, Option<String>) {
+    let Some((name, rest)) = path.split_first() else {
+        return (nodes, parent_id);
+    };
+
+    let index = match nodes
+        .iter()
+        .position(|n| matches!(n.node_type, NodeType::Folder) && n.label == *name)
+    {
+        Some(index) => index,
+        None => {
+            nodes.push(Node::new_folder(name.clone(), parent_id));
+            nodes.len() - 1
+        }
+    };
+
+    let folder = &mut nodes[index];
+    let folder_id = Some(folder.id.clone());
+    ensure_folder(
+        folder.children.get_or_insert_with(Vec::new),
+        folder_id,
+        rest,
+    )
+}
+
+/// Adds one snippet according to `strategy`. Returns whether the label was already taken.
+fn insert_snippet(
+    siblings: &mut Vec<Node>,
+    parent_id: Option<String>,
+    label: &str,
+    field: &ImportedField,
+    strategy: ConflictStrategy,
+) -> bool {
+    let existing = siblings
+        .iter()
+        .position(|n| matches!(n.node_type, NodeType::Snippet) && n.label == label);
+
+    match (existing, strategy) {
+        (None, _) => {
+            siblings.push(Node::new_snippet(
+                label,
+                parent_id,
+                field.value.clone(),
+                field.secret,
+            ));
+            false
+        }
+        (Some(_), ConflictStrategy::Skip) => true,
+        (Some(index), ConflictStrategy::Overwrite) => {
+            let node = &mut siblings[index];
+            node.value = Some(field.value.clone());
+            node.encrypted_value = None;
+            node.is_secret = Some(field.secret);
+            true
+        }
+        (Some(_), ConflictStrategy::KeepBoth) => {
+            let label = unique_label(siblings, label);
+            siblings.push(Node::new_snippet(
+                label,
+                parent_id,
+                field.value.clone(),
+                field.secret,
+            ));
+            true
+        }
+    }
+}
+
+fn unique_label(siblings: &[Node], label: &str) -> String {
+    (2..)
+        .map(|n| format!("{} ({})", label, n))
+        .find(|candidate| !siblings.iter().any(|n| n.label == *candidate))
+        .unwrap()
+}
+
+/// Builds the usual username/password/URL/notes fields of a login record.
+pub(crate) fn login_item(
+    folder: Vec<String>,
+    title: &str,
+    username: &str,
+    password: &str,
+    url: &str,
+    notes: &str,
+    totp: &str,
+) -> ImportedItem {
+    let mut item = ImportedItem {
+        folder,
+        title: if title.trim().is_empty() {
+            "Untitled".to_string()
+        } else {
+            title.trim().to_string()
+        },
+        fields: Vec::new(),
+    };
+    item.push_field("Username", username, false);
+    item.push_field("Password", password, true);
+    item.push_field("URL", url, false);
+    item.push_field("Notes", notes, false);
+    item.push_field("TOTP", totp, true);
+    item
+}
+
+/// Splits a `/`-separated group path, dropping empty segments.
+pub(crate) fn split_group_path(group: &str) -> Vec<String> {
+    group
+        .split('/')
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+pub(crate) fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+pub(crate) fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Imported".to_string())
+}
+
+pub(crate) fn read_file(path: &Path) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+/// Returns the lower-cased header row of a CSV file.
+pub(crate) fn csv_headers(path: &Path) -> Option<Vec<String>> {
+    let mut reader = csv::Reader::from_path(path).ok()?;
+    let headers = reader.headers().ok()?;
+    Some(headers.iter().map(|h| h.trim().to_lowercase()).collect())
+}
@@ -0,0 +1,62 @@
+use super::{
+    csv_headers, has_extension, login_item, split_group_path, ImportFormat, ImportedItem, Importer,
+};
+use std::path::Path;
+
+/// 1Password CSV export (1Password 7 and 8 column layouts).
+pub struct OnepasswordCsv;
+
+impl Importer for OnepasswordCsv {
+    fn format(&self) -> ImportFormat {
+        ImportFormat::OnepasswordCsv
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        has_extension(path, &["csv"])
+            && csv_headers(path).is_some_and(|headers| {
+                headers.iter().any(|h| h == "title")
+                    && headers.iter().any(|h| h == "password")
+                    && !headers.iter().any(|h| h == "group")
+            })
+    }
+
+    fn parse(&self, path: &Path) -> Result<Vec<ImportedItem>, String> {
+        let mut reader = csv::Reader::from_path(path).map_err(|e| e.to_string())?;
+        let headers: Vec<String> = reader
+            .headers()
+            .map_err(|e| e.to_string())?
+            .iter()
+            .map(|h| h.trim().to_lowercase())
+            .collect();
+        let column = |names: &[&str]| headers.iter().position(|h| names.contains(&h.as_str()));
+        let title = column(&["title"]);
+        let username = column(&["username"]);
+        let password = column(&["password"]);
+        let url = column(&["url", "website"]);
+        let notes = column(&["notes", "notesplain"]);
+        let totp = column(&["otpauth", "one-time password"]);
+        let vault = column(&["vault"]);
+        let archived = column(&["archived"]);
+
+        let mut items = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(|e| e.to_string())?;
+            let get = |index: Option<usize>| index.and_then(|i| record.get(i)).unwrap_or("");
+
+            if get(archived).eq_ignore_ascii_case("true") {
+                continue;
+            }
+
+            items.push(login_item(
+                split_group_path(get(vault)),
+                get(title),
+                get(username),
+                get(password),
+                get(url),
+                get(notes),
+                get(totp),
+            ));
+        }
+        Ok(items)
+    }
+}
@@ -0,0 +1,129 @@
+use super::{ConflictStrategy, ImportFormat, ImportPreview, ImportPreviewEntry};
+use crate::models::{Node, NodeType};
+use std::collections::HashSet;
+
+/// Merges the node tree of a decrypted bundle. Unlike the label-based [`super::merge`],
+/// bundles keep their node ids, so conflicts are detected per top-level node by id.
+/// Secrets are left in plain text for the caller to encrypt.
+pub fn merge(
+    nodes: &mut Vec<Node>,
+    bundle_nodes: Vec<Node>,
+    strategy: ConflictStrategy,
+) -> ImportPreview {
+    let mut preview = ImportPreview {
+        format: ImportFormat::SkladBundle,
+        entries: Vec::new(),
+        snippet_count: 0,
+        secret_count: 0,
+        conflict_count: 0,
+    };
+
+    for mut incoming in bundle_nodes {
+        let mut existing_ids = HashSet::new();
+        collect_ids(nodes, &mut existing_ids);
+
+        let mut incoming_ids = HashSet::new();
+        collect_ids(std::slice::from_ref(&incoming), &mut incoming_ids);
+        let conflict = !existing_ids.is_disjoint(&incoming_ids);
+
+        let snippets_before = preview.entries.len();
+        record(&incoming, &mut Vec::new(), conflict, &mut preview);
+        if conflict {
+            preview.conflict_count += preview.entries.len() - snippets_before;
+        }
+
+        match (conflict, strategy) {
+            (false, _) => {
+                incoming.parent_id = None;
+                nodes.push(incoming);
+            }
+            (true, ConflictStrategy::Skip) => {}
+            (true, ConflictStrategy::Overwrite) => {
+                match crate::data_manager::DataManager::find_node_by_id_mut(nodes, &incoming.id) {
+                    Some(existing) => {
+                        // Ids outside the subtree being replaced must stay unique
+                        let mut replaced_ids = HashSet::new();
+                        collect_ids(std::slice::from_ref(existing), &mut replaced_ids);
+                        let taken: HashSet<String> =
+                            existing_ids.difference(&replaced_ids).cloned().collect();
+
+                        // Replace in place, keeping the node where the user has moved it
+                        incoming.parent_id = existing.parent_id.clone();
+                        let id = incoming.id.clone();
+                        if let Some(children) = incoming.children.as_mut() {
+                            reassign_colliding(children, &id, &taken);
+                        }
+                        *existing = incoming;
+                    }
+                    None => {
+                        // Only descendants collide, so add it as a new tree
+                        reassign_ids(&mut incoming, None);
+                        nodes.push(incoming);
+                    }
+                }
+            }
+            (true, ConflictStrategy::KeepBoth) => {
+                reassign_ids(&mut incoming, None);
+                nodes.push(incoming);
+            }
+        }
+    }
+
+    preview
+}
+
+fn collect_ids(nodes: &[Node], ids: &mut HashSet<String>) {
+    for node in nodes {
+        ids.insert(node.id.clone());
+        if let Some(children) = &node.children {
+            collect_ids(children, ids);
+        }
+    }
+}
+
+fn record(node: &Node, path: &mut Vec<String>, conflict: bool, preview: &mut ImportPreview) {
+    match node.node_type {
+        NodeType::Folder => {
+            path.push(node.label.clone());
+            for child in node.children.as_deref().unwrap_or_default() {
+                record(child, path, conflict, preview);
+            }
+            path.pop();
+        }
+        NodeType::Snippet => {
+            let is_secret = node.is_secret.unwrap_or(false);
+            preview.snippet_count += 1;
+            preview.secret_count += usize::from(is_secret);
+            preview.entries.push(ImportPreviewEntry {
+                path: path.clone(),
+                label: node.label.clone(),
+                is_secret,
+                conflict,
+            });
+        }
+        NodeType::Separator | NodeType::Attachment => {}
+    }
+}
+
+/// Gives `node` and its whole subtree fresh ids.
+fn reassign_ids(node: &mut Node, parent_id: Option<String>) {
+    node.id = uuid::Uuid::new_v4().to_string();
+    node.parent_id = parent_id;
+    let id = node.id.clone();
+    for child in node.children.iter_mut().flatten() {
+        reassign_ids(child, Some(id.clone()));
+    }
+}
+
+/// Gives fresh ids to the subtrees whose root id is in `taken`.
+fn reassign_colliding(children: &mut [Node], parent_id: &str, taken: &HashSet<String>) {
+    for child in children {
+        child.parent_id = Some(parent_id.to_string());
+        if taken.contains(&child.id) {
+            reassign_ids(child, Some(parent_id.to_string()));
+        } else if let Some(grandchildren) = child.children.as_mut() {
+            let id = child.id.clone();
+            reassign_colliding(grandchildren, &id, taken);
+        }
+    }
+}
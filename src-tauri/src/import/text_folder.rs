@@ -0,0 +1,79 @@
+use super::{ImportFormat, ImportedItem, Importer};
+use std::fs;
+use std::path::Path;
+
+/// A directory tree of plain text files, one snippet per file.
+pub struct TextFolder;
+
+/// Larger files are skipped; they are unlikely to be snippets.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+impl Importer for TextFolder {
+    fn format(&self) -> ImportFormat {
+        ImportFormat::TextFolder
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn parse(&self, path: &Path) -> Result<Vec<ImportedItem>, String> {
+        if !path.is_dir() {
+            return Err(format!("{} is not a folder", path.display()));
+        }
+
+        let root_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Imported".to_string());
+
+        let mut items = Vec::new();
+        collect_dir(path, &[root_name], &mut items)?;
+        Ok(items)
+    }
+}
+
+fn collect_dir(dir: &Path, folder: &[String], items: &mut Vec<ImportedItem>) -> Result<(), String> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+        .filter_map(Result::ok)
+        .collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            let mut sub_folder = folder.to_vec();
+            sub_folder.push(name);
+            collect_dir(&path, &sub_folder, items)?;
+        } else if metadata.len() <= MAX_FILE_SIZE {
+            // Binary or non-UTF-8 files are skipped
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let title = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or(name);
+
+            let mut item = ImportedItem {
+                folder: folder.to_vec(),
+                title,
+                fields: Vec::new(),
+            };
+            item.push_field("Content", &content, false);
+            items.push(item);
+        }
+    }
+
+    Ok(())
+}
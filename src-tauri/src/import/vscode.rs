@@ -0,0 +1,112 @@
+use super::{file_stem, has_extension, read_file, ImportFormat, ImportedItem, Importer};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// VS Code user snippets (`*.code-snippets` or a language `*.json` snippet file).
+pub struct VscodeSnippets;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrList {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Deserialize)]
+struct Snippet {
+    prefix: Option<StringOrList>,
+    body: StringOrList,
+}
+
+impl Importer for VscodeSnippets {
+    fn format(&self) -> ImportFormat {
+        ImportFormat::VscodeSnippets
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        has_extension(path, &["code-snippets"])
+            || (has_extension(path, &["json"])
+                && read_file(path).is_ok_and(|content| {
+                    serde_json::from_str::<BTreeMap<String, Snippet>>(&strip_jsonc(&content))
+                        .is_ok()
+                }))
+    }
+
+    fn parse(&self, path: &Path) -> Result<Vec<ImportedItem>, String> {
+        let snippets: BTreeMap<String, Snippet> =
+            serde_json::from_str(&strip_jsonc(&read_file(path)?))
+                .map_err(|e| format!("Invalid VS Code snippets file: {}", e))?;
+        let folder = vec![file_stem(path)];
+
+        Ok(snippets
+            .into_iter()
+            .map(|(name, snippet)| {
+                let body = match snippet.body {
+                    StringOrList::One(line) => line,
+                    StringOrList::Many(lines) => lines.join("\n"),
+                };
+                let title = match snippet.prefix {
+                    Some(StringOrList::One(prefix)) if !prefix.is_empty() => {
+                        format!("{} ({})", name, prefix)
+                    }
+                    _ => name,
+                };
+
+                let mut item = ImportedItem {
+                    folder: folder.clone(),
+                    title,
+                    fields: Vec::new(),
+                };
+                item.push_field("Body", &body, false);
+                item
+            })
+            .collect())
+    }
+}
+
+/// Removes `//` and `/* */` comments and trailing commas, which VS Code allows in snippet files.
+fn strip_jsonc(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => while chars.next_if(|&c| c != '\n').is_some() {},
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = '\0';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            (',', _) => {
+                let rest = chars.clone().find(|c| !c.is_whitespace());
+                if !matches!(rest, Some('}') | Some(']')) {
+                    out.push(c);
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
@@ -0,0 +1,282 @@
+use crate::models::Node;
+use crate::security::Key;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Kept next to the vault's other local files, so a sync tool doesn't carry it along.
+const STATE_FILE_NAME: &str = "integrity.json";
+/// The vault as last verified, to show what changed when verification fails.
+const VERIFIED_FILE_NAME: &str = "sklad.verified.json";
+/// HKDF info for the MAC key, so the vault key itself never keys the MAC.
+const MAC_INFO: &[u8] = b"sklad data file mac";
+
+/// What this device knows about the vault file beyond what is in it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct IntegrityState {
+    /// Digest of the tree as last verified, which `VERIFIED_FILE_NAME` holds.
+    #[serde(rename = "verifiedDigest", default)]
+    verified_digest: Option<String>,
+    /// Digest of the tree as this device last wrote it without the key, while locked.
+    #[serde(rename = "unsignedDigest", default)]
+    unsigned_digest: Option<String>,
+}
+
+impl IntegrityState {
+    fn load(dir: &Path) -> Self {
+        fs::read(dir.join(STATE_FILE_NAME))
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, dir: &Path) -> io::Result<()> {
+        fs::write(
+            dir.join(STATE_FILE_NAME),
+            serde_json::to_string_pretty(self)?,
+        )
+    }
+}
+
+/// The tree as the MAC covers it. Usage stats are left out: they live in a local file
+/// now, and vault files from before that got them updated even while locked.
+fn canonical(nodes: &[Node]) -> Vec<u8> {
+    fn strip(nodes: &mut [Node]) {
+        for node in nodes {
+            node.last_used_at = None;
+            node.use_count = None;
+            if let Some(children) = &mut node.children {
+                strip(children);
+            }
+        }
+    }
+
+    let mut nodes = nodes.to_vec();
+    strip(&mut nodes);
+    serde_json::to_vec(&nodes).expect("nodes always serialize")
+}
+
+fn digest(nodes: &[Node]) -> String {
+    hex::encode(Sha256::digest(canonical(nodes)))
+}
+
+fn mac_for(nodes: &[Node], key: &Key) -> Hmac<Sha256> {
+    let mut mac_key = [0u8; 32];
+    Hkdf::<Sha256>::new(None, key)
+        .expand(MAC_INFO, &mut mac_key)
+        .expect("32 bytes is a valid HKDF output length");
+    let mut mac = Hmac::<Sha256>::new_from_slice(&mac_key).expect("HMAC takes keys of any length");
+    mac.update(&canonical(nodes));
+    mac
+}
+
+/// HMAC-SHA256 of the tree, keyed from the vault key, for the header of sklad.json.
+pub fn mac(nodes: &[Node], key: &Key) -> String {
+    hex::encode(mac_for(nodes, key).finalize().into_bytes())
+}
+
+pub fn verify_mac(nodes: &[Node], key: &Key, mac: &str) -> bool {
+    hex::decode(mac).is_ok_and(|mac| mac_for(nodes, key).verify_slice(&mac).is_ok())
+}
+
+/// Records `nodes` as verified, keeping a copy to compare later versions against.
+pub fn record_verified(dir: &Path, nodes: &[Node]) -> io::Result<()> {
+    let mut state = IntegrityState::load(dir);
+    let digest = digest(nodes);
+    if state.verified_digest.as_deref() == Some(digest.as_str()) && state.unsigned_digest.is_none()
+    {
+        return Ok(());
+    }
+    fs::write(dir.join(VERIFIED_FILE_NAME), serde_json::to_vec(nodes)?)?;
+    state.verified_digest = Some(digest);
+    state.unsigned_digest = None;
+    state.save(dir)
+}
+
+/// Records that this device wrote `nodes` without the key, for vaults that had a MAC.
+pub fn record_unsigned(dir: &Path, nodes: &[Node]) -> io::Result<()> {
+    let mut state = IntegrityState::load(dir);
+    state.unsigned_digest = Some(digest(nodes));
+    state.save(dir)
+}
+
+/// Whether writes without the key have to be recorded, because the vault had a MAC.
+pub fn tracks_unsigned(dir: &Path) -> bool {
+    IntegrityState::load(dir).unsigned_digest.is_some()
+}
+
+/// Whether `nodes` is the vault as last verified or as this device last wrote it while
+/// locked, so a write over it can't hide changes someone else made.
+pub fn is_known(dir: &Path, nodes: &[Node]) -> bool {
+    let state = IntegrityState::load(dir);
+    let digest = Some(digest(nodes));
+    state.verified_digest == digest || state.unsigned_digest == digest
+}
+
+/// Whether `nodes` is what this device last wrote while locked.
+pub fn is_own_unsigned(dir: &Path, nodes: &[Node]) -> bool {
+    IntegrityState::load(dir).unsigned_digest == Some(digest(nodes))
+}
+
+/// Forgets the verified state, for a vault that gets a new key.
+pub fn forget(dir: &Path) -> io::Result<()> {
+    for name in [STATE_FILE_NAME, VERIFIED_FILE_NAME] {
+        match fs::remove_file(dir.join(name)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// The vault as last verified on this device, if it ever was.
+pub fn load_verified(dir: &Path) -> Option<Vec<Node>> {
+    fs::read(dir.join(VERIFIED_FILE_NAME))
+        .ok()
+        .and_then(|content| serde_json::from_slice(&content).ok())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One node that differs from the last verified vault.
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeChange {
+    pub id: String,
+    /// Labels from the root down, as in `Folder/Sub/Label`.
+    pub path: String,
+    pub kind: ChangeKind,
+    /// What changed about a changed node: "label", "location", "value", "secret", ...
+    pub fields: Vec<String>,
+    /// The plain value before and after, for changed public snippets.
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// The vault file failed verification.
+#[derive(Debug, Clone, Serialize)]
+pub struct TamperReport {
+    /// Changes since the last verified vault.
+    pub changes: Vec<NodeChange>,
+}
+
+struct Flat<'a> {
+    path: String,
+    parent: Option<&'a str>,
+    node: &'a Node,
+}
+
+fn flatten<'a>(
+    nodes: &'a [Node],
+    parent: Option<&'a Node>,
+    prefix: &str,
+    out: &mut Vec<(&'a str, Flat<'a>)>,
+) {
+    for node in nodes {
+        let path = if prefix.is_empty() {
+            node.label.clone()
+        } else {
+            format!("{}/{}", prefix, node.label)
+        };
+        out.push((
+            &node.id,
+            Flat {
+                path: path.clone(),
+                parent: parent.map(|parent| parent.id.as_str()),
+                node,
+            },
+        ));
+        flatten(
+            node.children.as_deref().unwrap_or_default(),
+            Some(node),
+            &path,
+            out,
+        );
+    }
+}
+
+/// What changed from `before` to `after`, node by node.
+pub fn diff(before: &[Node], after: &[Node]) -> Vec<NodeChange> {
+    let mut old = Vec::new();
+    flatten(before, None, "", &mut old);
+    let old: HashMap<_, _> = old.into_iter().collect();
+    let mut new = Vec::new();
+    flatten(after, None, "", &mut new);
+
+    let mut changes = Vec::new();
+    for (id, entry) in &new {
+        let Some(previous) = old.get(id) else {
+            changes.push(NodeChange {
+                id: id.to_string(),
+                path: entry.path.clone(),
+                kind: ChangeKind::Added,
+                fields: Vec::new(),
+                before: None,
+                after: None,
+            });
+            continue;
+        };
+
+        let (a, b) = (previous.node, entry.node);
+        let mut fields = Vec::new();
+        let mut changed = |field: &str, differs: bool| {
+            if differs {
+                fields.push(field.to_string());
+            }
+        };
+        changed("type", a.node_type != b.node_type);
+        changed("label", a.label != b.label);
+        changed("location", previous.parent != entry.parent);
+        changed("value", a.value != b.value);
+        changed(
+            "secret",
+            a.is_secret != b.is_secret || a.encrypted_value != b.encrypted_value,
+        );
+        changed("icon", a.icon != b.icon);
+        changed("tray", a.show_in_tray != b.show_in_tray);
+        changed("sorting", a.sort_mode != b.sort_mode);
+        changed("totp", a.totp != b.totp);
+        changed("history", a.history != b.history);
+        changed("expiry", a.expires_at != b.expires_at);
+        changed("attachment", a.attachment != b.attachment);
+        if fields.is_empty() {
+            continue;
+        }
+        let value_changed = fields.iter().any(|field| field == "value");
+        changes.push(NodeChange {
+            id: id.to_string(),
+            path: entry.path.clone(),
+            kind: ChangeKind::Changed,
+            fields,
+            before: a.value.clone().filter(|_| value_changed),
+            after: b.value.clone().filter(|_| value_changed),
+        });
+    }
+
+    let remaining: HashSet<_> = new.iter().map(|(id, _)| *id).collect();
+    let mut removed: Vec<_> = old
+        .iter()
+        .filter(|(id, _)| !remaining.contains(*id))
+        .map(|(id, entry)| NodeChange {
+            id: id.to_string(),
+            path: entry.path.clone(),
+            kind: ChangeKind::Removed,
+            fields: Vec::new(),
+            before: None,
+            after: None,
+        })
+        .collect();
+    removed.sort_by(|a, b| a.path.cmp(&b.path));
+    changes.extend(removed);
+    changes
+}
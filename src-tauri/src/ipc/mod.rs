@@ -0,0 +1,253 @@
+#[cfg(unix)]
+mod unix;
+#[cfg(windows)]
+mod windows;
+
+pub mod service;
+
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[cfg(unix)]
+use unix as transport;
+#[cfg(windows)]
+use windows as transport;
+
+/// Longest request line a client may send.
+const MAX_REQUEST_LEN: u64 = 64 * 1024;
+
+/// One request per line, as JSON, e.g. `{"command":"get","path":"Work/API key"}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    Get { path: String },
+    Search { query: String },
+    Copy { path: String },
+    Lock,
+    Status,
+}
+
+impl Request {
+    /// Whether the request reveals vault contents and so needs the client to be approved.
+    pub fn needs_approval(&self) -> bool {
+        matches!(
+            self,
+            Request::Get { .. } | Request::Search { .. } | Request::Copy { .. }
+        )
+    }
+}
+
+/// One response line per request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum Response {
+    Value {
+        value: String,
+    },
+    Matches {
+        paths: Vec<String>,
+    },
+    Status {
+        unlocked: bool,
+        #[serde(rename = "masterPasswordEnabled")]
+        master_password_enabled: bool,
+    },
+    Done,
+    Error {
+        message: String,
+    },
+}
+
+impl Response {
+    pub fn error(message: impl Into<String>) -> Self {
+        Response::Error {
+            message: message.into(),
+        }
+    }
+}
+
+/// The process on the other end of a connection, as reported by the OS.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClientInfo {
+    pub pid: Option<u32>,
+    pub executable: Option<PathBuf>,
+}
+
+impl ClientInfo {
+    /// Key under which an approval is remembered. Clients whose executable can't be
+    /// determined are only remembered for the lifetime of their process.
+    pub fn id(&self) -> String {
+        match (&self.executable, self.pid) {
+            (Some(executable), _) => executable.to_string_lossy().to_string(),
+            (None, Some(pid)) => format!("pid {}", pid),
+            (None, None) => "unknown client".to_string(),
+        }
+    }
+
+    /// Whether the executable runs scripts, e.g. python3 or node. Its path is then the
+    /// same for every script, so an approval by `id` must not be remembered.
+    pub fn is_interpreter(&self) -> bool {
+        let Some(stem) = self.executable.as_deref().and_then(Path::file_stem) else {
+            return false;
+        };
+        let stem = stem.to_string_lossy().to_lowercase();
+        // python3.12, ruby3.3, node18
+        let name = stem.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.' || c == '-');
+        INTERPRETERS.contains(&name)
+    }
+}
+
+/// Executables that run scripts, see `ClientInfo::is_interpreter`.
+const INTERPRETERS: &[&str] = &[
+    "bash",
+    "bun",
+    "cmd",
+    "cscript",
+    "dash",
+    "deno",
+    "env",
+    "fish",
+    "java",
+    "javaw",
+    "ksh",
+    "lua",
+    "luajit",
+    "mshta",
+    "node",
+    "nodejs",
+    "osascript",
+    "perl",
+    "php",
+    "powershell",
+    "pwsh",
+    "pypy",
+    "python",
+    "pythonw",
+    "r",
+    "rscript",
+    "ruby",
+    "sh",
+    "tclsh",
+    "wscript",
+    "zsh",
+];
+
+/// Answers requests for the server. Implemented by the app in [`service`].
+pub trait Handler: Send + Sync + 'static {
+    /// Asks the user whether `client` may read from the vault. Blocks until answered.
+    /// An approval holds for the rest of the connection it was asked on.
+    fn approve(&self, client: &ClientInfo) -> bool;
+    fn handle(&self, request: Request) -> Response;
+}
+
+/// Where the socket (or pipe) lives for a given app data dir.
+pub fn endpoint(data_dir: &Path) -> PathBuf {
+    transport::endpoint(data_dir)
+}
+
+/// A running server. Dropping it stops accepting connections and removes the socket.
+pub struct Server {
+    endpoint: PathBuf,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Server {
+    pub fn start<H: Handler>(endpoint: PathBuf, handler: Arc<H>) -> io::Result<Self> {
+        let listener = transport::Listener::bind(&endpoint)?;
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let accept_stopped = stopped.clone();
+        std::thread::spawn(move || loop {
+            let accepted = listener.accept();
+            if accept_stopped.load(Ordering::SeqCst) {
+                break;
+            }
+            match accepted {
+                Ok((stream, client)) => {
+                    let handler = handler.clone();
+                    std::thread::spawn(move || {
+                        let _ = serve_connection(stream, &client, handler.as_ref());
+                    });
+                }
+                // Connections from other users end up here
+                Err(e) => eprintln!("IPC connection rejected: {}", e),
+            }
+        });
+
+        Ok(Self { endpoint, stopped })
+    }
+
+    pub fn endpoint(&self) -> &Path {
+        &self.endpoint
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag
+        let _ = transport::connect(&self.endpoint);
+        transport::cleanup(&self.endpoint);
+    }
+}
+
+fn serve_connection<S: Read + Write>(
+    stream: S,
+    client: &ClientInfo,
+    handler: &dyn Handler,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut approved = false;
+
+    loop {
+        let mut line = String::new();
+        if reader.by_ref().take(MAX_REQUEST_LEN).read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        if !line.ends_with('\n') && line.len() as u64 == MAX_REQUEST_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Request too long",
+            ));
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Err(e) => Response::error(format!("Invalid request: {}", e)),
+            Ok(request) if request.needs_approval() && !approved => {
+                approved = handler.approve(client);
+                if approved {
+                    handler.handle(request)
+                } else {
+                    Response::error("Access denied")
+                }
+            }
+            Ok(request) => handler.handle(request),
+        };
+
+        let mut out = serde_json::to_vec(&response).map_err(io::Error::other)?;
+        out.push(b'\n');
+        let stream = reader.get_mut();
+        stream.write_all(&out)?;
+        stream.flush()?;
+    }
+}
+
+/// Sends one request to a running server and waits for the response.
+pub fn send(endpoint: &Path, request: &Request) -> io::Result<Response> {
+    let mut stream = transport::connect(endpoint)?;
+    let mut line = serde_json::to_vec(request).map_err(io::Error::other)?;
+    line.push(b'\n');
+    stream.write_all(&line)?;
+    stream.flush()?;
+
+    let mut reader = BufReader::new(stream);
+    let mut response = String::new();
+    reader.read_line(&mut response)?;
+    serde_json::from_str(&response).map_err(io::Error::other)
+}
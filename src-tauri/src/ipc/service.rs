@@ -0,0 +1,199 @@
+use super::{ClientInfo, Handler, Request, Response, Server};
+use crate::commands;
+use crate::data_manager::DataManager;
+use crate::models::{AppSettings, NodeType};
+use crate::security::VaultManager;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+/// How long a client waits for the user to answer the approval prompt.
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+pub struct IpcState {
+    server: Mutex<Option<Server>>,
+    /// Clients the user chose to always allow, so far this run. "Allow once" only
+    /// approves the connection it was asked on.
+    approved: Mutex<HashSet<String>>,
+    /// Open approval prompts by request id, with the client id they are about, if it
+    /// may be remembered.
+    pending: Mutex<HashMap<u32, (mpsc::Sender<bool>, Option<String>)>>,
+    next_request_id: AtomicU32,
+}
+
+#[derive(Clone, Serialize)]
+struct ApprovalRequest {
+    #[serde(rename = "requestId")]
+    request_id: u32,
+    client: String,
+    pid: Option<u32>,
+    /// False for interpreters, which can only be allowed once.
+    #[serde(rename = "canRemember")]
+    can_remember: bool,
+}
+
+struct AppHandler<R: Runtime> {
+    app: AppHandle<R>,
+}
+
+impl<R: Runtime> Handler for AppHandler<R> {
+    fn approve(&self, client: &ClientInfo) -> bool {
+        let id = client.id();
+        // An interpreter's path is the same for any script it runs, so it is asked each time
+        let can_remember = !client.is_interpreter();
+        let state = self.app.state::<IpcState>();
+        if can_remember
+            && (state.approved.lock().unwrap().contains(&id)
+                || DataManager::new(&self.app)
+                    .load_settings()
+                    .ipc_trusted_clients
+                    .contains(&id))
+        {
+            return true;
+        }
+
+        let request_id = state.next_request_id.fetch_add(1, Ordering::SeqCst);
+        let (sender, receiver) = mpsc::channel();
+        state
+            .pending
+            .lock()
+            .unwrap()
+            .insert(request_id, (sender, can_remember.then(|| id.clone())));
+
+        if let Some(window) = self.app.get_webview_window("main") {
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+        let _ = self.app.emit_to(
+            "main",
+            "ipc-approval-request",
+            ApprovalRequest {
+                request_id,
+                client: id.clone(),
+                pid: client.pid,
+                can_remember,
+            },
+        );
+
+        let approved = receiver.recv_timeout(APPROVAL_TIMEOUT).unwrap_or(false);
+        state.pending.lock().unwrap().remove(&request_id);
+        approved
+    }
+
+    fn handle(&self, request: Request) -> Response {
+        self.try_handle(request).unwrap_or_else(Response::error)
+    }
+}
+
+impl<R: Runtime> AppHandler<R> {
+    fn try_handle(&self, request: Request) -> Result<Response, String> {
+        let vault_manager = self.app.state::<VaultManager>();
+        let data_manager = DataManager::new(&self.app);
+
+        match request {
+            Request::Status => Ok(Response::Status {
+                unlocked: vault_manager.is_unlocked(data_manager.vault_id()),
+                master_password_enabled: data_manager
+                    .load_settings()
+                    .security
+                    .master_password_enabled,
+            }),
+            Request::Lock => {
+                commands::lock_vault(self.app.clone(), vault_manager)?;
+                let _ = self.app.emit("vault-locked", ());
+                Ok(Response::Done)
+            }
+            Request::Search { query } => Ok(Response::Matches {
+                paths: DataManager::search_paths(&data_manager.load_data(), &query),
+            }),
+            Request::Get { path } => {
+                let nodes = data_manager.load_data();
+                let node = find_snippet(&nodes, &path)?;
+                let value = commands::reveal_value(&vault_manager, &data_manager, node)?;
+                Ok(Response::Value { value })
+            }
+            Request::Copy { path } => {
+                let nodes = data_manager.load_data();
+                let id = find_snippet(&nodes, &path)?.id.clone();
+                commands::copy_snippet(self.app.clone(), vault_manager, id, None)?;
+                Ok(Response::Done)
+            }
+        }
+    }
+}
+
+fn find_snippet<'a>(
+    nodes: &'a [crate::models::Node],
+    path: &str,
+) -> Result<&'a crate::models::Node, String> {
+    DataManager::find_node_by_path(nodes, &DataManager::split_path(path))
+        .filter(|node| matches!(node.node_type, NodeType::Snippet))
+        .ok_or_else(|| format!("Snippet '{}' not found", path))
+}
+
+/// Starts or stops the server to match `settings.ipc_enabled`.
+pub fn apply_settings<R: Runtime>(app: &AppHandle<R>, settings: &AppSettings) {
+    let state = app.state::<IpcState>();
+    let mut server = state.server.lock().unwrap();
+    if settings.ipc_enabled == server.is_some() {
+        return;
+    }
+
+    if !settings.ipc_enabled {
+        *server = None;
+        state.approved.lock().unwrap().clear();
+        return;
+    }
+
+    let data_manager = DataManager::new(app);
+    let handler = Arc::new(AppHandler { app: app.clone() });
+    match Server::start(super::endpoint(data_manager.data_dir()), handler) {
+        Ok(started) => *server = Some(started),
+        Err(e) => eprintln!("Failed to start IPC server: {}", e),
+    }
+}
+
+/// The socket path or pipe name while the server runs.
+pub fn endpoint<R: Runtime>(app: &AppHandle<R>) -> Option<String> {
+    let state = app.state::<IpcState>();
+    let server = state.server.lock().unwrap();
+    server
+        .as_ref()
+        .map(|server| server.endpoint().to_string_lossy().to_string())
+}
+
+/// Answers an approval prompt. With `remember` the client is trusted permanently, unless
+/// it is an interpreter.
+pub fn respond<R: Runtime>(
+    app: &AppHandle<R>,
+    request_id: u32,
+    approved: bool,
+    remember: bool,
+) -> Result<(), String> {
+    let state = app.state::<IpcState>();
+    let (sender, client_id) = state
+        .pending
+        .lock()
+        .unwrap()
+        .remove(&request_id)
+        .ok_or("The request has expired")?;
+
+    if let Some(client_id) = client_id.filter(|_| approved && remember) {
+        let data_manager = DataManager::new(app);
+        let mut settings = data_manager.load_settings();
+        if !settings.ipc_trusted_clients.contains(&client_id) {
+            settings.ipc_trusted_clients.push(client_id.clone());
+            data_manager
+                .save_settings(&settings)
+                .map_err(|e| e.to_string())?;
+        }
+        state.approved.lock().unwrap().insert(client_id);
+    }
+
+    let _ = sender.send(approved);
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use super::ClientInfo;
+use std::cell::Cell;
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::os::windows::io::{FromRawHandle, RawHandle};
+use std::path::{Path, PathBuf};
+use windows_sys::Win32::Foundation::{
+    CloseHandle, ERROR_PIPE_CONNECTED, HANDLE, INVALID_HANDLE_VALUE,
+};
+use windows_sys::Win32::Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX};
+use windows_sys::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, GetNamedPipeClientProcessId, GetNamedPipeClientSessionId,
+    PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+};
+use windows_sys::Win32::System::RemoteDesktop::ProcessIdToSessionId;
+use windows_sys::Win32::System::Threading::{
+    GetCurrentProcessId, OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+    PROCESS_QUERY_LIMITED_INFORMATION,
+};
+
+const BUFFER_SIZE: u32 = 4096;
+
+/// Named pipes live in a global namespace, so the name includes the user.
+pub fn endpoint(_data_dir: &Path) -> PathBuf {
+    let user = std::env::var("USERNAME").unwrap_or_else(|_| "default".to_string());
+    PathBuf::from(format!(r"\\.\pipe\sklad-{}", user))
+}
+
+pub struct Listener {
+    name: Vec<u16>,
+    /// The pipe instance the next client connects to, kept as an integer so the
+    /// listener can move to the accept thread.
+    next: Cell<isize>,
+}
+
+impl Listener {
+    pub fn bind(path: &Path) -> io::Result<Self> {
+        let name: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        // The first instance fails if another process already owns the name
+        let first = create_instance(&name, true)?;
+        Ok(Self {
+            name,
+            next: Cell::new(first as isize),
+        })
+    }
+
+    /// Waits for a client of the same logon session on the pending pipe instance.
+    pub fn accept(&self) -> io::Result<(File, ClientInfo)> {
+        let handle = self.next.get() as HANDLE;
+
+        // SAFETY: `handle` is a pipe instance created by `create_instance`
+        let connect_error = if unsafe { ConnectNamedPipe(handle, std::ptr::null_mut()) } == 0 {
+            let e = io::Error::last_os_error();
+            // The client may connect between CreateNamedPipeW and ConnectNamedPipe
+            (e.raw_os_error() != Some(ERROR_PIPE_CONNECTED as i32)).then_some(e)
+        } else {
+            None
+        };
+        self.next.set(create_instance(&self.name, false)? as isize);
+
+        // SAFETY: we own `handle` and nothing else closes it
+        let file = unsafe { File::from_raw_handle(handle as RawHandle) };
+        if let Some(e) = connect_error {
+            return Err(e);
+        }
+
+        let mut pid = 0u32;
+        let mut client_session = 0u32;
+        let mut own_session = 0u32;
+        // SAFETY: all out-pointers are valid for writes
+        let ok = unsafe {
+            GetNamedPipeClientProcessId(handle, &mut pid) != 0
+                && GetNamedPipeClientSessionId(handle, &mut client_session) != 0
+                && ProcessIdToSessionId(GetCurrentProcessId(), &mut own_session) != 0
+        };
+        if !ok {
+            return Err(io::Error::last_os_error());
+        }
+        if client_session != own_session {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "client session {} is not the current session",
+                    client_session
+                ),
+            ));
+        }
+
+        let client = ClientInfo {
+            pid: Some(pid),
+            executable: executable(pid),
+        };
+        Ok((file, client))
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        // SAFETY: the pending instance was never handed out
+        unsafe { CloseHandle(self.next.get() as HANDLE) };
+    }
+}
+
+fn create_instance(name: &[u16], first: bool) -> io::Result<HANDLE> {
+    let mut open_mode = PIPE_ACCESS_DUPLEX;
+    if first {
+        open_mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+    }
+    // SAFETY: `name` is NUL-terminated; a null security descriptor means the default
+    // one, which only grants write access to the owner, SYSTEM and administrators
+    let handle = unsafe {
+        CreateNamedPipeW(
+            name.as_ptr(),
+            open_mode,
+            PIPE_TYPE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            BUFFER_SIZE,
+            BUFFER_SIZE,
+            0,
+            std::ptr::null(),
+        )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(handle)
+}
+
+pub fn connect(path: &Path) -> io::Result<File> {
+    OpenOptions::new().read(true).write(true).open(path)
+}
+
+pub fn cleanup(_path: &Path) {
+    // Pipes disappear with their last handle
+}
+
+fn executable(pid: u32) -> Option<PathBuf> {
+    // SAFETY: the handle is checked and closed below
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+    if process.is_null() {
+        return None;
+    }
+
+    let mut buf = vec![0u16; 32 * 1024];
+    let mut len = buf.len() as u32;
+    // SAFETY: `buf` is writable for `len` UTF-16 units
+    let ok = unsafe {
+        QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, buf.as_mut_ptr(), &mut len)
+    } != 0;
+    // SAFETY: `process` is a valid handle from OpenProcess
+    unsafe { CloseHandle(process) };
+
+    ok.then(|| PathBuf::from(OsString::from_wide(&buf[..len as usize])))
+}
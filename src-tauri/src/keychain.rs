@@ -0,0 +1,90 @@
+use crate::security::{self, Key};
+use crate::vault::ENCRYPTED_VALUE_SEPARATOR;
+use keyring::Entry;
+
+/// Service name of the entries in the platform secret store.
+const SERVICE: &str = "sklad";
+
+/// Where the device keys of `remember` are kept, one per vault.
+pub trait SecretStore {
+    /// The stored secret of `vault`, `None` if there is none.
+    fn get(&self, vault: &str) -> Result<Option<Vec<u8>>, String>;
+    fn set(&self, vault: &str, secret: &[u8]) -> Result<(), String>;
+    /// Deletes the secret of `vault`, if there is one.
+    fn delete(&self, vault: &str) -> Result<(), String>;
+}
+
+/// The platform secret store: Secret Service on Linux, Keychain on macOS, Credential
+/// Manager on Windows.
+pub struct PlatformStore;
+
+impl PlatformStore {
+    fn entry(vault: &str) -> Result<Entry, String> {
+        Entry::new(SERVICE, vault).map_err(|e| format!("Secret store unavailable: {}", e))
+    }
+}
+
+impl SecretStore for PlatformStore {
+    fn get(&self, vault: &str) -> Result<Option<Vec<u8>>, String> {
+        match Self::entry(vault)?.get_secret() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(format!("Failed to read the key: {}", e)),
+        }
+    }
+
+    fn set(&self, vault: &str, secret: &[u8]) -> Result<(), String> {
+        Self::entry(vault)?
+            .set_secret(secret)
+            .map_err(|e| format!("Failed to store the key: {}", e))
+    }
+
+    fn delete(&self, vault: &str) -> Result<(), String> {
+        match Self::entry(vault)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!("Failed to delete the key: {}", e)),
+        }
+    }
+}
+
+/// Remembers `key` on this device: it is wrapped with a random device key, which goes
+/// into `store`. Returns the wrapped key for `AppSettingsSecurity::remembered_key`, so
+/// neither the store nor the settings open the vault alone.
+pub fn remember(store: &dyn SecretStore, vault: &str, key: &Key) -> Result<String, String> {
+    let device_key = security::random_bytes::<32>();
+    let (ciphertext, nonce) = security::encrypt_bytes(key, &device_key, vault.as_bytes())?;
+    store.set(vault, &device_key)?;
+    Ok(format!(
+        "{}{}{}",
+        hex::encode(nonce),
+        ENCRYPTED_VALUE_SEPARATOR,
+        hex::encode(ciphertext)
+    ))
+}
+
+/// Unwraps a key from `remember`. `None` if the device key is gone or doesn't fit.
+pub fn recall(store: &dyn SecretStore, vault: &str, wrapped: &str) -> Result<Option<Key>, String> {
+    let Some(device_key) = store.get(vault)? else {
+        return Ok(None);
+    };
+    let Ok(device_key) = Key::try_from(device_key) else {
+        return Ok(None);
+    };
+
+    let Some((nonce, ciphertext)) = wrapped.split_once(ENCRYPTED_VALUE_SEPARATOR) else {
+        return Ok(None);
+    };
+    let (Ok(nonce), Ok(ciphertext)) = (hex::decode(nonce), hex::decode(ciphertext)) else {
+        return Ok(None);
+    };
+    Ok(
+        security::decrypt_bytes(&ciphertext, &nonce, &device_key, vault.as_bytes())
+            .ok()
+            .and_then(|key| Key::try_from(key).ok()),
+    )
+}
+
+/// Deletes the device key of `vault`, if there is one.
+pub fn forget(store: &dyn SecretStore, vault: &str) -> Result<(), String> {
+    store.delete(vault)
+}
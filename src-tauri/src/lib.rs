@@ -1,11 +1,33 @@
+pub mod attachments;
+pub mod audit;
+pub mod cli;
 pub mod commands;
 pub mod data_manager;
+pub mod export;
+pub mod generator;
+pub mod git;
+pub mod import;
+pub mod integrity;
+pub mod ipc;
+pub mod keychain;
 pub mod models;
+pub mod recovery;
+pub mod reminders;
 pub mod security;
+pub mod sharing;
+pub mod sorting;
+pub mod strength;
+pub mod sync;
+pub mod totp;
 pub mod tray_generator;
+pub mod tray_status;
+pub mod unlock_guard;
+pub mod vault;
 
 use crate::data_manager::DataManager;
-use crate::tray_generator::TrayGenerator;
+use crate::tray_generator::{
+    TrayGenerator, TrayState, MORE_ID_PREFIX, SWITCH_VAULT_ID_PREFIX, VAULT_ID_PREFIX,
+};
 use tauri::{Emitter, Manager};
 use tauri_plugin_autostart::MacosLauncher;
 use tauri_plugin_notification::NotificationExt;
@@ -77,6 +99,8 @@ pub fn run() {
         // on_window_event removed to allow frontend to handle CloseRequested
         .setup(|app| {
             let handle = app.handle();
+            // Attachments copied by path before a crash
+            commands::remove_decrypted_files();
             let data_manager = DataManager::new(handle);
             let nodes = data_manager.load_data();
 
@@ -125,9 +149,68 @@ pub fn run() {
                 .on_menu_event(|app, event| {
                     let id = event.id.as_ref();
                     match id {
-                        "quit" => app.exit(0),
+                        "quit" => {
+                            commands::remove_decrypted_files();
+                            app.exit(0)
+                        }
                         "open" => show_main_window(app),
-                        snippet_id => handle_snippet_click(app, snippet_id.to_string()),
+                        "search" => show_search_window(app, None),
+                        "create" => {
+                            if let Some(window) = app.get_webview_window("create") {
+                                let _ = window.show();
+                                let _ = window.set_focus();
+                            }
+                        }
+                        "lock" => {
+                            let vault_manager = app.state::<crate::security::VaultManager>();
+                            commands::lock_vaults(
+                                app,
+                                &vault_manager,
+                                &vault_manager.unlocked_vaults(),
+                            );
+                            let _ = app.emit("vault-locked", ());
+                        }
+                        "unlock" => {
+                            show_main_window(app);
+                            let _ = app.emit("request-vault-unlock", ());
+                        }
+                        more_id if more_id.starts_with(MORE_ID_PREFIX) => {
+                            let folder_id = &more_id[MORE_ID_PREFIX.len()..];
+                            show_search_window(
+                                app,
+                                (!folder_id.is_empty()).then(|| folder_id.to_string()),
+                            );
+                        }
+                        switch_id if switch_id.starts_with(SWITCH_VAULT_ID_PREFIX) => {
+                            let vault = &switch_id[SWITCH_VAULT_ID_PREFIX.len()..];
+                            let _ = commands::switch_vault(app.clone(), vault.to_string());
+                        }
+                        vault_id if vault_id.starts_with(VAULT_ID_PREFIX) => {
+                            let Some((vault, item_id)) =
+                                vault_id[VAULT_ID_PREFIX.len()..].split_once('/')
+                            else {
+                                return;
+                            };
+                            match item_id.strip_prefix(MORE_ID_PREFIX) {
+                                // The search window only covers the active vault
+                                Some(folder_id) => {
+                                    if commands::switch_vault(app.clone(), vault.to_string())
+                                        .is_ok()
+                                    {
+                                        show_search_window(
+                                            app,
+                                            (!folder_id.is_empty()).then(|| folder_id.to_string()),
+                                        );
+                                    }
+                                }
+                                None => handle_snippet_click(
+                                    app,
+                                    Some(vault.to_string()),
+                                    item_id.to_string(),
+                                ),
+                            }
+                        }
+                        snippet_id => handle_snippet_click(app, None, snippet_id.to_string()),
                     }
                 })
                 .on_tray_icon_event(|tray, event| {
@@ -147,16 +230,20 @@ pub fn run() {
                         } else {
                             // "copy_last" is the default fallback
                             let vault_manager = app.state::<crate::security::VaultManager>();
-                            let last_id = vault_manager.last_used_id.lock().unwrap().clone();
+                            let last_used = vault_manager.last_used.lock().unwrap().clone();
 
-                            if let Some(id) = last_id {
-                                handle_snippet_click(app, id);
+                            if let Some((vault, id)) = last_used {
+                                handle_snippet_click(app, Some(vault), id);
                             }
                         }
                     }
                 })
                 .build(app)?;
 
+            tray_status::update(handle);
+            tray_status::spawn_ticker(handle.clone());
+            reminders::spawn(handle.clone());
+
             use std::env;
 
             // Check for --minimized flag
@@ -173,6 +260,8 @@ pub fn run() {
             // Register global shortcuts
             let settings = data_manager.load_settings();
 
+            ipc::service::apply_settings(handle, &settings);
+
             use tauri_plugin_global_shortcut::GlobalShortcutExt;
             let shortcut_str = &settings.global_search_shortcut;
             if !shortcut_str.is_empty() {
@@ -211,19 +300,61 @@ pub fn run() {
             Ok(())
         })
         .manage(crate::security::VaultManager::new())
+        .manage(crate::data_manager::DataState::default())
+        .manage(TrayState::default())
+        .manage(ipc::service::IpcState::default())
         .invoke_handler(tauri::generate_handler![
             commands::get_data,
             commands::save_data,
+            commands::accept_vault_changes,
+            commands::restore_verified_vault,
             commands::copy_snippet,
+            commands::generate_password,
+            commands::regenerate_secret,
+            commands::get_secret_history,
+            commands::add_totp,
+            commands::get_totp_code,
+            commands::add_attachment,
+            commands::save_attachment,
+            commands::copy_attachment,
+            commands::copy_attachment_path,
             commands::init_vault,
+            commands::estimate_strength,
+            commands::audit_vault,
             commands::unlock_vault,
+            commands::unlock_with_keychain,
+            commands::set_remember_key,
+            commands::set_keyfile,
+            commands::change_master_password,
+            commands::create_recovery_key,
+            commands::save_emergency_kit,
+            commands::recover_vault,
             commands::lock_vault,
             commands::get_settings,
             commands::save_settings,
             commands::get_snippets_path,
             commands::open_snippets_path,
             commands::reset_vault,
-            commands::is_vault_unlocked
+            commands::is_vault_unlocked,
+            commands::import_data,
+            commands::export_data,
+            commands::respond_ipc_approval,
+            commands::get_ipc_endpoint,
+            commands::get_sync_conflicts,
+            commands::merge_sync_conflicts,
+            commands::git_pull,
+            commands::git_push,
+            commands::list_vaults,
+            commands::create_vault,
+            commands::rename_vault,
+            commands::remove_vault,
+            commands::switch_vault,
+            commands::set_vault_mounted,
+            commands::get_identity,
+            commands::add_shared_vault,
+            commands::list_recipients,
+            commands::add_recipient,
+            commands::remove_recipient
         ])
         .build(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -251,11 +382,27 @@ fn show_main_window(app: &tauri::AppHandle) {
     }
 }
 
-fn handle_snippet_click(app: &tauri::AppHandle, id: String) {
+/// Shows the search window, optionally narrowed to the contents of a folder.
+fn show_search_window(app: &tauri::AppHandle, folder_id: Option<String>) {
+    if let Some(window) = app.get_webview_window("search") {
+        let _ = window.show();
+        let _ = window.set_focus();
+        let _ = app.emit_to("search", "search-folder", folder_id);
+    }
+}
+
+/// Copies a snippet of `vault` (default: the active vault) from the tray.
+fn handle_snippet_click(app: &tauri::AppHandle, vault: Option<String>, id: String) {
     let vault_manager = app.state::<crate::security::VaultManager>();
 
-    if let Err(e) = crate::commands::copy_snippet(app.clone(), vault_manager, id.clone()) {
+    if let Err(e) =
+        crate::commands::copy_snippet(app.clone(), vault_manager, id.clone(), vault.clone())
+    {
         if e == "Vault is Locked" {
+            // The window unlocks the active vault, so bring this one up first
+            if let Some(vault) = vault {
+                let _ = crate::commands::switch_vault(app.clone(), vault);
+            }
             show_main_window(app);
             let _ = app.emit("request-unlock", id);
         } else {
@@ -1,10 +1,25 @@
 use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum NodeType {
     Folder,
     Snippet,
+    Separator,
+    /// A file, whose bytes are always encrypted, see `attachments`.
+    Attachment,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortMode {
+    #[default]
+    Manual,
+    Alphabetical,
+    Created,
+    LastUsed,
+    UseCount,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,10 +32,17 @@ pub struct Node {
     pub parent_id: Option<String>,
     #[serde(rename = "createdAt")]
     pub created_at: i64,
+    #[serde(rename = "showInTray", skip_serializing_if = "Option::is_none")]
+    pub show_in_tray: Option<bool>,
+    /// Emoji shown before the label, or the name of a bundled tray icon (e.g. `dot-red`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
 
     // Fields for Folder
     #[serde(skip_serializing_if = "Option::is_none")]
     pub children: Option<Vec<Node>>,
+    #[serde(rename = "sortMode", skip_serializing_if = "Option::is_none")]
+    pub sort_mode: Option<SortMode>,
 
     // Fields for Snippet
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -29,6 +51,129 @@ pub struct Node {
     pub encrypted_value: Option<String>,
     #[serde(rename = "isSecret", skip_serializing_if = "Option::is_none")]
     pub is_secret: Option<bool>,
+    #[serde(rename = "lastUsedAt", skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<i64>,
+    #[serde(rename = "useCount", skip_serializing_if = "Option::is_none")]
+    pub use_count: Option<u32>,
+    /// Set on TOTP snippets, whose secret value is an `otpauth://` URI, see `totp`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub totp: Option<Totp>,
+    /// Earlier values of a secret, encrypted like the current one, oldest first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history: Option<Vec<HistoryEntry>>,
+    /// When a secret is due for rotation, see `reminders`.
+    #[serde(rename = "expiresAt", skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+
+    // Fields for Attachment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<Attachment>,
+
+    /// Bumped whenever the node's content or position changes, see `sync::stamp_revisions`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revision: Option<u32>,
+    #[serde(rename = "modifiedAt", skip_serializing_if = "Option::is_none")]
+    pub modified_at: Option<i64>,
+}
+
+/// What a TOTP snippet shows without its key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Totp {
+    /// Seconds each code is valid for, for the countdown in the tray.
+    pub period: u32,
+}
+
+/// A value a secret had before it was replaced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    #[serde(rename = "encryptedValue")]
+    pub encrypted_value: String,
+    #[serde(rename = "replacedAt")]
+    pub replaced_at: i64,
+}
+
+/// A decrypted `HistoryEntry`, for the window.
+#[derive(Debug, Clone, Serialize)]
+pub struct PastValue {
+    pub value: String,
+    #[serde(rename = "replacedAt")]
+    pub replaced_at: i64,
+}
+
+/// The file an attachment node holds. Small files are encrypted into `encrypted_value`;
+/// larger ones go to a blob file, and `encrypted_value` holds the key of that file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    /// Size of the file in bytes.
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub blob: bool,
+}
+
+impl Node {
+    pub fn new_folder(label: impl Into<String>, parent_id: Option<String>) -> Self {
+        Self::new(NodeType::Folder, label.into(), parent_id)
+    }
+
+    pub fn new_snippet(
+        label: impl Into<String>,
+        parent_id: Option<String>,
+        value: String,
+        is_secret: bool,
+    ) -> Self {
+        let mut node = Self::new(NodeType::Snippet, label.into(), parent_id);
+        node.value = Some(value);
+        node.is_secret = Some(is_secret);
+        node
+    }
+
+    /// An attachment without content yet, see `attachments::attach`.
+    pub fn new_attachment(
+        label: impl Into<String>,
+        parent_id: Option<String>,
+        attachment: Attachment,
+    ) -> Self {
+        let mut node = Self::new(NodeType::Attachment, label.into(), parent_id);
+        node.is_secret = Some(true);
+        node.attachment = Some(attachment);
+        node
+    }
+
+    fn new(node_type: NodeType, label: String, parent_id: Option<String>) -> Self {
+        let is_folder = matches!(node_type, NodeType::Folder);
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            node_type,
+            label,
+            parent_id,
+            created_at: timestamp_millis(),
+            show_in_tray: None,
+            icon: None,
+            children: is_folder.then(Vec::new),
+            sort_mode: None,
+            value: None,
+            encrypted_value: None,
+            is_secret: None,
+            last_used_at: None,
+            use_count: None,
+            totp: None,
+            history: None,
+            expires_at: None,
+            attachment: None,
+            revision: None,
+            modified_at: None,
+        }
+    }
+}
+
+/// Milliseconds since the Unix epoch, matching the frontend's `Date.now()`.
+pub fn timestamp_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,10 +184,62 @@ pub struct AppSettingsSecurity {
     pub clear_clipboard: bool,
     #[serde(rename = "masterPasswordEnabled")]
     pub master_password_enabled: bool,
+    /// Argon2 hash of the master password, only left in vaults set up before `wrapped_key`.
     #[serde(rename = "passwordHash")]
     pub password_hash: Option<String>,
     #[serde(rename = "derivationSalt")]
     pub derivation_salt: Option<String>,
+    /// A known value encrypted with the key of the secrets, telling whether a key is the
+    /// vault's. Vaults set up before `wrapped_key` derive that key from the password.
+    #[serde(rename = "keyCheck", default)]
+    pub key_check: Option<String>,
+    /// The random data key that encrypts the secrets, wrapped by the password-derived key.
+    #[serde(rename = "wrappedKey", default)]
+    pub wrapped_key: Option<String>,
+    /// The data key wrapped by the recovery key, if the user made one.
+    #[serde(rename = "recoveryWrappedKey", default)]
+    pub recovery_wrapped_key: Option<String>,
+    /// Whether the vault is a shared one, whose key is wrapped for each member in the
+    /// vault file instead of derived from a master password.
+    #[serde(default)]
+    pub shared: bool,
+    /// Keyfile that is needed along with the master password, if any.
+    #[serde(rename = "keyfilePath", default)]
+    pub keyfile_path: Option<String>,
+    /// The vault key as remembered on this device, see `keychain::remember`.
+    #[serde(rename = "rememberedKey", default)]
+    pub remembered_key: Option<String>,
+    /// Failed unlocks in a row before `lockout_action` applies, 0 for no limit.
+    #[serde(rename = "maxUnlockAttempts", default)]
+    pub max_unlock_attempts: u32,
+    #[serde(rename = "lockoutAction", default)]
+    pub lockout_action: LockoutAction,
+    /// Least `strength::estimate` score, 0 to 4, a new master password needs.
+    #[serde(rename = "minPasswordScore", default = "default_min_password_score")]
+    pub min_password_score: u8,
+    /// SHA-1 breach list the vault audit checks secrets against, see `audit`.
+    #[serde(rename = "breachListPath", default)]
+    pub breach_list_path: Option<String>,
+    /// `vault::CIPHERTEXT_VERSION` for new vaults and once a save left no secret in the
+    /// older format, which is refused from then on. 0 while such secrets may still be
+    /// around, as in settings from before the field.
+    #[serde(rename = "ciphertextVersion", default)]
+    pub ciphertext_version: u32,
+}
+
+fn default_min_password_score() -> u8 {
+    2
+}
+
+/// What happens once `max_unlock_attempts` unlocks failed in a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockoutAction {
+    /// Refuse passwords for a day.
+    #[default]
+    Lockout,
+    /// Delete the secrets, as resetting the vault does.
+    Wipe,
 }
 
 impl Default for AppSettingsSecurity {
@@ -53,10 +250,77 @@ impl Default for AppSettingsSecurity {
             master_password_enabled: false, // No password set yet on fresh install
             password_hash: None,
             derivation_salt: None,
+            key_check: None,
+            wrapped_key: None,
+            recovery_wrapped_key: None,
+            shared: false,
+            keyfile_path: None,
+            remembered_key: None,
+            max_unlock_attempts: 0,
+            lockout_action: LockoutAction::default(),
+            min_password_score: default_min_password_score(),
+            breach_list_path: None,
+            ciphertext_version: crate::vault::CIPHERTEXT_VERSION,
+        }
+    }
+}
+
+impl AppSettingsSecurity {
+    /// Takes the settings the user picks from `other`, keeping the key material and the
+    /// rest that only the backend changes.
+    pub fn set_preferences(&mut self, other: &AppSettingsSecurity) {
+        self.lock_timeout = other.lock_timeout;
+        self.clear_clipboard = other.clear_clipboard;
+        self.max_unlock_attempts = other.max_unlock_attempts;
+        self.lockout_action = other.lockout_action;
+        self.min_password_score = other.min_password_score;
+        self.breach_list_path = other.breach_list_path.clone();
+    }
+}
+
+/// Id of the vault that lives in the app data dir, which every install starts with.
+pub const DEFAULT_VAULT_ID: &str = "default";
+
+/// The per-vault part of the settings for a vault that isn't the active one. The active
+/// vault's are the matching top-level fields of `AppSettings`; see `switch_vault`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultProfile {
+    pub id: String,
+    pub name: String,
+    pub security: AppSettingsSecurity,
+    #[serde(rename = "vaultPath", default)]
+    pub vault_path: Option<String>,
+    #[serde(rename = "gitEnabled", default)]
+    pub git_enabled: bool,
+    #[serde(rename = "gitRemote", default)]
+    pub git_remote: Option<String>,
+}
+
+impl VaultProfile {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.into(),
+            security: AppSettingsSecurity::default(),
+            vault_path: None,
+            git_enabled: false,
+            git_remote: None,
         }
     }
 }
 
+/// A vault as listed in the vault switcher.
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultInfo {
+    pub id: String,
+    pub name: String,
+    pub active: bool,
+    pub mounted: bool,
+    #[serde(rename = "masterPasswordEnabled")]
+    pub master_password_enabled: bool,
+    pub unlocked: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppSettings {
     pub theme: String, // 'dark' | 'light' | 'system'
@@ -78,6 +342,117 @@ pub struct AppSettings {
         default = "default_tray_menu_root_position"
     )]
     pub tray_menu_root_position: String,
+    #[serde(rename = "trayMaxDepth", default)]
+    pub tray_max_depth: u32,
+    #[serde(
+        rename = "trayMaxItemsPerFolder",
+        default = "default_tray_max_items_per_folder"
+    )]
+    pub tray_max_items_per_folder: u32,
+    #[serde(rename = "rootSortMode", default)]
+    pub root_sort_mode: SortMode,
+    /// Serve the local socket scripts use to query an unlocked vault.
+    #[serde(rename = "ipcEnabled", default)]
+    pub ipc_enabled: bool,
+    /// Executables that may use the socket without being asked again.
+    #[serde(rename = "ipcTrustedClients", default)]
+    pub ipc_trusted_clients: Vec<String>,
+    /// Folder holding sklad.json, e.g. a synced folder. Defaults to the app data dir.
+    #[serde(rename = "vaultPath", default)]
+    pub vault_path: Option<String>,
+    /// Commit every save to a git repository in the vault folder.
+    #[serde(rename = "gitEnabled", default)]
+    pub git_enabled: bool,
+    /// URL pulled from and pushed to.
+    #[serde(rename = "gitRemote", default)]
+    pub git_remote: Option<String>,
+    /// Id of the active vault, which `security` and the vault fields above belong to.
+    #[serde(rename = "vaultId", default = "default_vault_id")]
+    pub vault_id: String,
+    #[serde(rename = "vaultName", default = "default_vault_name")]
+    pub vault_name: String,
+    /// The other vaults.
+    #[serde(default)]
+    pub vaults: Vec<VaultProfile>,
+    /// Vaults whose snippets the tray lists below the active vault's.
+    #[serde(rename = "mountedVaults", default)]
+    pub mounted_vaults: Vec<String>,
+}
+
+impl AppSettings {
+    /// Makes vault `id` the active one, moving the current vault's fields into its
+    /// profile. Returns false if there is no such vault.
+    pub fn switch_vault(&mut self, id: &str) -> bool {
+        let Some(index) = self.vaults.iter().position(|vault| vault.id == id) else {
+            return false;
+        };
+        let active = self.active_profile();
+        let profile = std::mem::replace(&mut self.vaults[index], active);
+        self.vault_id = profile.id;
+        self.vault_name = profile.name;
+        self.security = profile.security;
+        self.vault_path = profile.vault_path;
+        self.git_enabled = profile.git_enabled;
+        self.git_remote = profile.git_remote;
+        true
+    }
+
+    /// Takes the settings the user picks from `other`, as sent by a window that may have
+    /// loaded them a while ago. Key material, the vaults and the rest that commands
+    /// change stay as they are; trusted IPC clients can only be forgotten.
+    pub fn set_preferences(&mut self, other: &AppSettings) {
+        self.theme = other.theme.clone();
+        self.notifications_enabled = other.notifications_enabled;
+        self.launch_at_startup = other.launch_at_startup;
+        self.auto_save = other.auto_save;
+        self.global_search_shortcut = other.global_search_shortcut.clone();
+        self.global_create_shortcut = other.global_create_shortcut.clone();
+        self.tray_click_action = other.tray_click_action.clone();
+        self.tray_menu_root_position = other.tray_menu_root_position.clone();
+        self.tray_max_depth = other.tray_max_depth;
+        self.tray_max_items_per_folder = other.tray_max_items_per_folder;
+        self.root_sort_mode = other.root_sort_mode;
+        self.ipc_enabled = other.ipc_enabled;
+        self.ipc_trusted_clients
+            .retain(|client| other.ipc_trusted_clients.contains(client));
+        // The window's vault fields are only about the vault it showed
+        if other.vault_id == self.vault_id {
+            self.vault_path = other.vault_path.clone();
+            self.git_enabled = other.git_enabled;
+            self.git_remote = other.git_remote.clone();
+            self.security.set_preferences(&other.security);
+        }
+    }
+
+    fn active_profile(&self) -> VaultProfile {
+        VaultProfile {
+            id: self.vault_id.clone(),
+            name: self.vault_name.clone(),
+            security: self.security.clone(),
+            vault_path: self.vault_path.clone(),
+            git_enabled: self.git_enabled,
+            git_remote: self.git_remote.clone(),
+        }
+    }
+
+    /// Id and name of every vault, the active one first.
+    pub fn vault_names(&self) -> Vec<(&str, &str)> {
+        std::iter::once((self.vault_id.as_str(), self.vault_name.as_str()))
+            .chain(
+                self.vaults
+                    .iter()
+                    .map(|vault| (vault.id.as_str(), vault.name.as_str())),
+            )
+            .collect()
+    }
+}
+
+fn default_vault_id() -> String {
+    DEFAULT_VAULT_ID.to_string()
+}
+
+fn default_vault_name() -> String {
+    "Personal".to_string()
 }
 
 fn default_tray_click_action() -> String {
@@ -88,6 +463,10 @@ fn default_tray_menu_root_position() -> String {
     "bottom".to_string()
 }
 
+fn default_tray_max_items_per_folder() -> u32 {
+    100
+}
+
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
@@ -100,6 +479,18 @@ impl Default for AppSettings {
             global_create_shortcut: "".to_string(),
             tray_click_action: default_tray_click_action(),
             tray_menu_root_position: default_tray_menu_root_position(),
+            tray_max_depth: 0, // unlimited
+            tray_max_items_per_folder: default_tray_max_items_per_folder(),
+            root_sort_mode: SortMode::Manual,
+            ipc_enabled: false,
+            ipc_trusted_clients: Vec::new(),
+            vault_path: None,
+            git_enabled: false,
+            git_remote: None,
+            vault_id: default_vault_id(),
+            vault_name: default_vault_name(),
+            vaults: Vec::new(),
+            mounted_vaults: Vec::new(),
         }
     }
 }
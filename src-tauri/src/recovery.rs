@@ -0,0 +1,135 @@
+use crate::security::{self, Key};
+use crate::vault;
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+/// Crockford's base32, which leaves out letters that are easy to misread.
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+/// 160 random bits, which need no slow key derivation.
+const KEY_BYTES: usize = 20;
+/// Characters per dash-separated group of the printed key.
+const GROUP_LEN: usize = 4;
+/// HKDF info for the key that wraps the data key.
+const RECOVERY_INFO: &[u8] = b"sklad recovery key";
+/// Associated data of the data key wrapped by the recovery key.
+const RECOVERY_AAD: &[u8] = b"sklad recovery";
+
+/// A fresh recovery key, printed like "7K3M-Q9TX-…", which fits on paper and in a QR code.
+pub fn generate() -> String {
+    encode(&security::random_bytes::<KEY_BYTES>())
+}
+
+fn encode(bytes: &[u8]) -> String {
+    let mut chars = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            chars.push(ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        chars.push(ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    chars
+        .chunks(GROUP_LEN)
+        .map(|group| group.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Reads a recovery key the way it was likely typed: in any case, with or without dashes
+/// and spaces, and with O, I and L for 0, 1 and 1.
+fn decode(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text.chars().filter(|c| !c.is_whitespace() && *c != '-') {
+        let c = match c.to_ascii_uppercase() {
+            'O' => '0',
+            'I' | 'L' => '1',
+            c => c,
+        };
+        let value = ALPHABET.iter().position(|a| *a as char == c)? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    (bytes.len() == KEY_BYTES).then_some(bytes)
+}
+
+fn recovery_kek(recovery_key: &str) -> Result<Key, String> {
+    let secret = decode(recovery_key).ok_or("Not a recovery key")?;
+    let mut kek = [0u8; 32];
+    Hkdf::<Sha256>::new(None, &secret)
+        .expand(RECOVERY_INFO, &mut kek)
+        .expect("32 bytes is a valid HKDF output length");
+    Ok(kek)
+}
+
+/// Wraps the data key for `recovery_key`, for `AppSettingsSecurity::recovery_wrapped_key`.
+pub fn wrap(data_key: &Key, recovery_key: &str) -> Result<String, String> {
+    vault::wrap_key(data_key, &recovery_kek(recovery_key)?, RECOVERY_AAD)
+}
+
+/// The data key, or `None` if `recovery_key` is a different one.
+pub fn unwrap(wrapped: &str, recovery_key: &str) -> Result<Option<Key>, String> {
+    Ok(vault::unwrap_key(
+        wrapped,
+        &recovery_kek(recovery_key)?,
+        RECOVERY_AAD,
+    ))
+}
+
+/// A printable page with the recovery key and where the vault lives, to keep on paper.
+pub fn emergency_kit(vault_name: &str, vault_path: &str, recovery_key: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Sklad Emergency Kit</title>
+<style>
+body {{ font-family: sans-serif; max-width: 40em; margin: 3em auto; color: #111; }}
+.key {{ font-family: monospace; font-size: 1.6em; letter-spacing: 0.05em; padding: 0.6em; border: 2px solid #111; text-align: center; }}
+dt {{ font-weight: bold; margin-top: 1em; }}
+.note {{ border-bottom: 1px solid #999; height: 2em; }}
+</style>
+</head>
+<body>
+<h1>Sklad Emergency Kit</h1>
+<p>This key opens the vault's secrets if the master password is lost. Print it or write it
+down, keep it somewhere safe, and don't store it next to the vault.</p>
+<dl>
+<dt>Vault</dt>
+<dd>{}</dd>
+<dt>Vault file</dt>
+<dd>{}</dd>
+<dt>Recovery key</dt>
+<dd><div class="key">{}</div></dd>
+<dt>Notes</dt>
+<dd><div class="note"></div><div class="note"></div></dd>
+</dl>
+<p>To recover, choose "Forgot Password?" when unlocking, enter this key and a new master
+password.</p>
+</body>
+</html>
+"#,
+        escape_html(vault_name),
+        escape_html(vault_path),
+        escape_html(recovery_key)
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
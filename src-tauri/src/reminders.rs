@@ -0,0 +1,96 @@
+use crate::data_manager::DataManager;
+use crate::models::{self, Node};
+use std::collections::HashSet;
+use std::time::Duration;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_notification::NotificationExt;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Secrets are reminded of this long before they expire, and again once they have.
+const REMIND_BEFORE_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// A secret of the active vault that is due for rotation.
+struct Due {
+    label: String,
+    /// Days until it expires, none once it has.
+    days_left: Option<i64>,
+}
+
+/// Notifies about secrets of the active vault that expire within a week, and again
+/// when they have expired. Each stage of a secret is notified once per run of the app.
+pub fn spawn<R: Runtime>(app: AppHandle<R>) {
+    std::thread::spawn(move || {
+        let mut notified = HashSet::new();
+        loop {
+            notify_due(&app, &mut notified);
+            std::thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
+
+fn notify_due<R: Runtime>(app: &AppHandle<R>, notified: &mut HashSet<(String, i64, bool)>) {
+    let data_manager = DataManager::new(app);
+    if !data_manager.load_settings().notifications_enabled {
+        return;
+    }
+    let now = models::timestamp_millis();
+    let mut due = Vec::new();
+    collect_due(&data_manager.load_data(), now, notified, &mut due);
+
+    let body = match due.as_slice() {
+        [] => return,
+        [Due {
+            label,
+            days_left: None,
+        }] => format!("'{}' has expired, rotate it", label),
+        [Due {
+            label,
+            days_left: Some(days),
+        }] => format!("'{}' expires in {} day(s), rotate it", label, days),
+        several => format!(
+            "{} secrets are due for rotation: {}",
+            several.len(),
+            several
+                .iter()
+                .map(|due| due.label.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let _ = app
+        .notification()
+        .builder()
+        .title("Sklad: Rotate Secrets")
+        .body(body)
+        .show();
+}
+
+/// Secrets in `nodes` that entered a new stage, expiring soon or expired, since they were
+/// last notified.
+fn collect_due(
+    nodes: &[Node],
+    now: i64,
+    notified: &mut HashSet<(String, i64, bool)>,
+    out: &mut Vec<Due>,
+) {
+    for node in nodes {
+        if let Some(expires_at) = node.expires_at.filter(|_| node.is_secret.unwrap_or(false)) {
+            let expired = expires_at <= now;
+            if expires_at - now <= REMIND_BEFORE_MS
+                && notified.insert((node.id.clone(), expires_at, expired))
+            {
+                out.push(Due {
+                    label: node.label.clone(),
+                    days_left: (!expired).then(|| (expires_at - now + DAY_MS - 1) / DAY_MS),
+                });
+            }
+        }
+        collect_due(
+            node.children.as_deref().unwrap_or_default(),
+            now,
+            notified,
+            out,
+        );
+    }
+}
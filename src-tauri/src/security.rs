@@ -1,22 +1,42 @@
 use aes_gcm::{
-    aead::{Aead, KeyInit, OsRng},
+    aead::{Aead, KeyInit, OsRng, Payload},
     Aes256Gcm, Key as AesKey, Nonce,
 };
 use aes_gcm::aead::rand_core::RngCore;
 use argon2::{
-    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    password_hash::{PasswordHash, PasswordVerifier},
     Argon2,
 };
+use std::collections::HashMap;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 const AES_KEY_SIZE: usize = 32;
-const NONCE_SIZE: usize = 12;
+pub const NONCE_SIZE: usize = 12;
 
 pub type Key = [u8; AES_KEY_SIZE];
 
 pub struct VaultManager {
-    pub state: Mutex<VaultState>,
-    pub last_used_id: Mutex<Option<String>>,
+    /// Keys of the unlocked vaults by vault id; any other vault is locked.
+    unlocked: Mutex<HashMap<String, UnlockedVault>>,
+    /// Vault id and node id of the snippet copied last.
+    pub last_used: Mutex<Option<(String, String)>>,
+    /// A copied secret that is still waiting to be wiped from the clipboard.
+    pub clipboard_wipe: Mutex<Option<ClipboardWipe>>,
+}
+
+struct UnlockedVault {
+    key: Key,
+    /// When the vault locks itself, if auto-lock is enabled.
+    auto_lock_at: Option<Instant>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClipboardWipe {
+    pub at: Instant,
+    pub value: String,
+    /// Folder with a decrypted attachment whose path was copied, deleted along with the value.
+    pub temp_dir: Option<std::path::PathBuf>,
 }
 
 impl Default for VaultManager {
@@ -34,20 +54,69 @@ pub enum VaultState {
 impl VaultManager {
     pub fn new() -> Self {
         Self {
-            state: Mutex::new(VaultState::Locked),
-            last_used_id: Mutex::new(None),
+            unlocked: Mutex::new(HashMap::new()),
+            last_used: Mutex::new(None),
+            clipboard_wipe: Mutex::new(None),
         }
     }
-}
 
-pub fn hash_password(password: &str) -> String {
-    let salt = SaltString::generate(&mut OsRng);
-    Argon2::default()
-        .hash_password(password.as_bytes(), &salt)
-        .expect("Password hashing failed")
-        .to_string()
+    /// Unlocks `vault` with `key` and schedules auto-lock after `lock_timeout_ms` (0 = never).
+    pub fn unlock(&self, vault: &str, key: Key, lock_timeout_ms: u32) {
+        let auto_lock_at = (lock_timeout_ms > 0)
+            .then(|| Instant::now() + Duration::from_millis(lock_timeout_ms.into()));
+        self.unlocked
+            .lock()
+            .unwrap()
+            .insert(vault.to_string(), UnlockedVault { key, auto_lock_at });
+    }
+
+    /// Swaps the key of an unlocked `vault`, keeping its auto-lock time.
+    pub fn replace_key(&self, vault: &str, key: Key) {
+        if let Some(unlocked) = self.unlocked.lock().unwrap().get_mut(vault) {
+            unlocked.key = key;
+        }
+    }
+
+    pub fn lock(&self, vault: &str) {
+        self.unlocked.lock().unwrap().remove(vault);
+    }
+
+    pub fn state(&self, vault: &str) -> VaultState {
+        match self.unlocked.lock().unwrap().get(vault) {
+            Some(unlocked) => VaultState::Unlocked(unlocked.key),
+            None => VaultState::Locked,
+        }
+    }
+
+    pub fn is_unlocked(&self, vault: &str) -> bool {
+        self.unlocked.lock().unwrap().contains_key(vault)
+    }
+
+    pub fn unlocked_vaults(&self) -> Vec<String> {
+        self.unlocked.lock().unwrap().keys().cloned().collect()
+    }
+
+    pub fn auto_lock_at(&self, vault: &str) -> Option<Instant> {
+        self.unlocked
+            .lock()
+            .unwrap()
+            .get(vault)
+            .and_then(|unlocked| unlocked.auto_lock_at)
+    }
+
+    /// Vaults whose auto-lock time has come.
+    pub fn due_for_lock(&self, now: Instant) -> Vec<String> {
+        self.unlocked
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, unlocked)| unlocked.auto_lock_at.is_some_and(|at| at <= now))
+            .map(|(vault, _)| vault.clone())
+            .collect()
+    }
 }
 
+/// Checks a password against an Argon2 hash, as stored by vaults from before key checks.
 pub fn verify_password(password: &str, hash: &str) -> bool {
     PasswordHash::new(hash)
         .ok()
@@ -64,25 +133,14 @@ pub fn derive_key_from_password(password: &str, salt: &str) -> Key {
     key
 }
 
-/// Encrypts data using AES-256-GCM. Returns (ciphertext_hex, nonce_hex).
-pub fn encrypt(data: &str, key: &Key) -> Result<(String, String), String> {
-    let cipher = Aes256Gcm::new(AesKey::<Aes256Gcm>::from_slice(key));
-    
-    let mut nonce_bytes = [0u8; NONCE_SIZE];
-    OsRng.fill_bytes(&mut nonce_bytes);
-    let nonce = Nonce::from_slice(&nonce_bytes);
-
-    let ciphertext = cipher
-        .encrypt(nonce, data.as_bytes())
-        .map_err(|e| e.to_string())?;
-
-    Ok((hex::encode(ciphertext), hex::encode(nonce_bytes)))
-}
-
-/// Decrypts AES-256-GCM encrypted data.
+/// Decrypts AES-256-GCM encrypted data without associated data, the format secrets had
+/// before `vault::encrypt_value`.
 pub fn decrypt(ciphertext_hex: &str, nonce_hex: &str, key: &Key) -> Result<String, String> {
     let ciphertext = hex::decode(ciphertext_hex).map_err(|_| "Invalid ciphertext hex".to_string())?;
     let nonce_bytes = hex::decode(nonce_hex).map_err(|_| "Invalid nonce hex".to_string())?;
+    if nonce_bytes.len() != NONCE_SIZE {
+        return Err("Invalid nonce length".into());
+    }
 
     let cipher = Aes256Gcm::new(AesKey::<Aes256Gcm>::from_slice(key));
     let nonce = Nonce::from_slice(&nonce_bytes);
@@ -93,3 +151,46 @@ pub fn decrypt(ciphertext_hex: &str, nonce_hex: &str, key: &Key) -> Result<Strin
 
     String::from_utf8(plaintext).map_err(|_| "Invalid UTF-8".to_string())
 }
+
+/// Returns `N` bytes from the OS random number generator.
+pub fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Encrypts raw bytes using AES-256-GCM, authenticating `aad` alongside them.
+/// Returns (ciphertext, nonce).
+pub fn encrypt_bytes(data: &[u8], key: &Key, aad: &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let cipher = Aes256Gcm::new(AesKey::<Aes256Gcm>::from_slice(key));
+
+    let nonce_bytes = random_bytes::<NONCE_SIZE>();
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce_bytes), Payload { msg: data, aad })
+        .map_err(|e| e.to_string())?;
+
+    Ok((ciphertext, nonce_bytes.to_vec()))
+}
+
+/// Decrypts bytes produced by `encrypt_bytes`; fails if `aad` differs from the one used to encrypt.
+pub fn decrypt_bytes(
+    ciphertext: &[u8],
+    nonce: &[u8],
+    key: &Key,
+    aad: &[u8],
+) -> Result<Vec<u8>, String> {
+    if nonce.len() != NONCE_SIZE {
+        return Err("Invalid nonce length".into());
+    }
+
+    let cipher = Aes256Gcm::new(AesKey::<Aes256Gcm>::from_slice(key));
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| "Decryption failed".to_string())
+}
@@ -0,0 +1,185 @@
+use crate::security::{self, Key};
+use crate::vault::{self, ENCRYPTED_VALUE_SEPARATOR};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fs;
+use std::io;
+use std::path::Path;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// Text form of a public key, as members send it to each other.
+const PUBLIC_KEY_PREFIX: &str = "sklad-pub-";
+const IDENTITY_FILE_NAME: &str = "identity.key";
+/// HKDF info for the key that wraps a vault key for one recipient.
+const WRAP_INFO: &[u8] = b"sklad x25519 key wrap";
+/// Associated data of a previous vault key wrapped by the one that replaced it.
+const ROTATION_AAD: &[u8] = b"sklad key rotation";
+
+/// This device's X25519 key pair, which opens the shared vaults it was added to.
+/// Like an age identity it is kept unencrypted, readable only by the user.
+pub struct Identity {
+    secret: StaticSecret,
+}
+
+impl Identity {
+    /// Loads the identity from `dir`, creating one on first use.
+    pub fn load_or_create(dir: &Path) -> io::Result<Self> {
+        let path = dir.join(IDENTITY_FILE_NAME);
+        if let Ok(content) = fs::read_to_string(&path) {
+            let bytes = hex::decode(content.trim())
+                .ok()
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                .ok_or_else(|| io::Error::other(format!("{} is damaged", path.display())))?;
+            return Ok(Self {
+                secret: StaticSecret::from(bytes),
+            });
+        }
+
+        let bytes = security::random_bytes::<32>();
+        write_private(&path, &hex::encode(bytes))?;
+        Ok(Self {
+            secret: StaticSecret::from(bytes),
+        })
+    }
+
+    pub fn public_key(&self) -> String {
+        format_public_key(&PublicKey::from(&self.secret))
+    }
+}
+
+#[cfg(unix)]
+fn write_private(path: &Path, content: &str) -> io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(content.as_bytes())
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, content: &str) -> io::Result<()> {
+    fs::write(path, content)
+}
+
+/// The vault key wrapped for one member of a shared vault, stored in the vault file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recipient {
+    pub name: String,
+    #[serde(rename = "publicKey")]
+    pub public_key: String,
+    /// Public half of the one-off key pair used for this wrap, hex.
+    pub ephemeral: String,
+    /// Nonce and ciphertext of the vault key, laid out like `Node::encrypted_value`.
+    #[serde(rename = "wrappedKey")]
+    pub wrapped_key: String,
+}
+
+fn format_public_key(key: &PublicKey) -> String {
+    format!("{}{}", PUBLIC_KEY_PREFIX, hex::encode(key.as_bytes()))
+}
+
+pub fn parse_public_key(text: &str) -> Result<PublicKey, String> {
+    text.trim()
+        .strip_prefix(PUBLIC_KEY_PREFIX)
+        .and_then(|key| hex::decode(key).ok())
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .map(PublicKey::from)
+        .ok_or_else(|| format!("Not a Sklad public key: '{}'", text.trim()))
+}
+
+/// A fresh random vault key.
+pub fn random_key() -> Key {
+    security::random_bytes::<32>()
+}
+
+/// Derives the key that wraps the vault key from an X25519 exchange, binding it to both
+/// public keys the way age does.
+fn wrapping_key(shared: &[u8; 32], ephemeral: &PublicKey, recipient: &PublicKey) -> Key {
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(ephemeral.as_bytes());
+    salt[32..].copy_from_slice(recipient.as_bytes());
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(WRAP_INFO, &mut key)
+        .expect("32 bytes is a valid HKDF output length");
+    key
+}
+
+/// Wraps `key` so that only the holder of the identity behind `public_key` can unwrap it.
+pub fn wrap(key: &Key, name: &str, public_key: &str) -> Result<Recipient, String> {
+    let recipient = parse_public_key(public_key)?;
+    let ephemeral_secret = StaticSecret::from(security::random_bytes::<32>());
+    let ephemeral = PublicKey::from(&ephemeral_secret);
+    let shared = ephemeral_secret.diffie_hellman(&recipient);
+    if !shared.was_contributory() {
+        return Err("Invalid public key".into());
+    }
+
+    let wrapping_key = wrapping_key(shared.as_bytes(), &ephemeral, &recipient);
+    let (ciphertext, nonce) = security::encrypt_bytes(key, &wrapping_key, recipient.as_bytes())?;
+    Ok(Recipient {
+        name: name.trim().to_string(),
+        public_key: format_public_key(&recipient),
+        ephemeral: hex::encode(ephemeral.as_bytes()),
+        wrapped_key: format!(
+            "{}{}{}",
+            hex::encode(nonce),
+            ENCRYPTED_VALUE_SEPARATOR,
+            hex::encode(ciphertext)
+        ),
+    })
+}
+
+/// The vault key, if one of `recipients` was wrapped for `identity`.
+pub fn unwrap(recipients: &[Recipient], identity: &Identity) -> Option<Key> {
+    let own = identity.public_key();
+    let recipient = recipients
+        .iter()
+        .find(|recipient| recipient.public_key == own)?;
+
+    let ephemeral = hex::decode(&recipient.ephemeral)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .map(PublicKey::from)?;
+    let shared = identity.secret.diffie_hellman(&ephemeral);
+    let public = PublicKey::from(&identity.secret);
+    let wrapping_key = wrapping_key(shared.as_bytes(), &ephemeral, &public);
+
+    let (nonce, ciphertext) = recipient
+        .wrapped_key
+        .split_once(ENCRYPTED_VALUE_SEPARATOR)?;
+    let key = security::decrypt_bytes(
+        &hex::decode(ciphertext).ok()?,
+        &hex::decode(nonce).ok()?,
+        &wrapping_key,
+        public.as_bytes(),
+    )
+    .ok()?;
+    Key::try_from(key).ok()
+}
+
+/// Proof for the vault file that `key` replaced `previous`: the previous key wrapped by
+/// the new one, which only someone who had the previous key can write.
+pub fn rotation(previous: &Key, key: &Key) -> Result<String, String> {
+    vault::wrap_key(previous, key, ROTATION_AAD)
+}
+
+/// Whether `key` was rotated from a key `is_known` accepts, following `rotations` from
+/// `rotation` back from the newest.
+pub fn rotated_from(rotations: &[String], key: &Key, is_known: impl Fn(&Key) -> bool) -> bool {
+    let mut key = *key;
+    for rotation in rotations.iter().rev() {
+        let Some(previous) = vault::unwrap_key(rotation, &key, ROTATION_AAD) else {
+            return false;
+        };
+        if is_known(&previous) {
+            return true;
+        }
+        key = previous;
+    }
+    false
+}
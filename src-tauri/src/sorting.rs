@@ -0,0 +1,44 @@
+use crate::models::{Node, NodeType, SortMode};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+
+/// Sorts `nodes` with `root_mode`, then every folder's children with that folder's own mode.
+pub fn sort_tree(nodes: &mut [Node], root_mode: SortMode) {
+    sort_siblings(nodes, root_mode);
+    for node in nodes {
+        let mode = node.sort_mode.unwrap_or_default();
+        if let Some(children) = &mut node.children {
+            sort_tree(children, mode);
+        }
+    }
+}
+
+/// Sorts one level of siblings. Separators stay in place and sort each section independently.
+pub fn sort_siblings<T: Borrow<Node>>(siblings: &mut [T], mode: SortMode) {
+    if mode == SortMode::Manual {
+        return;
+    }
+
+    for section in siblings.split_mut(|node| matches!(node.borrow().node_type, NodeType::Separator))
+    {
+        // Stable, so ties keep their manual order
+        section.sort_by(|a, b| compare(a.borrow(), b.borrow(), mode));
+    }
+}
+
+fn compare(a: &Node, b: &Node, mode: SortMode) -> Ordering {
+    let folders_first = is_folder(b).cmp(&is_folder(a));
+
+    folders_first.then_with(|| match mode {
+        SortMode::Manual => Ordering::Equal,
+        SortMode::Alphabetical => a.label.to_lowercase().cmp(&b.label.to_lowercase()),
+        SortMode::Created => a.created_at.cmp(&b.created_at),
+        // Most recent / most used first, never-used last
+        SortMode::LastUsed => b.last_used_at.cmp(&a.last_used_at),
+        SortMode::UseCount => b.use_count.unwrap_or(0).cmp(&a.use_count.unwrap_or(0)),
+    })
+}
+
+fn is_folder(node: &Node) -> bool {
+    matches!(node.node_type, NodeType::Folder)
+}
@@ -0,0 +1,391 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Most used passwords, most common first.
+const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+/// English words, shared with the passphrase generator.
+const WORDLIST: &str = include_str!("wordlist.txt");
+/// Guesses below these get scores 0 to 3, and more get 4.
+const SCORE_THRESHOLDS: [f64; 4] = [1e3, 1e6, 1e8, 1e10];
+/// Guesses per character not covered by a pattern.
+const BRUTEFORCE_CARDINALITY: f64 = 10.0;
+/// Least guesses a pattern of several characters is worth.
+const MIN_MATCH_GUESSES: f64 = 50.0;
+const MIN_MATCH_LENGTH: usize = 3;
+/// Longest word looked up in the dictionaries.
+const MAX_WORD_LENGTH: usize = 20;
+const KEYBOARD_ROWS: [&str; 4] = [
+    "`1234567890-=",
+    "qwertyuiop[]\\",
+    "asdfghjkl;'",
+    "zxcvbnm,./",
+];
+/// Keys a keyboard pattern can start on.
+const KEYBOARD_KEYS: f64 = 47.0;
+/// Years this far from now or closer are guessed as one space.
+const MIN_YEAR_SPACE: i64 = 20;
+const MILLIS_PER_YEAR: i64 = 31_556_952_000;
+
+/// How guessable a password is, after zxcvbn.
+#[derive(Debug, Clone, Serialize)]
+pub struct Strength {
+    /// 0 (too guessable) to 4 (very unguessable).
+    pub score: u8,
+    /// Estimated guesses needed to find the password, as log10.
+    #[serde(rename = "guessesLog10")]
+    pub guesses_log10: f64,
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pattern {
+    /// A common password, at its rank.
+    Password(usize),
+    Word,
+    Sequence,
+    Repeat,
+    Keyboard,
+    Year,
+}
+
+#[derive(Debug, Clone)]
+struct Match {
+    start: usize,
+    /// Exclusive.
+    end: usize,
+    guesses: f64,
+    pattern: Pattern,
+    /// Capitalization beyond the first letter or all letters.
+    mixed_case: bool,
+    /// Digits or symbols standing in for letters, like "p@ssw0rd".
+    substituted: bool,
+}
+
+/// Estimates the strength of `password` by the cheapest way to guess it through common
+/// passwords, words, sequences, repeats, keyboard rows and years.
+pub fn estimate(password: &str) -> Strength {
+    let chars: Vec<char> = password.chars().collect();
+    let matches = find_matches(&chars);
+
+    // best[j]: fewest guesses for the first j characters, and the match ending there
+    let mut best: Vec<(f64, Option<usize>)> = vec![(1.0, None); chars.len() + 1];
+    for j in 1..=chars.len() {
+        best[j] = (best[j - 1].0 * BRUTEFORCE_CARDINALITY, None);
+        for (index, m) in matches.iter().enumerate().filter(|(_, m)| m.end == j) {
+            let guesses = best[m.start].0 * m.guesses;
+            if guesses < best[j].0 {
+                best[j] = (guesses, Some(index));
+            }
+        }
+    }
+    let guesses = best[chars.len()].0;
+
+    let mut used = Vec::new();
+    let mut j = chars.len();
+    while j > 0 {
+        match best[j].1 {
+            Some(index) => {
+                used.push(&matches[index]);
+                j = matches[index].start;
+            }
+            None => j -= 1,
+        }
+    }
+
+    let score = SCORE_THRESHOLDS
+        .iter()
+        .take_while(|threshold| guesses >= **threshold)
+        .count() as u8;
+    let (warning, suggestions) = feedback(&chars, &used, score);
+    Strength {
+        score,
+        guesses_log10: guesses.log10(),
+        warning,
+        suggestions,
+    }
+}
+
+/// Rank of each word, 1 for the most common.
+type Dictionary = HashMap<&'static str, usize>;
+
+/// The common passwords and the English words.
+fn dictionaries() -> &'static (Dictionary, Dictionary) {
+    static DICTIONARIES: OnceLock<(Dictionary, Dictionary)> = OnceLock::new();
+    DICTIONARIES.get_or_init(|| {
+        let passwords = COMMON_PASSWORDS
+            .lines()
+            .enumerate()
+            .map(|(rank, password)| (password, rank + 1))
+            .collect();
+        // The wordlist is not ordered by use, so every word counts as equally common
+        let word_count = WORDLIST.lines().count();
+        let words = WORDLIST.lines().map(|word| (word, word_count)).collect();
+        (passwords, words)
+    })
+}
+
+fn find_matches(chars: &[char]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    dictionary_matches(chars, &mut matches);
+    sequence_matches(chars, &mut matches);
+    repeat_matches(chars, &mut matches);
+    keyboard_matches(chars, &mut matches);
+    year_matches(chars, &mut matches);
+    for m in &mut matches {
+        m.guesses = m.guesses.max(MIN_MATCH_GUESSES);
+    }
+    matches
+}
+
+/// The letter a digit or symbol commonly stands in for.
+fn unsubstitute(c: char) -> char {
+    match c {
+        '4' | '@' => 'a',
+        '8' => 'b',
+        '(' | '{' | '[' | '<' => 'c',
+        '3' => 'e',
+        '6' | '9' => 'g',
+        '1' | '!' | '|' => 'i',
+        '0' => 'o',
+        '$' | '5' => 's',
+        '7' | '+' => 't',
+        '2' => 'z',
+        c => c,
+    }
+}
+
+fn dictionary_matches(chars: &[char], out: &mut Vec<Match>) {
+    let (passwords, words) = dictionaries();
+    let lower: Vec<char> = chars.iter().flat_map(|c| c.to_lowercase()).collect();
+    if lower.len() != chars.len() {
+        return;
+    }
+    for start in 0..chars.len() {
+        for end in start + MIN_MATCH_LENGTH..=chars.len().min(start + MAX_WORD_LENGTH) {
+            let token: String = lower[start..end].iter().collect();
+            let plain: String = lower[start..end].iter().map(|c| unsubstitute(*c)).collect();
+            let reversed: String = token.chars().rev().collect();
+            let candidates = [
+                (&token, 1.0, false),
+                (&plain, 1.0, true),
+                (&reversed, 2.0, false),
+            ];
+            for (candidate, factor, substituted) in candidates {
+                if substituted && *candidate == token {
+                    continue;
+                }
+                let found = passwords
+                    .get(candidate.as_str())
+                    .map(|rank| (*rank, Pattern::Password(*rank)))
+                    .or_else(|| {
+                        words
+                            .get(candidate.as_str())
+                            .map(|rank| (*rank, Pattern::Word))
+                    });
+                let Some((rank, pattern)) = found else {
+                    continue;
+                };
+                let original = &chars[start..end];
+                let (case_factor, mixed_case) = case_variations(original);
+                let substitutions = token
+                    .chars()
+                    .zip(plain.chars())
+                    .filter(|(a, b)| a != b)
+                    .count();
+                let substitution_factor = if substituted {
+                    2f64.powi(substitutions as i32)
+                } else {
+                    1.0
+                };
+                out.push(Match {
+                    start,
+                    end,
+                    guesses: rank as f64 * factor * case_factor * substitution_factor,
+                    pattern,
+                    mixed_case,
+                    substituted,
+                });
+            }
+        }
+    }
+}
+
+/// Guesses for the capitalization of a word, and whether it is more than a capital first
+/// letter or all capitals.
+fn case_variations(chars: &[char]) -> (f64, bool) {
+    let upper = chars.iter().filter(|c| c.is_uppercase()).count();
+    let letters = chars.iter().filter(|c| c.is_alphabetic()).count();
+    if upper == 0 {
+        (1.0, false)
+    } else if upper == letters || (upper == 1 && chars[0].is_uppercase()) {
+        (2.0, false)
+    } else {
+        (2f64.powi(upper.min(letters - upper) as i32 + 1), true)
+    }
+}
+
+fn sequence_matches(chars: &[char], out: &mut Vec<Match>) {
+    let mut start = 0;
+    while start + 1 < chars.len() {
+        let delta = chars[start + 1] as i64 - chars[start] as i64;
+        let mut end = start + 1;
+        while end < chars.len()
+            && delta.abs() == 1
+            && chars[end] as i64 - chars[end - 1] as i64 == delta
+            && same_class(chars[end], chars[start])
+        {
+            end += 1;
+        }
+        if end - start >= MIN_MATCH_LENGTH {
+            let first = chars[start];
+            let base = if "aAzZ019".contains(first) {
+                4.0
+            } else if first.is_ascii_digit() {
+                10.0
+            } else {
+                26.0
+            };
+            let direction = if delta > 0 { 1.0 } else { 2.0 };
+            out.push(simple_match(
+                start,
+                end,
+                base * (end - start) as f64 * direction,
+                Pattern::Sequence,
+            ));
+            start = end - 1;
+        } else {
+            start += 1;
+        }
+    }
+}
+
+fn same_class(a: char, b: char) -> bool {
+    (a.is_ascii_digit() && b.is_ascii_digit())
+        || (a.is_ascii_lowercase() && b.is_ascii_lowercase())
+        || (a.is_ascii_uppercase() && b.is_ascii_uppercase())
+}
+
+/// Runs of one character, and chunks repeated right after themselves like "abcabc".
+fn repeat_matches(chars: &[char], out: &mut Vec<Match>) {
+    for start in 0..chars.len() {
+        for chunk in 1..=(chars.len() - start) / 2 {
+            let mut count = 1;
+            while start + (count + 1) * chunk <= chars.len()
+                && chars[start..start + chunk]
+                    == chars[start + count * chunk..start + (count + 1) * chunk]
+            {
+                count += 1;
+            }
+            if count >= 2 && chunk * count >= MIN_MATCH_LENGTH {
+                let chunk_guesses = BRUTEFORCE_CARDINALITY.powi(chunk as i32) + 1.0;
+                out.push(simple_match(
+                    start,
+                    start + chunk * count,
+                    chunk_guesses * count as f64,
+                    Pattern::Repeat,
+                ));
+                break;
+            }
+        }
+    }
+}
+
+/// Runs of neighbouring keys along a row, either way.
+fn keyboard_matches(chars: &[char], out: &mut Vec<Match>) {
+    let lower: Vec<char> = chars.iter().map(|c| c.to_ascii_lowercase()).collect();
+    let neighbours = |a: char, b: char| {
+        KEYBOARD_ROWS.iter().any(|row| {
+            let row: Vec<char> = row.chars().collect();
+            row.windows(2)
+                .any(|pair| (pair[0] == a && pair[1] == b) || (pair[0] == b && pair[1] == a))
+        })
+    };
+    let mut start = 0;
+    while start < lower.len() {
+        let mut end = start + 1;
+        while end < lower.len() && neighbours(lower[end - 1], lower[end]) {
+            end += 1;
+        }
+        if end - start >= MIN_MATCH_LENGTH {
+            out.push(simple_match(
+                start,
+                end,
+                KEYBOARD_KEYS * (end - start) as f64 * 2.0,
+                Pattern::Keyboard,
+            ));
+        }
+        start = end;
+    }
+}
+
+fn year_matches(chars: &[char], out: &mut Vec<Match>) {
+    let current_year = 1970 + crate::models::timestamp_millis() / MILLIS_PER_YEAR;
+    for start in 0..chars.len().saturating_sub(3) {
+        let token: String = chars[start..start + 4].iter().collect();
+        let Ok(year) = token.parse::<i64>() else {
+            continue;
+        };
+        if (1900..=2099).contains(&year) && token.chars().all(|c| c.is_ascii_digit()) {
+            out.push(simple_match(
+                start,
+                start + 4,
+                (year - current_year).abs().max(MIN_YEAR_SPACE) as f64,
+                Pattern::Year,
+            ));
+        }
+    }
+}
+
+fn simple_match(start: usize, end: usize, guesses: f64, pattern: Pattern) -> Match {
+    Match {
+        start,
+        end,
+        guesses,
+        pattern,
+        mixed_case: false,
+        substituted: false,
+    }
+}
+
+fn feedback(chars: &[char], used: &[&Match], score: u8) -> (Option<String>, Vec<String>) {
+    if chars.is_empty() {
+        return (
+            Some("The password is empty".into()),
+            vec!["Use a few words, avoid common phrases".into()],
+        );
+    }
+    if score > 2 {
+        return (None, Vec::new());
+    }
+
+    let mut suggestions = vec!["Add another word or two. Uncommon words are better.".to_string()];
+    // The longest pattern gives the warning
+    let Some(longest) = used.iter().max_by_key(|m| m.end - m.start) else {
+        return (None, suggestions);
+    };
+    let whole = longest.end - longest.start == chars.len();
+    let warning = match longest.pattern {
+        Pattern::Password(rank) if rank <= 10 => Some("This is a top-10 common password"),
+        Pattern::Password(rank) if rank <= 100 => Some("This is a top-100 common password"),
+        Pattern::Password(_) => Some("This is a very common password"),
+        Pattern::Word if whole => Some("A word by itself is easy to guess"),
+        Pattern::Word => None,
+        Pattern::Sequence => Some("Sequences like abc or 6543 are easy to guess"),
+        Pattern::Repeat => Some("Repeats like \"aaa\" or \"abcabc\" are easy to guess"),
+        Pattern::Keyboard => Some("Straight rows of keys are easy to guess"),
+        Pattern::Year => Some("Recent years are easy to guess"),
+    };
+    if longest.mixed_case {
+        suggestions.push("Capitalization doesn't help very much".into());
+    }
+    if longest.substituted {
+        suggestions
+            .push("Predictable substitutions like '@' instead of 'a' don't help very much".into());
+    }
+    if longest.pattern == Pattern::Year {
+        suggestions.push("Avoid years that are associated with you".into());
+    }
+    (warning.map(str::to_string), suggestions)
+}
@@ -0,0 +1,331 @@
+use crate::models::{Node, NodeType};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Which copy a conflicting node was taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeSide {
+    Local,
+    Remote,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// Both copies changed the node; the later change wins.
+    BothChanged,
+    /// One copy deleted the node and the other changed it; the change wins.
+    ChangedAndDeleted,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeConflict {
+    pub id: String,
+    pub label: String,
+    pub kind: ConflictKind,
+    pub kept: MergeSide,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MergeReport {
+    /// Conflict files, or the git remote, that were merged in.
+    #[serde(rename = "mergedFiles")]
+    pub merged_files: Vec<String>,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Copies of `vault_file` left next to it by sync tools when two machines changed it at
+/// once, e.g. `sklad.sync-conflict-20240101-120000-ABC1234.json` (Syncthing) or
+/// `sklad (conflicted copy 2024-01-01).json` (Dropbox).
+pub fn conflict_files(vault_file: &Path) -> Vec<PathBuf> {
+    let (Some(dir), Some(stem), Some(name)) = (
+        vault_file.parent(),
+        vault_file.file_stem(),
+        vault_file.file_name(),
+    ) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let stem = stem.to_string_lossy();
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let Some(file_name) = path.file_name() else {
+                return false;
+            };
+            let file_name = file_name.to_string_lossy();
+            file_name != name.to_string_lossy()
+                && file_name.starts_with(stem.as_ref())
+                && file_name.ends_with(".json")
+                && file_name.to_lowercase().contains("conflict")
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// A node of a flattened tree with its position.
+struct Entry<'a> {
+    node: &'a Node,
+    parent: Option<&'a str>,
+    children: Vec<&'a str>,
+}
+
+impl<'a> Entry<'a> {
+    fn new(node: &'a Node, parent: Option<&'a str>) -> Self {
+        Self {
+            node,
+            parent,
+            children: ids(node.children.as_deref().unwrap_or_default()),
+        }
+    }
+
+    /// Whether the two match in everything but revision metadata, usage stats and the
+    /// children, which are merged one by one. Differing ciphertexts are passed to
+    /// `same_secret` along with the node id.
+    fn same_content(&self, other: &Entry, same_secret: &dyn Fn(&str, &str, &str) -> bool) -> bool {
+        let (a, b) = (self.node, other.node);
+        self.parent == other.parent
+            && a.node_type == b.node_type
+            && a.label == b.label
+            && a.created_at == b.created_at
+            && a.show_in_tray == b.show_in_tray
+            && a.icon == b.icon
+            && a.sort_mode == b.sort_mode
+            && a.value == b.value
+            && a.is_secret == b.is_secret
+            && a.totp == b.totp
+            && a.history == b.history
+            && a.expires_at == b.expires_at
+            && a.attachment == b.attachment
+            && match (&a.encrypted_value, &b.encrypted_value) {
+                (Some(x), Some(y)) => x == y || same_secret(&a.id, x, y),
+                (x, y) => x == y,
+            }
+    }
+}
+
+struct Flat<'a> {
+    entries: HashMap<&'a str, Entry<'a>>,
+    /// Ids in tree order.
+    order: Vec<&'a str>,
+    roots: Vec<&'a str>,
+}
+
+impl<'a> Flat<'a> {
+    fn new(nodes: &'a [Node]) -> Self {
+        let mut flat = Self {
+            entries: HashMap::new(),
+            order: Vec::new(),
+            roots: ids(nodes),
+        };
+        flat.add(nodes, None);
+        flat
+    }
+
+    fn add(&mut self, nodes: &'a [Node], parent: Option<&'a str>) {
+        for node in nodes {
+            self.order.push(&node.id);
+            self.entries.insert(&node.id, Entry::new(node, parent));
+            self.add(node.children.as_deref().unwrap_or_default(), Some(&node.id));
+        }
+    }
+}
+
+fn ids(nodes: &[Node]) -> Vec<&str> {
+    nodes.iter().map(|node| node.id.as_str()).collect()
+}
+
+/// Gives nodes that differ from their version in `previous` a new revision and
+/// `modified_at`, and carries the metadata over for the rest.
+pub fn stamp_revisions(nodes: &mut [Node], previous: &[Node], now: i64) {
+    stamp_recursive(nodes, None, &Flat::new(previous), now);
+}
+
+fn stamp_recursive(nodes: &mut [Node], parent: Option<&str>, previous: &Flat, now: i64) {
+    for node in nodes {
+        let old = previous.entries.get(node.id.as_str());
+        let changed =
+            old.is_none_or(|old| !old.same_content(&Entry::new(node, parent), &|_, _, _| false));
+
+        if changed {
+            node.revision = Some(old.and_then(|old| old.node.revision).unwrap_or(0) + 1);
+            node.modified_at = Some(now);
+        } else if let Some(old) = old {
+            node.revision = old.node.revision;
+            node.modified_at = old.node.modified_at;
+        }
+
+        let id = node.id.clone();
+        if let Some(children) = &mut node.children {
+            stamp_recursive(children, Some(&id), previous, now);
+        }
+    }
+}
+
+/// Three-way merge of two copies of the vault that both descend from `base`, node by
+/// node. A change made in only one copy is kept; when both changed a node, the later
+/// `modified_at` wins and the node is reported. Secrets whose ciphertext is identical are
+/// never decrypted; `same_secret` decides about differing ciphertexts, given the node id
+/// and both, and may simply return false when the vault is locked.
+pub fn merge(
+    base: &[Node],
+    local: &[Node],
+    remote: &[Node],
+    same_secret: &dyn Fn(&str, &str, &str) -> bool,
+) -> (Vec<Node>, Vec<MergeConflict>) {
+    let (base, local, remote) = (Flat::new(base), Flat::new(local), Flat::new(remote));
+    let same = |a: Option<&Entry>, b: Option<&Entry>| match (a, b) {
+        (Some(a), Some(b)) => a.same_content(b, same_secret),
+        (a, b) => a.is_none() && b.is_none(),
+    };
+
+    let mut seen = HashSet::new();
+    let mut order = Vec::new();
+    let mut chosen = HashMap::new();
+    let mut conflicts = Vec::new();
+    for &id in local.order.iter().chain(&remote.order) {
+        if !seen.insert(id) {
+            continue;
+        }
+        let (b, l, r) = (
+            base.entries.get(id),
+            local.entries.get(id),
+            remote.entries.get(id),
+        );
+
+        let pick = if same(l, r) || same(b, r) {
+            l
+        } else if same(b, l) {
+            r
+        } else {
+            let (entry, kept, kind) = match (l, r) {
+                (Some(l), Some(r)) if r.node.modified_at > l.node.modified_at => {
+                    (r, MergeSide::Remote, ConflictKind::BothChanged)
+                }
+                (Some(l), Some(_)) => (l, MergeSide::Local, ConflictKind::BothChanged),
+                (Some(l), None) => (l, MergeSide::Local, ConflictKind::ChangedAndDeleted),
+                (None, Some(r)) => (r, MergeSide::Remote, ConflictKind::ChangedAndDeleted),
+                (None, None) => continue,
+            };
+            conflicts.push(MergeConflict {
+                id: id.to_string(),
+                label: entry.node.label.clone(),
+                kind,
+                kept,
+            });
+            Some(entry)
+        };
+
+        if let Some(entry) = pick {
+            order.push(id);
+            chosen.insert(id, entry);
+        }
+    }
+
+    let root_order = if local.roots == base.roots {
+        &remote.roots
+    } else {
+        &local.roots
+    };
+    let mut builder = Builder {
+        chosen,
+        base: &base,
+        local: &local,
+        remote: &remote,
+        by_parent: HashMap::new(),
+        placed: HashSet::new(),
+    };
+    for &id in &order {
+        let parent = builder.parent_of(id);
+        builder.by_parent.entry(parent).or_default().push(id);
+    }
+
+    let mut nodes = builder.build_children(None, root_order);
+    // Nodes that ended up in a cycle, e.g. two folders moved into each other
+    for &id in &order {
+        if !builder.placed.contains(id) {
+            nodes.push(builder.build_node(id, None));
+        }
+    }
+    (nodes, conflicts)
+}
+
+struct Builder<'a, 'b> {
+    chosen: HashMap<&'a str, &'b Entry<'a>>,
+    base: &'b Flat<'a>,
+    local: &'b Flat<'a>,
+    remote: &'b Flat<'a>,
+    /// Chosen nodes by the parent they end up in.
+    by_parent: HashMap<Option<&'a str>, Vec<&'a str>>,
+    placed: HashSet<&'a str>,
+}
+
+impl<'a> Builder<'a, '_> {
+    /// The chosen parent, or the root if that parent is gone or no longer a folder.
+    fn parent_of(&self, id: &str) -> Option<&'a str> {
+        self.chosen[id].parent.filter(|parent| {
+            self.chosen
+                .get(parent)
+                .is_some_and(|entry| matches!(entry.node.node_type, NodeType::Folder))
+        })
+    }
+
+    /// Children of `parent` in `preferred` order, followed by ones `preferred` lacks.
+    fn build_children(&mut self, parent: Option<&'a str>, preferred: &[&'a str]) -> Vec<Node> {
+        let mut ids = self.by_parent.remove(&parent).unwrap_or_default();
+        ids.sort_by_key(|id| {
+            preferred
+                .iter()
+                .position(|preferred| preferred == id)
+                .unwrap_or(usize::MAX)
+        });
+        let mut nodes = Vec::new();
+        for id in ids {
+            if !self.placed.contains(id) {
+                nodes.push(self.build_node(id, parent));
+            }
+        }
+        nodes
+    }
+
+    fn build_node(&mut self, id: &'a str, parent: Option<&'a str>) -> Node {
+        self.placed.insert(id);
+        let entry = self.chosen[id];
+        let mut node = entry.node.clone();
+        node.parent_id = parent.map(str::to_string);
+
+        // Usage is counted per machine, so keep the higher numbers
+        for other in [self.local.entries.get(id), self.remote.entries.get(id)]
+            .into_iter()
+            .flatten()
+        {
+            node.last_used_at = node.last_used_at.max(other.node.last_used_at);
+            node.use_count = node.use_count.max(other.node.use_count);
+        }
+
+        if matches!(node.node_type, NodeType::Folder) {
+            let children = self.child_order(id);
+            node.children = Some(self.build_children(Some(id), &children));
+        }
+        node
+    }
+
+    /// Order of the children of folder `id`: the remote one unless the local copy changed
+    /// it, as for the roots.
+    fn child_order(&self, id: &str) -> Vec<&'a str> {
+        let children = |flat: &Flat<'a>| flat.entries.get(id).map(|entry| entry.children.clone());
+        match (children(self.local), children(self.remote)) {
+            (Some(local), Some(remote)) if children(self.base).as_ref() == Some(&local) => remote,
+            (Some(local), _) => local,
+            (None, remote) => remote.unwrap_or_default(),
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use crate::models::{Node, Totp};
+use hmac::digest::KeyInit;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+const URI_PREFIX: &str = "otpauth://totp/";
+const DEFAULT_DIGITS: u32 = 6;
+const DEFAULT_PERIOD: u32 = 30;
+/// 80 bits, the shortest secret RFC 4226 allows, which is 16 base32 characters.
+const MIN_SECRET_BYTES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// What a TOTP snippet's `otpauth://totp/` URI describes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TotpParams {
+    pub secret: Vec<u8>,
+    pub algorithm: Algorithm,
+    pub digits: u32,
+    /// Seconds each code is valid for.
+    pub period: u32,
+    pub issuer: Option<String>,
+    pub account: Option<String>,
+}
+
+impl TotpParams {
+    /// Reads an `otpauth://totp/Issuer:account?secret=…` URI, or a bare base32 secret
+    /// with the usual 6 digits every 30 seconds.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let Some(rest) = text.strip_prefix(URI_PREFIX) else {
+            if text.starts_with("otpauth://") {
+                return Err("Only time-based (totp) otpauth URIs are supported".into());
+            }
+            return Ok(Self {
+                secret: decode_base32(text).ok_or("Not an otpauth URI or base32 secret")?,
+                algorithm: Algorithm::Sha1,
+                digits: DEFAULT_DIGITS,
+                period: DEFAULT_PERIOD,
+                issuer: None,
+                account: None,
+            });
+        };
+
+        let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let label = percent_decode(label);
+        let (label_issuer, account) = match label.split_once(':') {
+            Some((issuer, account)) => (Some(issuer.trim().to_string()), account.trim()),
+            None => (None, label.trim()),
+        };
+        let mut params = Self {
+            secret: Vec::new(),
+            algorithm: Algorithm::Sha1,
+            digits: DEFAULT_DIGITS,
+            period: DEFAULT_PERIOD,
+            issuer: label_issuer,
+            account: (!account.is_empty()).then(|| account.to_string()),
+        };
+
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(value);
+            match name.to_ascii_lowercase().as_str() {
+                "secret" => {
+                    params.secret = decode_base32(&value).ok_or("The secret is not base32")?
+                }
+                "issuer" if !value.is_empty() => params.issuer = Some(value),
+                "algorithm" => {
+                    params.algorithm = match value.to_ascii_uppercase().as_str() {
+                        "SHA1" => Algorithm::Sha1,
+                        "SHA256" => Algorithm::Sha256,
+                        "SHA512" => Algorithm::Sha512,
+                        _ => return Err(format!("Unsupported algorithm '{}'", value)),
+                    }
+                }
+                "digits" => {
+                    params.digits = value
+                        .parse()
+                        .ok()
+                        .filter(|digits| (6..=8).contains(digits))
+                        .ok_or("Codes must have 6 to 8 digits")?
+                }
+                "period" => {
+                    params.period = value
+                        .parse()
+                        .ok()
+                        .filter(|period| *period > 0)
+                        .ok_or("The period must be a positive number of seconds")?
+                }
+                _ => {}
+            }
+        }
+        if params.secret.is_empty() {
+            return Err("The otpauth URI has no secret".into());
+        }
+        Ok(params)
+    }
+
+    /// `parse` for a new snippet, which also refuses secrets shorter than `MIN_SECRET_BYTES`.
+    /// Snippets made before keep working with theirs.
+    pub fn parse_new(text: &str) -> Result<Self, String> {
+        let params = Self::parse(text)?;
+        if params.secret.len() < MIN_SECRET_BYTES {
+            return Err(format!(
+                "The secret is too short: one-time codes need at least {} bits, {} base32 characters",
+                MIN_SECRET_BYTES * 8,
+                MIN_SECRET_BYTES * 8 / 5
+            ));
+        }
+        Ok(params)
+    }
+
+    /// A label for a new snippet, "Issuer (account)" or whichever of them is known.
+    pub fn label(&self) -> String {
+        match (&self.issuer, &self.account) {
+            (Some(issuer), Some(account)) => format!("{} ({})", issuer, account),
+            (Some(name), None) | (None, Some(name)) => name.clone(),
+            (None, None) => "One-time code".into(),
+        }
+    }
+
+    /// The RFC 6238 code at `unix_secs`.
+    pub fn code(&self, unix_secs: u64) -> Result<String, String> {
+        let counter = (unix_secs / checked_period(self.period)?).to_be_bytes();
+        let hash = match self.algorithm {
+            Algorithm::Sha1 => hmac::<Hmac<Sha1>>(&self.secret, &counter),
+            Algorithm::Sha256 => hmac::<Hmac<Sha256>>(&self.secret, &counter),
+            Algorithm::Sha512 => hmac::<Hmac<Sha512>>(&self.secret, &counter),
+        };
+        // Dynamic truncation from RFC 4226
+        let offset = usize::from(hash[hash.len() - 1] & 0x0f);
+        let binary = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+        let code = binary % 10u32.pow(self.digits);
+        Ok(format!("{:0width$}", code, width = self.digits as usize))
+    }
+}
+
+/// The current code of a TOTP snippet, for the snippet editor.
+#[derive(Debug, Clone, Serialize)]
+pub struct TotpCode {
+    pub code: String,
+    /// Seconds until the code changes.
+    pub remaining: u32,
+}
+
+/// A secret TOTP snippet under `parent_id` for an `otpauth://` URI or base32 secret,
+/// labelled after its issuer and account unless `label` is given.
+pub fn new_snippet(
+    text: &str,
+    parent_id: Option<String>,
+    label: Option<String>,
+) -> Result<Node, String> {
+    let params = TotpParams::parse_new(text)?;
+    let label = label.unwrap_or_else(|| params.label());
+    let mut node = Node::new_snippet(label, parent_id, text.trim().to_string(), true);
+    node.totp = Some(Totp {
+        period: params.period,
+    });
+    Ok(node)
+}
+
+/// Checks the secrets of the TOTP snippets in `nodes`, which have plain values, as
+/// `TotpParams::parse_new` does, e.g. before they are imported.
+pub fn check_new_snippets(nodes: &[Node]) -> Result<(), String> {
+    for node in nodes {
+        if let (Some(_), Some(value)) = (node.totp, &node.value) {
+            TotpParams::parse_new(value).map_err(|e| format!("'{}': {}", node.label, e))?;
+        }
+        check_new_snippets(node.children.as_deref().unwrap_or_default())?;
+    }
+    Ok(())
+}
+
+/// What using `node` gives: the current code for a TOTP snippet, otherwise its plain `value`.
+pub fn resolve(node: &Node, value: String) -> Result<String, String> {
+    if node.totp.is_none() {
+        return Ok(value);
+    }
+    TotpParams::parse(&value)?.code(unix_secs())
+}
+
+fn hmac<M: Mac + KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = <M as Mac>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Seconds until the code of a `period` changes.
+pub fn remaining_secs(period: u32, unix_secs: u64) -> Result<u32, String> {
+    Ok(period - (unix_secs % checked_period(period)?) as u32)
+}
+
+/// `period` for dividing by, which a vault file written elsewhere may have left at 0.
+fn checked_period(period: u32) -> Result<u64, String> {
+    match period {
+        0 => Err("The period must be a positive number of seconds".into()),
+        period => Ok(u64::from(period)),
+    }
+}
+
+/// Seconds since the Unix epoch, for `TotpParams::code`.
+pub fn unix_secs() -> u64 {
+    (crate::models::timestamp_millis() / 1000) as u64
+}
+
+/// RFC 4648 base32, in any case and with or without padding and spaces.
+fn decode_base32(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '=' && *c != '-')
+    {
+        let value = match c.to_ascii_uppercase() {
+            c @ 'A'..='Z' => c as u32 - 'A' as u32,
+            c @ '2'..='7' => c as u32 - '2' as u32 + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    (!bytes.is_empty()).then_some(bytes)
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The seeds of the RFC 6238 test vectors, ASCII digits repeated to the hash size.
+    fn rfc_params(algorithm: Algorithm, seed: &[u8]) -> TotpParams {
+        TotpParams {
+            secret: seed.to_vec(),
+            algorithm,
+            digits: 8,
+            period: 30,
+            issuer: None,
+            account: None,
+        }
+    }
+
+    #[test]
+    fn rfc6238_vectors() {
+        let sha1 = rfc_params(Algorithm::Sha1, b"12345678901234567890");
+        let sha256 = rfc_params(Algorithm::Sha256, b"12345678901234567890123456789012");
+        let sha512 = rfc_params(
+            Algorithm::Sha512,
+            b"1234567890123456789012345678901234567890123456789012345678901234",
+        );
+        let vectors = [
+            (59, "94287082", "46119246", "90693936"),
+            (1111111109, "07081804", "68084774", "25091201"),
+            (1111111111, "14050471", "67062674", "99943326"),
+            (1234567890, "89005924", "91819424", "93441116"),
+            (2000000000, "69279037", "90698825", "38618901"),
+            (20000000000, "65353130", "77737706", "47863826"),
+        ];
+        for (time, expected_sha1, expected_sha256, expected_sha512) in vectors {
+            assert_eq!(sha1.code(time).unwrap(), expected_sha1, "SHA1 at {}", time);
+            assert_eq!(
+                sha256.code(time).unwrap(),
+                expected_sha256,
+                "SHA256 at {}",
+                time
+            );
+            assert_eq!(
+                sha512.code(time).unwrap(),
+                expected_sha512,
+                "SHA512 at {}",
+                time
+            );
+        }
+    }
+
+    #[test]
+    fn uri_parameters_are_read() {
+        // Base32 of the SHA1 seed above
+        let params = TotpParams::parse(
+            "otpauth://totp/Example:alice%40example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&digits=8&algorithm=SHA1&issuer=Example",
+        )
+        .unwrap();
+        assert_eq!(params.secret, b"12345678901234567890");
+        assert_eq!(params.account.as_deref(), Some("alice@example.com"));
+        assert_eq!(params.code(59).unwrap(), "94287082");
+    }
+
+    #[test]
+    fn short_secrets_are_refused_for_new_snippets() {
+        // 15 base32 characters are 75 bits
+        let short = "GEZDGNBVGY3TQOJ";
+        assert!(TotpParams::parse(short).is_ok());
+        assert!(TotpParams::parse_new(short).is_err());
+        assert!(new_snippet(short, None, None).is_err());
+        assert!(new_snippet("GEZDGNBVGY3TQOJQ", None, None).is_ok());
+
+        let mut imported = Node::new_snippet("Weak", None, short.into(), true);
+        imported.totp = Some(Totp { period: 30 });
+        let mut folder = Node::new_folder("Folder", None);
+        folder.children = Some(vec![imported]);
+        assert!(check_new_snippets(&[folder]).is_err());
+    }
+
+    #[test]
+    fn a_zero_period_is_an_error() {
+        let mut params = rfc_params(Algorithm::Sha1, b"12345678901234567890");
+        params.period = 0;
+        assert!(params.code(59).is_err());
+        assert!(remaining_secs(0, 59).is_err());
+        assert_eq!(remaining_secs(30, 59), Ok(1));
+        assert!(TotpParams::parse("otpauth://totp/x?secret=GEZDGNBVGY3TQOJQ&period=0").is_err());
+    }
+}
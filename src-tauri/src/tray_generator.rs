@@ -1,50 +1,388 @@
-use crate::models::{Node, NodeType};
+use crate::models::{AppSettings, Node, NodeType, SortMode};
+use crate::security::VaultManager;
+use crate::sorting;
+use crate::totp;
+use std::sync::Mutex;
 use tauri::{
-    menu::{Menu, MenuBuilder, MenuItemBuilder, SubmenuBuilder},
-    AppHandle, Runtime,
+    image::Image,
+    menu::{
+        CheckMenuItemBuilder, IconMenuItemBuilder, IsMenuItem, Menu, MenuBuilder, MenuItemBuilder,
+        PredefinedMenuItem, SubmenuBuilder,
+    },
+    AppHandle, Manager, Runtime,
 };
 
 use crate::data_manager::DataManager;
 
+/// Menu id prefix for "More…" entries. The suffix is the folder id, or empty for the root.
+pub const MORE_ID_PREFIX: &str = "more:";
+/// Menu id prefix for entries of a mounted vault, followed by `<vault id>/<entry id>`.
+pub const VAULT_ID_PREFIX: &str = "vault:";
+/// Menu id prefix for the vault switcher. The suffix is the vault id.
+pub const SWITCH_VAULT_ID_PREFIX: &str = "switch-vault:";
+
+/// Limits applied when turning the node tree into a tray menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrayOptions {
+    /// Number of nested folder levels whose contents are rendered (0 = unlimited).
+    pub max_depth: u32,
+    /// Number of entries shown per folder before a "More…" entry (0 = unlimited).
+    pub max_items_per_folder: u32,
+    /// Secret snippets are shown greyed out while the vault is locked.
+    pub secrets_locked: bool,
+}
+
+impl TrayOptions {
+    pub fn from_settings(settings: &AppSettings, vault_unlocked: bool) -> Self {
+        Self {
+            max_depth: settings.tray_max_depth,
+            max_items_per_folder: settings.tray_max_items_per_folder,
+            secrets_locked: settings.security.master_password_enabled && !vault_unlocked,
+        }
+    }
+}
+
+/// Platform-independent description of a tray menu entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrayEntry {
+    Snippet {
+        id: String,
+        label: String,
+        icon: Option<String>,
+        enabled: bool,
+        /// Code period of a TOTP snippet, whose label counts down to the next code.
+        totp_period: Option<u32>,
+    },
+    Folder {
+        label: String,
+        icon: Option<String>,
+        children: Vec<TrayEntry>,
+    },
+    /// A separator line, followed by a disabled header item when `label` is set.
+    Separator { label: Option<String> },
+    /// Opens the search window filtered to `folder_id` (`None` = whole vault).
+    More {
+        folder_id: Option<String>,
+        hidden: usize,
+    },
+    /// The entries of a mounted vault, below a header with its name.
+    Vault {
+        id: String,
+        name: String,
+        entries: Vec<TrayEntry>,
+    },
+}
+
+/// An entry of the vault switcher.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrayVault {
+    pub id: String,
+    pub name: String,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrayLayout {
+    pub entries: Vec<TrayEntry>,
+    pub root_on_top: bool,
+    /// `None` when no master password is set, so no Lock/Unlock entry is shown.
+    pub vault_unlocked: Option<bool>,
+    /// The vault switcher, left out while there is only one vault.
+    pub vaults: Vec<TrayVault>,
+}
+
+/// Remembers what was last pushed to the tray so unchanged menus and icons are not rebuilt.
+#[derive(Default)]
+pub struct TrayState {
+    pub last_layout: Mutex<Option<TrayLayout>>,
+    pub last_status: Mutex<Option<crate::tray_status::TrayStatus>>,
+    /// TOTP entries of the current menu, see `tick_countdowns`.
+    pub countdowns: Mutex<Vec<Countdown>>,
+}
+
+/// A TOTP entry of the tray menu, whose label shows the seconds its code has left.
+pub struct Countdown {
+    label: String,
+    period: u32,
+    set_text: Box<dyn Fn(&str) + Send + Sync>,
+}
+
+fn countdown_label(label: &str, period: u32, unix_secs: u64) -> String {
+    match totp::remaining_secs(period, unix_secs) {
+        Ok(remaining) => format!("{} ({}s)", label, remaining),
+        Err(_) => label.to_string(),
+    }
+}
+
+/// Moves the countdowns of TOTP entries on, without rebuilding the menu.
+pub fn tick_countdowns<R: Runtime>(app: &AppHandle<R>) {
+    let Some(tray_state) = app.try_state::<TrayState>() else {
+        return;
+    };
+    let now = totp::unix_secs();
+    for countdown in tray_state.countdowns.lock().unwrap().iter() {
+        (countdown.set_text)(&countdown_label(&countdown.label, countdown.period, now));
+    }
+}
+
+/// Returns the PNG bytes of a bundled tray icon, e.g. `"dot-red"`.
+pub fn bundled_icon(name: &str) -> Option<&'static [u8]> {
+    let bytes: &'static [u8] = match name {
+        "dot-red" => include_bytes!("../icons/tray/dot-red.png"),
+        "dot-orange" => include_bytes!("../icons/tray/dot-orange.png"),
+        "dot-yellow" => include_bytes!("../icons/tray/dot-yellow.png"),
+        "dot-green" => include_bytes!("../icons/tray/dot-green.png"),
+        "dot-blue" => include_bytes!("../icons/tray/dot-blue.png"),
+        "dot-purple" => include_bytes!("../icons/tray/dot-purple.png"),
+        "dot-gray" => include_bytes!("../icons/tray/dot-gray.png"),
+        _ => return None,
+    };
+    Some(bytes)
+}
+
+/// Splits a node icon into a bundled image name and a text prefix (emoji) for the label.
+fn resolve_icon(node: &Node) -> (Option<String>, String) {
+    match node.icon.as_deref().map(str::trim) {
+        Some(icon) if bundled_icon(icon).is_some() => (Some(icon.to_string()), node.label.clone()),
+        Some(icon) if !icon.is_empty() => (None, format!("{} {}", icon, node.label)),
+        _ if node.is_secret.unwrap_or(false) => (None, format!("🔒 {}", node.label)),
+        _ => (None, node.label.clone()),
+    }
+}
+
+/// Whether `node` gets a menu entry. Attachments are saved from the main window, there
+/// is nothing to copy.
+fn shows_in_tray(node: &Node) -> bool {
+    node.show_in_tray.unwrap_or(true) && node.node_type != NodeType::Attachment
+}
+
 pub struct TrayGenerator;
 
 impl TrayGenerator {
     pub fn generate_menu<R: Runtime>(app: &AppHandle<R>, nodes: &[Node]) -> tauri::Result<Menu<R>> {
-        let mut menu_builder = MenuBuilder::new(app);
+        let layout = Self::current_layout(app, nodes);
+
+        let mut countdowns = Vec::new();
+        let menu = Self::build_menu(app, &layout, &mut countdowns)?;
 
+        if let Some(tray_state) = app.try_state::<TrayState>() {
+            *tray_state.last_layout.lock().unwrap() = Some(layout);
+            *tray_state.countdowns.lock().unwrap() = countdowns;
+        }
+
+        Ok(menu)
+    }
+
+    /// Rebuilds the tray menu, skipping the native rebuild when nothing visible changed.
+    pub fn refresh<R: Runtime>(app: &AppHandle<R>, nodes: &[Node]) -> tauri::Result<()> {
+        let layout = Self::current_layout(app, nodes);
+
+        if let Some(tray_state) = app.try_state::<TrayState>() {
+            if tray_state.last_layout.lock().unwrap().as_ref() == Some(&layout) {
+                return Ok(());
+            }
+        }
+
+        let mut countdowns = Vec::new();
+        let menu = Self::build_menu(app, &layout, &mut countdowns)?;
+        if let Some(tray) = app.tray_by_id("main") {
+            tray.set_menu(Some(menu))?;
+        }
+
+        if let Some(tray_state) = app.try_state::<TrayState>() {
+            *tray_state.last_layout.lock().unwrap() = Some(layout);
+            *tray_state.countdowns.lock().unwrap() = countdowns;
+        }
+
+        Ok(())
+    }
+
+    fn current_layout<R: Runtime>(app: &AppHandle<R>, nodes: &[Node]) -> TrayLayout {
         let data_manager = DataManager::new(app);
         let settings = data_manager.load_settings();
+        let vault_manager = app.try_state::<VaultManager>();
+        let is_unlocked = |vault: &str| {
+            vault_manager
+                .as_ref()
+                .is_some_and(|vault_manager| vault_manager.is_unlocked(vault))
+        };
 
-        let is_top = settings.tray_menu_root_position == "top";
-
-        let quit_item = MenuItemBuilder::new("Quit Sklad").id("quit").build(app)?;
-        let open_item = MenuItemBuilder::new("Open Sklad").id("open").build(app)?;
+        let mut layout = Self::layout(nodes, &settings, is_unlocked(&settings.vault_id));
+        for vault in &settings.vaults {
+            if !settings.mounted_vaults.contains(&vault.id) {
+                continue;
+            }
+            let mounted = DataManager::for_vault(app, &vault.id);
+            let mounted_settings = mounted.load_settings();
+            let unlocked = is_unlocked(&vault.id);
+            let options = TrayOptions::from_settings(&mounted_settings, unlocked);
+            layout.entries.push(TrayEntry::Vault {
+                id: vault.id.clone(),
+                name: vault.name.clone(),
+                entries: Self::layout_entries(
+                    &mounted.load_data(),
+                    None,
+                    mounted_settings.root_sort_mode,
+                    0,
+                    &options,
+                ),
+            });
+            // The Lock entry locks every vault, so it shows while any of them is open
+            if mounted_settings.security.master_password_enabled {
+                layout.vault_unlocked = Some(layout.vault_unlocked.unwrap_or(false) || unlocked);
+            }
+        }
+        layout
+    }
 
-        if is_top {
-            menu_builder = menu_builder.item(&quit_item);
-            menu_builder = menu_builder.item(&open_item);
-            menu_builder = menu_builder.separator();
+    pub fn layout(nodes: &[Node], settings: &AppSettings, vault_unlocked: bool) -> TrayLayout {
+        let options = TrayOptions::from_settings(settings, vault_unlocked);
+        TrayLayout {
+            entries: Self::layout_entries(nodes, None, settings.root_sort_mode, 0, &options),
+            root_on_top: settings.tray_menu_root_position == "top",
+            vault_unlocked: settings
+                .security
+                .master_password_enabled
+                .then_some(vault_unlocked),
+            vaults: if settings.vaults.is_empty() {
+                Vec::new()
+            } else {
+                settings
+                    .vault_names()
+                    .into_iter()
+                    .map(|(id, name)| TrayVault {
+                        id: id.to_string(),
+                        name: name.to_string(),
+                        active: id == settings.vault_id,
+                    })
+                    .collect()
+            },
         }
+    }
+
+    /// Converts `nodes` (the contents of `folder_id`, at nesting `depth`) into tray entries.
+    pub fn layout_entries(
+        nodes: &[Node],
+        folder_id: Option<&str>,
+        sort_mode: SortMode,
+        depth: u32,
+        options: &TrayOptions,
+    ) -> Vec<TrayEntry> {
+        let mut visible: Vec<&Node> = nodes.iter().filter(|node| shows_in_tray(node)).collect();
+        sorting::sort_siblings(&mut visible, sort_mode);
 
-        for node in nodes {
+        let limit = match options.max_items_per_folder {
+            0 => visible.len(),
+            max => (max as usize).min(visible.len()),
+        };
+
+        let mut entries = Vec::with_capacity(limit + 1);
+        for node in &visible[..limit] {
             match node.node_type {
                 NodeType::Folder => {
-                    let submenu = Self::generate_submenu(app, node)?;
-                    menu_builder = menu_builder.item(&submenu);
-                }
-                NodeType::Snippet => {
-                    let text = if node.is_secret.unwrap_or(false) {
-                        format!("🔒 {}", node.label)
+                    let children = node.children.as_deref().unwrap_or_default();
+                    let child_entries = if options.max_depth != 0 && depth >= options.max_depth {
+                        vec![TrayEntry::More {
+                            folder_id: Some(node.id.clone()),
+                            hidden: children.iter().filter(|child| shows_in_tray(child)).count(),
+                        }]
                     } else {
-                        node.label.clone()
+                        Self::layout_entries(
+                            children,
+                            Some(&node.id),
+                            node.sort_mode.unwrap_or_default(),
+                            depth + 1,
+                            options,
+                        )
                     };
-                    let item = MenuItemBuilder::new(text).id(&node.id).build(app)?;
-                    menu_builder = menu_builder.item(&item);
+                    let (icon, label) = resolve_icon(node);
+                    entries.push(TrayEntry::Folder {
+                        label,
+                        icon,
+                        children: child_entries,
+                    });
+                }
+                NodeType::Snippet => {
+                    let (icon, label) = resolve_icon(node);
+                    entries.push(TrayEntry::Snippet {
+                        id: node.id.clone(),
+                        label,
+                        icon,
+                        enabled: !(options.secrets_locked && node.is_secret.unwrap_or(false)),
+                        totp_period: node.totp.map(|totp| totp.period),
+                    });
+                }
+                NodeType::Separator => {
+                    let label = node.label.trim();
+                    entries.push(TrayEntry::Separator {
+                        label: (!label.is_empty()).then(|| label.to_string()),
+                    });
                 }
+                NodeType::Attachment => {}
+            }
+        }
+
+        if limit < visible.len() {
+            entries.push(TrayEntry::More {
+                folder_id: folder_id.map(str::to_string),
+                hidden: visible.len() - limit,
+            });
+        }
+
+        entries
+    }
+
+    /// TOTP entries of the menu are added to `countdowns`.
+    fn build_menu<R: Runtime>(
+        app: &AppHandle<R>,
+        layout: &TrayLayout,
+        countdowns: &mut Vec<Countdown>,
+    ) -> tauri::Result<Menu<R>> {
+        let mut menu_builder = MenuBuilder::new(app);
+
+        let quit_item = MenuItemBuilder::new("Quit Sklad").id("quit").build(app)?;
+        let open_item = MenuItemBuilder::new("Open Sklad").id("open").build(app)?;
+        let search_item = MenuItemBuilder::new("Search…").id("search").build(app)?;
+        let create_item = MenuItemBuilder::new("New snippet…")
+            .id("create")
+            .build(app)?;
+        let vault_item = match layout.vault_unlocked {
+            Some(true) => Some(MenuItemBuilder::new("Lock vault").id("lock").build(app)?),
+            Some(false) => Some(MenuItemBuilder::new("Unlock…").id("unlock").build(app)?),
+            None => None,
+        };
+        let vault_menu = Self::build_vault_switcher(app, &layout.vaults)?;
+
+        let items = Self::build_items(app, &layout.entries, "", countdowns)?;
+        let items: Vec<&dyn IsMenuItem<R>> = items.iter().map(|item| item.as_ref()).collect();
+
+        if layout.root_on_top {
+            menu_builder = menu_builder.item(&quit_item);
+            menu_builder = menu_builder.item(&open_item);
+            menu_builder = menu_builder.separator();
+            menu_builder = menu_builder.item(&search_item);
+            menu_builder = menu_builder.item(&create_item);
+            if let Some(vault_item) = &vault_item {
+                menu_builder = menu_builder.item(vault_item);
+            }
+            if let Some(vault_menu) = &vault_menu {
+                menu_builder = menu_builder.item(vault_menu);
             }
+            menu_builder = menu_builder.separator();
         }
 
-        if !is_top {
+        menu_builder = menu_builder.items(&items);
+
+        if !layout.root_on_top {
+            menu_builder = menu_builder.separator();
+            menu_builder = menu_builder.item(&search_item);
+            menu_builder = menu_builder.item(&create_item);
+            if let Some(vault_item) = &vault_item {
+                menu_builder = menu_builder.item(vault_item);
+            }
+            if let Some(vault_menu) = &vault_menu {
+                menu_builder = menu_builder.item(vault_menu);
+            }
             menu_builder = menu_builder.separator();
             menu_builder = menu_builder.item(&open_item);
             menu_builder = menu_builder.item(&quit_item);
@@ -53,32 +391,141 @@ impl TrayGenerator {
         menu_builder.build()
     }
 
-    fn generate_submenu<R: Runtime>(
+    /// A "Vault" submenu with the active vault checked, if there is more than one vault.
+    fn build_vault_switcher<R: Runtime>(
         app: &AppHandle<R>,
-        node: &Node,
-    ) -> tauri::Result<tauri::menu::Submenu<R>> {
-        let mut submenu_builder = SubmenuBuilder::new(app, &node.label);
-
-        if let Some(children) = &node.children {
-            for child in children {
-                match child.node_type {
-                    NodeType::Folder => {
-                        let sub = Self::generate_submenu(app, child)?;
-                        submenu_builder = submenu_builder.item(&sub);
+        vaults: &[TrayVault],
+    ) -> tauri::Result<Option<tauri::menu::Submenu<R>>> {
+        let Some(active) = vaults.iter().find(|vault| vault.active) else {
+            return Ok(None);
+        };
+
+        let mut submenu_builder = SubmenuBuilder::new(app, format!("Vault: {}", active.name));
+        for vault in vaults {
+            submenu_builder = submenu_builder.item(
+                &CheckMenuItemBuilder::new(&vault.name)
+                    .id(format!("{}{}", SWITCH_VAULT_ID_PREFIX, vault.id))
+                    .checked(vault.active)
+                    .enabled(!vault.active)
+                    .build(app)?,
+            );
+        }
+        submenu_builder.build().map(Some)
+    }
+
+    /// `id_prefix` goes before the menu ids of snippets and "More…" entries.
+    fn build_items<R: Runtime>(
+        app: &AppHandle<R>,
+        entries: &[TrayEntry],
+        id_prefix: &str,
+        countdowns: &mut Vec<Countdown>,
+    ) -> tauri::Result<Vec<Box<dyn IsMenuItem<R>>>> {
+        let mut items: Vec<Box<dyn IsMenuItem<R>>> = Vec::with_capacity(entries.len());
+
+        for entry in entries {
+            match entry {
+                TrayEntry::Folder {
+                    label,
+                    icon,
+                    children,
+                } => {
+                    let child_items = Self::build_items(app, children, id_prefix, countdowns)?;
+                    let child_items: Vec<&dyn IsMenuItem<R>> =
+                        child_items.iter().map(|item| item.as_ref()).collect();
+                    let mut submenu_builder = SubmenuBuilder::new(app, label).items(&child_items);
+                    if let Some(image) = icon.as_deref().and_then(Self::load_icon) {
+                        submenu_builder = submenu_builder.submenu_icon(image);
+                    }
+                    items.push(Box::new(submenu_builder.build()?));
+                }
+                TrayEntry::Snippet {
+                    id,
+                    label,
+                    icon,
+                    enabled,
+                    totp_period,
+                } => {
+                    // A locked TOTP entry has no code to count down to
+                    let period = totp_period.filter(|_| *enabled);
+                    let text = match period {
+                        Some(period) => countdown_label(label, period, totp::unix_secs()),
+                        None => label.clone(),
+                    };
+                    let id = format!("{}{}", id_prefix, id);
+                    match icon.as_deref().and_then(Self::load_icon) {
+                        Some(image) => {
+                            let item = IconMenuItemBuilder::new(text)
+                                .id(id)
+                                .icon(image)
+                                .enabled(*enabled)
+                                .build(app)?;
+                            if let Some(period) = period {
+                                let item = item.clone();
+                                countdowns.push(Countdown {
+                                    label: label.clone(),
+                                    period,
+                                    set_text: Box::new(move |text| {
+                                        let _ = item.set_text(text);
+                                    }),
+                                });
+                            }
+                            items.push(Box::new(item));
+                        }
+                        None => {
+                            let item = MenuItemBuilder::new(text)
+                                .id(id)
+                                .enabled(*enabled)
+                                .build(app)?;
+                            if let Some(period) = period {
+                                let item = item.clone();
+                                countdowns.push(Countdown {
+                                    label: label.clone(),
+                                    period,
+                                    set_text: Box::new(move |text| {
+                                        let _ = item.set_text(text);
+                                    }),
+                                });
+                            }
+                            items.push(Box::new(item));
+                        }
                     }
-                    NodeType::Snippet => {
-                        let text = if child.is_secret.unwrap_or(false) {
-                            format!("🔒 {}", child.label)
-                        } else {
-                            child.label.clone()
-                        };
-                        let item = MenuItemBuilder::new(text).id(&child.id).build(app)?;
-                        submenu_builder = submenu_builder.item(&item);
+                }
+                TrayEntry::Separator { label } => {
+                    items.push(Box::new(PredefinedMenuItem::separator(app)?));
+                    if let Some(label) = label {
+                        items.push(Box::new(
+                            MenuItemBuilder::new(label).enabled(false).build(app)?,
+                        ));
                     }
                 }
+                TrayEntry::More { folder_id, hidden } => {
+                    let id = format!(
+                        "{}{}{}",
+                        id_prefix,
+                        MORE_ID_PREFIX,
+                        folder_id.as_deref().unwrap_or("")
+                    );
+                    items.push(Box::new(
+                        MenuItemBuilder::new(format!("More… ({})", hidden))
+                            .id(id)
+                            .build(app)?,
+                    ));
+                }
+                TrayEntry::Vault { id, name, entries } => {
+                    items.push(Box::new(PredefinedMenuItem::separator(app)?));
+                    items.push(Box::new(
+                        MenuItemBuilder::new(name).enabled(false).build(app)?,
+                    ));
+                    let prefix = format!("{}{}/", VAULT_ID_PREFIX, id);
+                    items.extend(Self::build_items(app, entries, &prefix, countdowns)?);
+                }
             }
         }
 
-        submenu_builder.build()
+        Ok(items)
+    }
+
+    fn load_icon(name: &str) -> Option<Image<'static>> {
+        bundled_icon(name).and_then(|bytes| Image::from_bytes(bytes).ok())
     }
 }
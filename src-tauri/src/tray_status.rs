@@ -0,0 +1,167 @@
+use crate::data_manager::DataManager;
+use crate::security::VaultManager;
+use crate::tray_generator::TrayState;
+use std::time::{Duration, Instant};
+use tauri::{image::Image, AppHandle, Emitter, Manager, Runtime};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+/// How long a copied secret stays on the clipboard when `clearClipboard` is enabled.
+pub const CLIPBOARD_CLEAR_DELAY: Duration = Duration::from_secs(30);
+
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What the tray icon currently shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayStatus {
+    /// No master password configured, the plain app icon is used.
+    Plain,
+    Locked,
+    Unlocked,
+    /// A copied secret is waiting to be wiped from the clipboard.
+    ClipboardPending,
+}
+
+impl TrayStatus {
+    fn icon_bytes(self) -> Option<&'static [u8]> {
+        match self {
+            TrayStatus::Plain => None,
+            TrayStatus::Locked => Some(include_bytes!("../icons/tray/tray-locked.png")),
+            TrayStatus::Unlocked => Some(include_bytes!("../icons/tray/tray-unlocked.png")),
+            TrayStatus::ClipboardPending => {
+                Some(include_bytes!("../icons/tray/tray-clipboard.png"))
+            }
+        }
+    }
+}
+
+/// Formats a duration as `m:ss` for the tooltip.
+fn format_remaining(remaining: Duration) -> String {
+    let secs = remaining.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+pub fn tooltip(
+    status: TrayStatus,
+    auto_lock_in: Option<Duration>,
+    wipe_in: Option<Duration>,
+) -> String {
+    let mut text = match status {
+        TrayStatus::Plain => "Sklad".to_string(),
+        TrayStatus::Locked => "Sklad — Locked".to_string(),
+        TrayStatus::Unlocked | TrayStatus::ClipboardPending => match auto_lock_in {
+            Some(remaining) => format!(
+                "Sklad — Unlocked (auto-lock in {})",
+                format_remaining(remaining)
+            ),
+            None => "Sklad — Unlocked".to_string(),
+        },
+    };
+
+    if let Some(remaining) = wipe_in {
+        text.push_str(&format!(
+            "\nClipboard clears in {}",
+            format_remaining(remaining)
+        ));
+    }
+
+    text
+}
+
+/// Updates the tray icon and tooltip from the current `VaultManager` state.
+pub fn update<R: Runtime>(app: &AppHandle<R>) {
+    let Some(tray) = app.tray_by_id("main") else {
+        return;
+    };
+    let vault_manager = app.state::<VaultManager>();
+    let data_manager = DataManager::new(app);
+    let vault = data_manager.vault_id();
+    let now = Instant::now();
+
+    let wipe_in = vault_manager
+        .clipboard_wipe
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|wipe| wipe.at.saturating_duration_since(now));
+    let auto_lock_in = vault_manager
+        .auto_lock_at(vault)
+        .map(|at| at.saturating_duration_since(now));
+
+    let status = if wipe_in.is_some() {
+        TrayStatus::ClipboardPending
+    } else if vault_manager.is_unlocked(vault) {
+        TrayStatus::Unlocked
+    } else if data_manager
+        .load_settings()
+        .security
+        .master_password_enabled
+    {
+        TrayStatus::Locked
+    } else {
+        TrayStatus::Plain
+    };
+
+    let _ = tray.set_tooltip(Some(tooltip(status, auto_lock_in, wipe_in)));
+
+    if let Some(tray_state) = app.try_state::<TrayState>() {
+        let mut last_status = tray_state.last_status.lock().unwrap();
+        if *last_status == Some(status) {
+            return;
+        }
+        *last_status = Some(status);
+    }
+
+    let icon = match status.icon_bytes() {
+        Some(bytes) => Image::from_bytes(bytes).ok(),
+        None => app.default_window_icon().cloned(),
+    };
+    if let Some(icon) = icon {
+        let _ = tray.set_icon(Some(icon));
+    }
+}
+
+/// Runs auto-lock and clipboard wipe deadlines and keeps the tray status current.
+pub fn spawn_ticker<R: Runtime>(app: AppHandle<R>) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(TICK_INTERVAL);
+
+        let vault_manager = app.state::<VaultManager>();
+        let now = Instant::now();
+
+        // Each vault runs out after its own lock timeout
+        let due = vault_manager.due_for_lock(now);
+        let lock_due = !due.is_empty();
+        if lock_due {
+            crate::commands::lock_vaults(&app, &vault_manager, &due);
+            let data_manager = DataManager::new(&app);
+            if due.iter().any(|vault| vault == data_manager.vault_id()) {
+                let _ = app.emit("vault-locked", ());
+            }
+        }
+
+        let due_wipe = {
+            let mut pending = vault_manager.clipboard_wipe.lock().unwrap();
+            match pending.as_ref() {
+                Some(wipe) if wipe.at <= now => pending.take(),
+                _ => None,
+            }
+        };
+        let wiped = due_wipe.is_some();
+        if let Some(wipe) = due_wipe {
+            // Leave the clipboard alone if the user copied something else meanwhile
+            if app.clipboard().read_text().ok().as_deref() == Some(wipe.value.as_str()) {
+                let _ = app.clipboard().clear();
+            }
+            if let Some(dir) = wipe.temp_dir {
+                let _ = std::fs::remove_dir_all(dir);
+            }
+        }
+
+        let active = !vault_manager.unlocked_vaults().is_empty()
+            || vault_manager.clipboard_wipe.lock().unwrap().is_some();
+        if active || lock_due || wiped {
+            update(&app);
+        }
+        crate::tray_generator::tick_countdowns(&app);
+    });
+}
@@ -0,0 +1,430 @@
+use crate::data_manager::DataManager;
+use crate::keychain::{self, SecretStore};
+use crate::models::{timestamp_millis, AppSettings, LockoutAction};
+use crate::security::Key;
+use crate::vault;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Kept next to the vault's other local files, so it survives restarts but isn't synced.
+const ATTEMPTS_FILE_NAME: &str = "unlock-attempts.json";
+/// Failures in a row that are answered without delay, for plain typos.
+const FREE_ATTEMPTS: u32 = 3;
+const MAX_BACKOFF_MS: i64 = 5 * 60 * 1000;
+/// How long `LockoutAction::Lockout` refuses passwords.
+const LOCKOUT_MS: i64 = 24 * 60 * 60 * 1000;
+/// Log entries kept until the next successful unlock shows them.
+const MAX_LOG_ENTRIES: usize = 100;
+
+/// A wrong password or keyfile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailedUnlock {
+    pub at: i64,
+    /// Where the attempt came from, e.g. "app" or "cli".
+    pub source: String,
+}
+
+/// Why an unlock attempt is refused before the password is even checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Blocked {
+    /// Backoff after recent failures.
+    Wait {
+        until: i64,
+    },
+    LockedOut {
+        until: i64,
+    },
+}
+
+impl Blocked {
+    pub fn message(&self, now: i64) -> String {
+        match *self {
+            Blocked::Wait { until } => format!(
+                "Too many failed attempts. Try again in {} seconds.",
+                (until - now + 999) / 1000
+            ),
+            Blocked::LockedOut { until } => format!(
+                "Too many failed attempts. Unlocking is disabled for {} more hours.",
+                (until - now + 3_599_999) / 3_600_000
+            ),
+        }
+    }
+}
+
+/// What a failure leads to under the configured policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Retry,
+    LockedOut,
+    /// The secrets have to be deleted now.
+    Wipe,
+}
+
+/// Failed unlocks of one vault since the last successful one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnlockAttempts {
+    /// Failures in a row; the backoff and the attempt limit count these.
+    pub failures: u32,
+    #[serde(rename = "lastFailureAt", default)]
+    pub last_failure_at: i64,
+    #[serde(rename = "lockedUntil", default)]
+    pub locked_until: Option<i64>,
+    /// Failures not yet shown to the user, which outlast a successful unlock until then.
+    #[serde(default)]
+    pub log: Vec<FailedUnlock>,
+}
+
+/// Delay before the next attempt after `failures` in a row: none for the first few,
+/// then doubling from one second up to `MAX_BACKOFF_MS`.
+pub fn backoff_ms(failures: u32) -> i64 {
+    if failures < FREE_ATTEMPTS {
+        return 0;
+    }
+    let doublings = (failures - FREE_ATTEMPTS).min(20);
+    (1000i64 << doublings).min(MAX_BACKOFF_MS)
+}
+
+impl UnlockAttempts {
+    pub fn load(dir: &Path) -> Self {
+        fs::read(dir.join(ATTEMPTS_FILE_NAME))
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        let path = dir.join(ATTEMPTS_FILE_NAME);
+        if *self == Self::default() {
+            return match fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            };
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Whether a password may be tried at `now`.
+    pub fn check(&self, now: i64) -> Result<(), Blocked> {
+        if let Some(until) = self.locked_until.filter(|until| *until > now) {
+            return Err(Blocked::LockedOut { until });
+        }
+        let until = self.last_failure_at + backoff_ms(self.failures);
+        if until > now {
+            return Err(Blocked::Wait { until });
+        }
+        Ok(())
+    }
+
+    /// Counts a failure and applies the policy once `max_attempts` (0 for no limit)
+    /// failures happened in a row.
+    pub fn record_failure(
+        &mut self,
+        now: i64,
+        source: &str,
+        max_attempts: u32,
+        action: LockoutAction,
+    ) -> Outcome {
+        self.failures += 1;
+        self.last_failure_at = now;
+        self.log.push(FailedUnlock {
+            at: now,
+            source: source.to_string(),
+        });
+        if self.log.len() > MAX_LOG_ENTRIES {
+            self.log.remove(0);
+        }
+
+        if max_attempts == 0 || self.failures < max_attempts {
+            return Outcome::Retry;
+        }
+        match action {
+            LockoutAction::Lockout => {
+                self.locked_until = Some(now + LOCKOUT_MS);
+                // The lockout takes over from the backoff and starts a fresh count
+                self.failures = 0;
+                Outcome::LockedOut
+            }
+            LockoutAction::Wipe => {
+                self.failures = 0;
+                Outcome::Wipe
+            }
+        }
+    }
+
+    /// Clears the count after a successful unlock, including the failure recorded for it
+    /// at `at`. The rest of the log stays until `take_failures`.
+    pub fn record_success(&mut self, at: i64, source: &str) {
+        self.failures = 0;
+        self.last_failure_at = 0;
+        self.locked_until = None;
+        if let Some(i) = self
+            .log
+            .iter()
+            .rposition(|failure| failure.at == at && failure.source == source)
+        {
+            self.log.remove(i);
+        }
+    }
+}
+
+/// `vault::derive_vault_key` held to the attempt limits in `settings`. Failures are logged
+/// under `source`, and one that calls for `LockoutAction::Wipe` resets the vault.
+pub fn derive_vault_key(
+    data_manager: &DataManager,
+    settings: &AppSettings,
+    password: &str,
+    keyfile: Option<&Path>,
+    source: &str,
+) -> Result<Option<Key>, String> {
+    let key = guarded(data_manager, settings, source, || {
+        vault::derive_vault_key(settings, password, keyfile)
+    })?;
+    if let Some(key) = &key {
+        if settings.security.wrapped_key.is_none() {
+            vault::migrate_wrapped_key(data_manager, key)?;
+        } else if settings.security.key_check.is_none() {
+            // Lets `recall_vault_key` tell a remembered key that is out of date
+            let mut settings = data_manager.load_settings();
+            settings.security.key_check = Some(vault::key_check(key)?);
+            let _ = data_manager.save_settings(&settings);
+        }
+    }
+    Ok(key)
+}
+
+/// The key remembered on this device, see `keychain::recall`, held to the same limits as
+/// a password. `None` if there is none, or it no longer is the vault's key.
+pub fn recall_vault_key(
+    data_manager: &DataManager,
+    settings: &AppSettings,
+    store: &dyn SecretStore,
+    source: &str,
+) -> Result<Option<Key>, String> {
+    let Some(wrapped) = &settings.security.remembered_key else {
+        return Ok(None);
+    };
+    guarded(data_manager, settings, source, || {
+        Ok(keychain::recall(store, data_manager.vault_id(), wrapped)?
+            .filter(|key| vault::is_vault_key(settings, key)))
+    })
+}
+
+/// Runs `attempt` unless the attempt limits block it, and counts it as failed if it
+/// gives no key.
+fn guarded(
+    data_manager: &DataManager,
+    settings: &AppSettings,
+    source: &str,
+    attempt: impl FnOnce() -> Result<Option<Key>, String>,
+) -> Result<Option<Key>, String> {
+    let dir = data_manager.vault_dir();
+    let now = timestamp_millis();
+    let outcome = {
+        // Attempts running in parallel, e.g. from several CLI processes, each have to see
+        // the failures of the others
+        let _lock = data_manager
+            .lock_for_write()
+            .map_err(|e| format!("Failed to lock the vault: {}", e))?;
+        let mut attempts = UnlockAttempts::load(dir);
+        attempts
+            .check(now)
+            .map_err(|blocked| blocked.message(now))?;
+
+        // Counted as failed until the password checks out, so that the attempt can't
+        // slip past the backoff while the key is derived
+        let outcome = attempts.record_failure(
+            now,
+            source,
+            settings.security.max_unlock_attempts,
+            settings.security.lockout_action,
+        );
+        attempts
+            .save(dir)
+            .map_err(|e| format!("Failed to record the unlock attempt: {}", e))?;
+        outcome
+    };
+
+    // An attempt that fails with an error counts like a wrong password, so it can't dodge
+    // the lockout once its failure is recorded
+    let error = match attempt() {
+        Ok(Some(key)) => {
+            let _lock = data_manager.lock_for_write();
+            let mut attempts = UnlockAttempts::load(dir);
+            attempts.record_success(now, source);
+            let _ = attempts.save(dir);
+            return Ok(Some(key));
+        }
+        Ok(None) => None,
+        Err(e) => Some(e),
+    };
+    match outcome {
+        Outcome::Retry => error.map_or(Ok(None), Err),
+        Outcome::LockedOut => Err(Blocked::LockedOut {
+            until: now + LOCKOUT_MS,
+        }
+        .message(now)),
+        Outcome::Wipe => {
+            vault::reset_secrets(data_manager)?;
+            Err("Too many failed attempts. The secrets were deleted.".into())
+        }
+    }
+}
+
+/// The failed unlocks not shown yet, which are forgotten with this.
+pub fn take_failures(data_manager: &DataManager) -> Vec<FailedUnlock> {
+    let dir = data_manager.vault_dir();
+    let _lock = data_manager.lock_for_write();
+    let mut attempts = UnlockAttempts::load(dir);
+    let log = std::mem::take(&mut attempts.log);
+    if !log.is_empty() {
+        let _ = attempts.save(dir);
+    }
+    log
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sharing;
+
+    const NOW: i64 = 1_700_000_000_000;
+
+    #[test]
+    fn first_failures_are_free() {
+        let mut attempts = UnlockAttempts::default();
+        for i in 0..FREE_ATTEMPTS {
+            assert_eq!(attempts.check(NOW), Ok(()));
+            let outcome = attempts.record_failure(NOW, "app", 0, LockoutAction::Lockout);
+            assert_eq!(outcome, Outcome::Retry);
+            assert_eq!(attempts.failures, i + 1);
+        }
+        assert_eq!(
+            attempts.check(NOW),
+            Err(Blocked::Wait { until: NOW + 1000 })
+        );
+        assert_eq!(attempts.check(NOW + 1000), Ok(()));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        assert_eq!(backoff_ms(0), 0);
+        assert_eq!(backoff_ms(FREE_ATTEMPTS - 1), 0);
+        assert_eq!(backoff_ms(3), 1000);
+        assert_eq!(backoff_ms(4), 2000);
+        assert_eq!(backoff_ms(5), 4000);
+        assert_eq!(backoff_ms(11), 256_000);
+        assert_eq!(backoff_ms(12), MAX_BACKOFF_MS);
+        assert_eq!(backoff_ms(u32::MAX), MAX_BACKOFF_MS);
+    }
+
+    #[test]
+    fn lockout_expires_after_a_day() {
+        let mut attempts = UnlockAttempts::default();
+        for _ in 0..4 {
+            assert_eq!(
+                attempts.record_failure(NOW, "app", 5, LockoutAction::Lockout),
+                Outcome::Retry
+            );
+        }
+        assert_eq!(
+            attempts.record_failure(NOW, "app", 5, LockoutAction::Lockout),
+            Outcome::LockedOut
+        );
+        assert_eq!(attempts.failures, 0);
+        assert_eq!(
+            attempts.check(NOW + LOCKOUT_MS - 1),
+            Err(Blocked::LockedOut {
+                until: NOW + LOCKOUT_MS
+            })
+        );
+        assert_eq!(attempts.check(NOW + LOCKOUT_MS), Ok(()));
+    }
+
+    #[test]
+    fn wipe_on_the_last_allowed_failure() {
+        let mut attempts = UnlockAttempts::default();
+        let outcomes: Vec<_> = (0..3)
+            .map(|_| attempts.record_failure(NOW, "cli", 3, LockoutAction::Wipe))
+            .collect();
+        assert_eq!(outcomes, [Outcome::Retry, Outcome::Retry, Outcome::Wipe]);
+    }
+
+    #[test]
+    fn success_clears_the_count_but_keeps_the_log() {
+        let mut attempts = UnlockAttempts::default();
+        attempts.record_failure(NOW, "app", 0, LockoutAction::Lockout);
+        attempts.record_failure(NOW + 1, "cli", 0, LockoutAction::Lockout);
+        attempts.record_success(NOW + 1, "cli");
+
+        assert_eq!(attempts.failures, 0);
+        assert_eq!(attempts.last_failure_at, 0);
+        assert_eq!(attempts.locked_until, None);
+        assert_eq!(
+            attempts.log,
+            [FailedUnlock {
+                at: NOW,
+                source: "app".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn correct_password_on_the_last_attempt_does_not_wipe() {
+        let dir = std::env::temp_dir().join(format!("sklad-guard-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let data_manager = DataManager::with_dir(dir.clone());
+        let key = sharing::random_key();
+        let (wrapped_key, salt) = vault::wrap_data_key(&key, "correct horse").unwrap();
+        let mut settings = data_manager.load_settings();
+        settings.security.master_password_enabled = true;
+        settings.security.wrapped_key = Some(wrapped_key);
+        settings.security.derivation_salt = Some(salt);
+        settings.security.max_unlock_attempts = 3;
+        settings.security.lockout_action = LockoutAction::Wipe;
+        data_manager.save_settings(&settings).unwrap();
+
+        let vault_dir = data_manager.vault_dir();
+        let mut attempts = UnlockAttempts::default();
+        attempts.record_failure(NOW, "app", 3, LockoutAction::Wipe);
+        attempts.record_failure(NOW, "app", 3, LockoutAction::Wipe);
+        attempts.save(vault_dir).unwrap();
+
+        let unlocked =
+            derive_vault_key(&data_manager, &settings, "correct horse", None, "app").unwrap();
+        assert_eq!(unlocked, Some(key));
+        assert_eq!(UnlockAttempts::load(vault_dir).failures, 0);
+        let settings = data_manager.load_settings();
+        assert!(settings.security.master_password_enabled);
+        assert!(settings.security.wrapped_key.is_some());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn failing_with_an_error_on_the_last_attempt_still_locks_out() {
+        let dir = std::env::temp_dir().join(format!("sklad-guard-error-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let data_manager = DataManager::with_dir(dir.clone());
+        let mut settings = data_manager.load_settings();
+        settings.security.max_unlock_attempts = 3;
+        settings.security.lockout_action = LockoutAction::Lockout;
+
+        let vault_dir = data_manager.vault_dir();
+        let mut attempts = UnlockAttempts::default();
+        attempts.record_failure(NOW, "cli", 3, LockoutAction::Lockout);
+        attempts.record_failure(NOW, "cli", 3, LockoutAction::Lockout);
+        attempts.save(vault_dir).unwrap();
+
+        let result = guarded(&data_manager, &settings, "cli", || {
+            Err("Failed to read the key file".into())
+        });
+        assert!(result.is_err());
+        let attempts = UnlockAttempts::load(vault_dir);
+        assert!(attempts.locked_until.is_some());
+        assert_eq!(attempts.failures, 0);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
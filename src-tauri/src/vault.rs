@@ -0,0 +1,563 @@
+use crate::data_manager::DataManager;
+use crate::models::{AppSettings, HistoryEntry, Node, NodeType, PastValue, Totp};
+use crate::security::{self, Key};
+use crate::totp::TotpParams;
+use crate::unlock_guard::UnlockAttempts;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Separates nonce and ciphertext in `Node::encrypted_value`.
+pub const ENCRYPTED_VALUE_SEPARATOR: char = ':';
+/// Leads `Node::encrypted_value` in the current format, "v2:nonce:ciphertext", whose
+/// ciphertext is bound to the node id. Older values are just "nonce:ciphertext".
+const ENCRYPTED_VALUE_VERSION: &str = "v2";
+/// `AppSettingsSecurity::ciphertext_version` of vaults whose secrets are all in the
+/// current format.
+pub const CIPHERTEXT_VERSION: u32 = 2;
+/// What `AppSettingsSecurity::key_check` decrypts to.
+const KEY_CHECK_PLAINTEXT: &[u8] = b"sklad key check";
+const SALT_SIZE: usize = 16;
+/// Associated data of the data key wrapped by the password.
+const DATA_KEY_AAD: &[u8] = b"sklad data key";
+/// Salt of vaults whose settings lost theirs, from before salts were always stored.
+const LEGACY_SALT: &str = "default-salt";
+/// Most earlier values a secret keeps; older ones are dropped.
+pub const MAX_HISTORY: usize = 10;
+
+/// Derives the key-encryption key from `password`, and the keyfile if the vault needs one,
+/// and unwraps the vault's data key with it. `keyfile` overrides the configured keyfile
+/// path. Returns `None` when the password or keyfile is wrong.
+pub fn derive_vault_key(
+    settings: &AppSettings,
+    password: &str,
+    keyfile: Option<&Path>,
+) -> Result<Option<Key>, String> {
+    if settings.security.shared {
+        return Err("Shared vaults are unlocked with this device's identity".into());
+    }
+    let keyfile = match (keyfile, &settings.security.keyfile_path) {
+        (_, None) => None,
+        (Some(path), Some(_)) => Some(read_keyfile(path)?),
+        (None, Some(path)) => Some(read_keyfile(Path::new(path))?),
+    };
+    let password = &composite_password(password, keyfile.as_deref());
+
+    if settings.security.wrapped_key.is_some() || settings.security.key_check.is_some() {
+        let salt = settings
+            .security
+            .derivation_salt
+            .as_deref()
+            .ok_or("The key derivation salt is missing. Please reset vault.")?;
+        let kek = security::derive_key_from_password(password, salt);
+        if let Some(wrapped) = &settings.security.wrapped_key {
+            return Ok(unwrap_key(wrapped, &kek, DATA_KEY_AAD));
+        }
+        // Older vaults encrypt the secrets with the derived key itself
+        let check = settings.security.key_check.as_deref().unwrap_or_default();
+        return Ok(key_check_matches(check, &kek).then_some(kek));
+    }
+
+    // The oldest vaults store an Argon2 hash of the password instead of a key check
+    if let Some(hash) = &settings.security.password_hash {
+        if !security::verify_password(password, hash) {
+            return Ok(None);
+        }
+    } else if settings.security.master_password_enabled {
+        return Err("Security enabled but no vault key found. Please reset vault.".into());
+    }
+
+    let salt = settings
+        .security
+        .derivation_salt
+        .as_deref()
+        .unwrap_or(LEGACY_SALT);
+    Ok(Some(security::derive_key_from_password(password, salt)))
+}
+
+/// Wraps `data_key` with a key derived from `password` under a fresh salt. Returns the
+/// wrapped key and the salt, for `AppSettingsSecurity::wrapped_key` and `derivation_salt`.
+pub fn wrap_data_key(data_key: &Key, password: &str) -> Result<(String, String), String> {
+    let salt = hex::encode(security::random_bytes::<SALT_SIZE>());
+    let kek = security::derive_key_from_password(password, &salt);
+    Ok((wrap_key(data_key, &kek, DATA_KEY_AAD)?, salt))
+}
+
+/// Encrypts `key` with `kek`, laid out like `Node::encrypted_value`.
+pub fn wrap_key(key: &Key, kek: &Key, aad: &[u8]) -> Result<String, String> {
+    let (ciphertext, nonce) = security::encrypt_bytes(key, kek, aad)?;
+    Ok(format!(
+        "{}{}{}",
+        hex::encode(nonce),
+        ENCRYPTED_VALUE_SEPARATOR,
+        hex::encode(ciphertext)
+    ))
+}
+
+/// The key in `wrapped`, or `None` if `kek` or `aad` doesn't fit.
+pub fn unwrap_key(wrapped: &str, kek: &Key, aad: &[u8]) -> Option<Key> {
+    let (nonce, ciphertext) = wrapped.split_once(ENCRYPTED_VALUE_SEPARATOR)?;
+    let key = security::decrypt_bytes(
+        &hex::decode(ciphertext).ok()?,
+        &hex::decode(nonce).ok()?,
+        kek,
+        aad,
+    )
+    .ok()?;
+    Key::try_from(key).ok()
+}
+
+/// A key check for `key`, see `AppSettingsSecurity::key_check`.
+pub fn key_check(key: &Key) -> Result<String, String> {
+    let (ciphertext, nonce) = security::encrypt_bytes(KEY_CHECK_PLAINTEXT, key, &[])?;
+    Ok(format!(
+        "{}{}{}",
+        hex::encode(nonce),
+        ENCRYPTED_VALUE_SEPARATOR,
+        hex::encode(ciphertext)
+    ))
+}
+
+/// Whether `key` is the one that encrypts the secrets of the vault. Vaults without a key
+/// check can't tell and accept any key.
+pub fn is_vault_key(settings: &AppSettings, key: &Key) -> bool {
+    settings
+        .security
+        .key_check
+        .as_deref()
+        .is_none_or(|check| key_check_matches(check, key))
+}
+
+fn key_check_matches(check: &str, key: &Key) -> bool {
+    let Some((nonce, ciphertext)) = check.split_once(ENCRYPTED_VALUE_SEPARATOR) else {
+        return false;
+    };
+    let (Ok(nonce), Ok(ciphertext)) = (hex::decode(nonce), hex::decode(ciphertext)) else {
+        return false;
+    };
+    security::decrypt_bytes(&ciphertext, &nonce, key, &[])
+        .is_ok_and(|plaintext| plaintext == KEY_CHECK_PLAINTEXT)
+}
+
+/// Moves a vault from before data keys onto one: the key derived from the password, which
+/// `key` is and which the secrets are already encrypted with, becomes the data key and is
+/// wrapped by itself. Password changes then give it a new wrapping key. Fails if the key
+/// doesn't open the secrets, as when the stored salt no longer matches the one they were
+/// encrypted with.
+pub fn migrate_wrapped_key(data_manager: &DataManager, key: &Key) -> Result<(), String> {
+    let mut settings = data_manager.load_settings();
+    // A key check already proves the key, a password hash only the password
+    if settings.security.key_check.is_none() {
+        let nodes = data_manager.load_data();
+        let legacy = accepts_legacy(&settings);
+        if has_encrypted_secrets(&nodes) && !decrypts_any(&nodes, key, legacy) {
+            return Err(
+                "The password is correct, but the key derived from it can't decrypt the secrets."
+                    .into(),
+            );
+        }
+    }
+
+    settings.security.wrapped_key = Some(wrap_key(key, key, DATA_KEY_AAD)?);
+    // A key check of the old key is one of the data key now
+    if settings.security.key_check.is_none() {
+        settings.security.key_check = Some(key_check(key)?);
+    }
+    settings.security.password_hash = None;
+    // Pins the fallback salt the key was derived with
+    settings
+        .security
+        .derivation_salt
+        .get_or_insert_with(|| LEGACY_SALT.to_string());
+    data_manager
+        .save_settings(&settings)
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+fn decrypts_any(nodes: &[Node], key: &Key, legacy: bool) -> bool {
+    nodes.iter().any(|node| {
+        node.encrypted_value
+            .as_deref()
+            .is_some_and(|encrypted| try_decrypt_value(&node.id, encrypted, key, legacy).is_some())
+            || node
+                .children
+                .as_deref()
+                .is_some_and(|children| decrypts_any(children, key, legacy))
+    })
+}
+
+pub fn read_keyfile(path: &Path) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("Failed to read keyfile '{}': {}", path.display(), e))
+}
+
+/// What the key derivation takes: the master password, followed by
+/// a digest of the keyfile contents when there is one.
+pub fn composite_password(password: &str, keyfile: Option<&[u8]>) -> String {
+    match keyfile {
+        Some(content) => format!("{}\0{}", password, hex::encode(Sha256::digest(content))),
+        None => password.to_string(),
+    }
+}
+
+/// Encrypts the plain secrets in `nodes`, and moves those of the older format over if
+/// `legacy`, see `accepts_legacy`.
+pub fn encrypt_nodes_recursive(nodes: &mut [Node], key: &Key, legacy: bool) -> Result<(), String> {
+    for node in nodes {
+        if matches!(node.node_type, NodeType::Snippet) && node.is_secret.unwrap_or(false) {
+            if let Some(plain_text) = &node.value {
+                if !plain_text.is_empty() {
+                    // Keeps the tray countdown in step with an edited URI
+                    if let Some(Ok(params)) = node.totp.map(|_| TotpParams::parse(plain_text)) {
+                        node.totp = Some(Totp {
+                            period: params.period,
+                        });
+                    }
+                    node.encrypted_value = Some(encrypt_value(&node.id, plain_text, key)?);
+                    node.value = None;
+                }
+            } else if let Some(encrypted) = node.encrypted_value.as_deref() {
+                // Moves secrets of the older format over, as long as they decrypt
+                if legacy && !is_current_format(encrypted) {
+                    if let Some(plain_text) = try_decrypt_value(&node.id, encrypted, key, true) {
+                        node.encrypted_value = Some(encrypt_value(&node.id, &plain_text, key)?);
+                    }
+                }
+            }
+            // Values moved to the history before they were moved over
+            for entry in node.history.iter_mut().flatten().filter(|_| legacy) {
+                if !is_current_format(&entry.encrypted_value) {
+                    if let Some(data) =
+                        try_decrypt_value_bytes(&node.id, &entry.encrypted_value, key, true)
+                    {
+                        entry.encrypted_value = encrypt_value_bytes(&node.id, &data, key)?;
+                    }
+                }
+            }
+        }
+        if let Some(children) = &mut node.children {
+            encrypt_nodes_recursive(children, key, legacy)?;
+        }
+    }
+    Ok(())
+}
+
+/// Puts back the stored ciphertext of secrets whose value is unchanged from `previous`,
+/// so a save doesn't rewrite every secret with a fresh nonce. Ciphertexts of the older
+/// format are left out, so the save moves them over.
+pub fn keep_unchanged_ciphertexts(nodes: &mut [Node], previous: &[Node], key: &Key) {
+    fn collect<'a>(nodes: &'a [Node], out: &mut HashMap<&'a str, &'a str>) {
+        for node in nodes {
+            if let Some(encrypted) = node.encrypted_value.as_deref() {
+                if is_current_format(encrypted) {
+                    out.insert(&node.id, encrypted);
+                }
+            }
+            collect(node.children.as_deref().unwrap_or_default(), out);
+        }
+    }
+
+    fn restore(nodes: &mut [Node], stored: &HashMap<&str, &str>, key: &Key) {
+        for node in nodes {
+            if node.is_secret.unwrap_or(false) {
+                if let (Some(plain_text), Some(encrypted)) =
+                    (&node.value, stored.get(node.id.as_str()))
+                {
+                    if try_decrypt_value(&node.id, encrypted, key, false).as_ref()
+                        == Some(plain_text)
+                    {
+                        node.encrypted_value = Some(encrypted.to_string());
+                        node.value = None;
+                    }
+                }
+            }
+            if let Some(children) = &mut node.children {
+                restore(children, stored, key);
+            }
+        }
+    }
+
+    let mut stored = HashMap::new();
+    collect(previous, &mut stored);
+    restore(nodes, &stored, key);
+}
+
+pub fn decrypt_nodes_recursive(nodes: &mut [Node], key: &Key, legacy: bool) {
+    for node in nodes {
+        if matches!(node.node_type, NodeType::Snippet) && node.is_secret.unwrap_or(false) {
+            if let Some(encrypted) = &node.encrypted_value {
+                if let Some(decrypted) = try_decrypt_value(&node.id, encrypted, key, legacy) {
+                    node.value = Some(decrypted);
+                }
+            }
+        }
+        if let Some(children) = &mut node.children {
+            decrypt_nodes_recursive(children, key, legacy);
+        }
+    }
+}
+
+/// Associated data of a secret: the format version and the node id, so a ciphertext
+/// moved to another snippet no longer decrypts.
+fn value_aad(node_id: &str) -> Vec<u8> {
+    format!(
+        "{}{}{}",
+        ENCRYPTED_VALUE_VERSION, ENCRYPTED_VALUE_SEPARATOR, node_id
+    )
+    .into_bytes()
+}
+
+fn is_current_format(encrypted: &str) -> bool {
+    encrypted
+        .strip_prefix(ENCRYPTED_VALUE_VERSION)
+        .is_some_and(|rest| rest.starts_with(ENCRYPTED_VALUE_SEPARATOR))
+}
+
+/// Encrypts the value of the secret `node_id` for `Node::encrypted_value`.
+pub fn encrypt_value(node_id: &str, plain_text: &str, key: &Key) -> Result<String, String> {
+    encrypt_value_bytes(node_id, plain_text.as_bytes(), key)
+}
+
+/// `encrypt_value` for bytes that need not be text, as attachments hold.
+pub fn encrypt_value_bytes(node_id: &str, data: &[u8], key: &Key) -> Result<String, String> {
+    let (ciphertext, nonce) = security::encrypt_bytes(data, key, &value_aad(node_id))?;
+    Ok(format!(
+        "{}{}{}{}{}",
+        ENCRYPTED_VALUE_VERSION,
+        ENCRYPTED_VALUE_SEPARATOR,
+        hex::encode(nonce),
+        ENCRYPTED_VALUE_SEPARATOR,
+        hex::encode(ciphertext)
+    ))
+}
+
+/// Whether the vault still takes ciphertexts of the older format, which aren't bound to
+/// their node. Only until a save moved them all over, so none can be slipped in later.
+pub fn accepts_legacy(settings: &AppSettings) -> bool {
+    settings.security.ciphertext_version < CIPHERTEXT_VERSION
+}
+
+/// Decrypts the `Node::encrypted_value` of the secret `node_id`, in the older format
+/// too if `legacy`.
+pub fn try_decrypt_value(
+    node_id: &str,
+    encrypted: &str,
+    key: &Key,
+    legacy: bool,
+) -> Option<String> {
+    String::from_utf8(try_decrypt_value_bytes(node_id, encrypted, key, legacy)?).ok()
+}
+
+/// `try_decrypt_value` for bytes that need not be text, as attachments hold.
+pub fn try_decrypt_value_bytes(
+    node_id: &str,
+    encrypted: &str,
+    key: &Key,
+    legacy: bool,
+) -> Option<Vec<u8>> {
+    let parts: Vec<&str> = encrypted.split(ENCRYPTED_VALUE_SEPARATOR).collect();
+    match parts[..] {
+        [version, nonce, ciphertext] if version == ENCRYPTED_VALUE_VERSION => {
+            security::decrypt_bytes(
+                &hex::decode(ciphertext).ok()?,
+                &hex::decode(nonce).ok()?,
+                key,
+                &value_aad(node_id),
+            )
+            .ok()
+        }
+        [nonce, ciphertext] if legacy => security::decrypt(ciphertext, nonce, key)
+            .ok()
+            .map(String::into_bytes),
+        _ => None,
+    }
+}
+
+/// Whether a secret still has a ciphertext of the older format, not bound to its node.
+pub fn has_legacy_ciphertexts(nodes: &[Node]) -> bool {
+    nodes.iter().any(|node| {
+        (node.is_secret.unwrap_or(false)
+            && (node
+                .encrypted_value
+                .as_deref()
+                .is_some_and(|encrypted| !is_current_format(encrypted))
+                || node
+                    .history
+                    .iter()
+                    .flatten()
+                    .any(|entry| !is_current_format(&entry.encrypted_value))))
+            || node.children.as_deref().is_some_and(has_legacy_ciphertexts)
+    })
+}
+
+/// Encrypts the secrets anew with `new`, as when the key of a shared vault is rotated.
+/// Fails if a secret doesn't decrypt with `old`, rather than losing it.
+pub fn reencrypt_nodes(
+    nodes: &mut [Node],
+    old: &Key,
+    new: &Key,
+    legacy: bool,
+) -> Result<(), String> {
+    for node in nodes.iter_mut() {
+        if let Some(encrypted) = &node.encrypted_value {
+            let data = try_decrypt_value_bytes(&node.id, encrypted, old, legacy)
+                .ok_or_else(|| format!("Failed to decrypt '{}'", node.label))?;
+            node.encrypted_value = Some(encrypt_value_bytes(&node.id, &data, new)?);
+        }
+        for entry in node.history.iter_mut().flatten() {
+            let data = try_decrypt_value_bytes(&node.id, &entry.encrypted_value, old, legacy)
+                .ok_or_else(|| format!("Failed to decrypt the history of '{}'", node.label))?;
+            entry.encrypted_value = encrypt_value_bytes(&node.id, &data, new)?;
+        }
+        if let Some(children) = &mut node.children {
+            reencrypt_nodes(children, old, new, legacy)?;
+        }
+    }
+    Ok(())
+}
+
+/// Moves the stored ciphertext of a secret to its history, before it gets a new value.
+pub fn push_history(node: &mut Node) {
+    if let Some(encrypted_value) = node.encrypted_value.take() {
+        add_history(node, encrypted_value);
+    }
+}
+
+/// Adds a ciphertext to the history of `node` unless it is there already, dropping the
+/// oldest values beyond `MAX_HISTORY`.
+fn add_history(node: &mut Node, encrypted_value: String) {
+    let history = node.history.get_or_insert_with(Vec::new);
+    if history
+        .iter()
+        .any(|entry| entry.encrypted_value == encrypted_value)
+    {
+        return;
+    }
+    history.push(HistoryEntry {
+        encrypted_value,
+        replaced_at: crate::models::timestamp_millis(),
+    });
+    let excess = history.len().saturating_sub(MAX_HISTORY);
+    history.drain(..excess);
+}
+
+/// Keeps the stored values of secrets the window changed since `previous` in their
+/// history. Runs after `keep_unchanged_ciphertexts`, so only changed secrets are plain.
+pub fn record_history(
+    nodes: &mut [Node],
+    previous: &[Node],
+    key: &Key,
+    legacy: bool,
+) -> Result<(), String> {
+    fn collect<'a>(nodes: &'a [Node], out: &mut HashMap<&'a str, &'a str>) {
+        for node in nodes {
+            if let Some(encrypted) = node.encrypted_value.as_deref() {
+                out.insert(&node.id, encrypted);
+            }
+            collect(node.children.as_deref().unwrap_or_default(), out);
+        }
+    }
+
+    fn record(
+        nodes: &mut [Node],
+        stored: &HashMap<&str, &str>,
+        key: &Key,
+        legacy: bool,
+    ) -> Result<(), String> {
+        for node in nodes {
+            if let (true, Some(plain_text), Some(&encrypted)) = (
+                node.is_secret.unwrap_or(false),
+                node.value.as_deref(),
+                stored.get(node.id.as_str()),
+            ) {
+                match try_decrypt_value(&node.id, encrypted, key, legacy) {
+                    Some(old) if !old.is_empty() && old != plain_text => {
+                        // Older ciphertexts go in as the current format
+                        let encrypted = if is_current_format(encrypted) {
+                            encrypted.to_string()
+                        } else {
+                            encrypt_value(&node.id, &old, key)?
+                        };
+                        add_history(node, encrypted);
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(children) = &mut node.children {
+                record(children, stored, key, legacy)?;
+            }
+        }
+        Ok(())
+    }
+
+    let mut stored = HashMap::new();
+    collect(previous, &mut stored);
+    record(nodes, &stored, key, legacy)
+}
+
+/// The earlier values of secret `node`, oldest first.
+pub fn reveal_history(node: &Node, key: &Key, legacy: bool) -> Result<Vec<PastValue>, String> {
+    node.history
+        .iter()
+        .flatten()
+        .map(|entry| {
+            Ok(PastValue {
+                value: try_decrypt_value(&node.id, &entry.encrypted_value, key, legacy)
+                    .ok_or("Failed to decrypt the history")?,
+                replaced_at: entry.replaced_at,
+            })
+        })
+        .collect()
+}
+
+pub fn has_encrypted_secrets(nodes: &[Node]) -> bool {
+    nodes.iter().any(|node| {
+        (node.is_secret.unwrap_or(false) && node.encrypted_value.is_some())
+            || node.children.as_deref().is_some_and(has_encrypted_secrets)
+    })
+}
+
+pub fn has_plain_secrets(nodes: &[Node]) -> bool {
+    nodes.iter().any(|n| {
+        let is_plain_secret = matches!(n.node_type, NodeType::Snippet)
+            && n.is_secret.unwrap_or(false)
+            && n.value.is_some();
+        is_plain_secret || n.children.as_deref().is_some_and(has_plain_secrets)
+    })
+}
+
+pub fn remove_secrets_recursive(nodes: &mut Vec<Node>) {
+    nodes.retain(|node| !node.is_secret.unwrap_or(false));
+    for node in nodes.iter_mut() {
+        if let Some(children) = &mut node.children {
+            remove_secrets_recursive(children);
+        }
+    }
+}
+
+/// Deletes the secrets and the master password of the vault, for when it can't be
+/// unlocked any more. Returns the remaining nodes and the new settings.
+pub fn reset_secrets(data_manager: &DataManager) -> Result<(Vec<Node>, AppSettings), String> {
+    let mut nodes = data_manager.load_data();
+    let mut settings = data_manager.load_settings();
+    if settings.security.shared {
+        return Err("A shared vault can't be reset. Remove it from your vaults instead.".into());
+    }
+
+    remove_secrets_recursive(&mut nodes);
+    settings.security.master_password_enabled = false;
+    settings.security.keyfile_path = None;
+    settings.security.recovery_wrapped_key = None;
+    if settings.security.remembered_key.take().is_some() {
+        let _ = crate::keychain::forget(&crate::keychain::PlatformStore, data_manager.vault_id());
+    }
+
+    data_manager.save_data(&nodes).map_err(|e| e.to_string())?;
+    data_manager
+        .save_settings(&settings)
+        .map_err(|e| e.to_string())?;
+    let _ = UnlockAttempts::default().save(data_manager.vault_dir());
+    // The next master password comes with a new key, which the old MAC doesn't match
+    let _ = crate::integrity::forget(data_manager.vault_dir());
+    let _ = crate::attachments::remove_all(data_manager);
+    Ok((nodes, settings))
+}
@@ -0,0 +1,114 @@
+//! Secrets of the older ciphertext format are moved over and then refused.
+
+use sklad_lib::data_manager::DataManager;
+use sklad_lib::models::{Node, VaultProfile};
+use sklad_lib::security::{self, Key};
+use sklad_lib::sharing;
+use sklad_lib::vault;
+
+/// A ciphertext of the format before it was bound to the node.
+fn legacy_ciphertext(value: &str, key: &Key) -> String {
+    let (ciphertext, nonce) = security::encrypt_bytes(value.as_bytes(), key, b"").unwrap();
+    format!("{}:{}", hex::encode(nonce), hex::encode(ciphertext))
+}
+
+fn stored_value(data_manager: &DataManager, id: &str) -> String {
+    DataManager::find_node_by_id(&data_manager.load_data(), id)
+        .and_then(|node| node.encrypted_value)
+        .unwrap()
+}
+
+fn accepts_legacy(data_manager: &DataManager) -> bool {
+    vault::accepts_legacy(&data_manager.load_settings())
+}
+
+#[test]
+fn legacy_ciphertexts_are_refused_once_moved_over() {
+    let dir = std::env::temp_dir().join(format!("sklad-ciphertexts-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let data_manager = DataManager::with_dir(dir.clone());
+    let key = sharing::random_key();
+
+    // A vault from before the current format
+    let mut nodes = data_manager.load_data();
+    let mut settings = data_manager.load_settings();
+    settings.security.ciphertext_version = 0;
+    data_manager.save_settings(&settings).unwrap();
+    let mut secret = Node::new_snippet("Secret", None, String::new(), true);
+    secret.value = None;
+    secret.encrypted_value = Some(legacy_ciphertext("hunter2", &key));
+    let id = secret.id.clone();
+    nodes.push(secret);
+    data_manager.save_data(&nodes).unwrap();
+
+    assert!(accepts_legacy(&data_manager));
+    let legacy = stored_value(&data_manager, &id);
+    assert_eq!(
+        vault::try_decrypt_value(&id, &legacy, &key, true).as_deref(),
+        Some("hunter2")
+    );
+
+    // A save while unlocked moves it over
+    let mut nodes = data_manager.load_data();
+    vault::encrypt_nodes_recursive(&mut nodes, &key, true).unwrap();
+    data_manager.save_data(&nodes).unwrap();
+    assert!(!accepts_legacy(&data_manager));
+    let current = stored_value(&data_manager, &id);
+    assert_ne!(current, legacy);
+    assert_eq!(
+        vault::try_decrypt_value(&id, &current, &key, false).as_deref(),
+        Some("hunter2")
+    );
+
+    // An older ciphertext put back into the file no longer decrypts
+    let mut nodes = data_manager.load_data();
+    DataManager::find_node_by_id_mut(&mut nodes, &id)
+        .unwrap()
+        .encrypted_value = Some(legacy.clone());
+    data_manager.save_data(&nodes).unwrap();
+    assert!(!accepts_legacy(&data_manager));
+    assert_eq!(
+        vault::try_decrypt_value(&id, &legacy, &key, accepts_legacy(&data_manager)),
+        None
+    );
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn new_vaults_refuse_the_older_format() {
+    let dir = std::env::temp_dir().join(format!("sklad-ciphertexts-new-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let data_manager = DataManager::with_dir(dir.clone());
+    assert!(!accepts_legacy(&data_manager));
+    assert_eq!(
+        VaultProfile::new("Work").security.ciphertext_version,
+        vault::CIPHERTEXT_VERSION
+    );
+
+    // Settings from before the version was recorded
+    data_manager
+        .save_settings(&data_manager.load_settings())
+        .unwrap();
+    let path = dir.join("settings.json");
+    let mut settings: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    settings["security"]
+        .as_object_mut()
+        .unwrap()
+        .remove("ciphertextVersion");
+    std::fs::write(&path, settings.to_string()).unwrap();
+    assert!(accepts_legacy(&data_manager));
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn older_format_with_a_short_nonce_is_refused() {
+    let key = sharing::random_key();
+    assert!(security::decrypt("abcd", "0011", &key).is_err());
+    assert_eq!(
+        vault::try_decrypt_value("id", "0011:abcd", &key, true),
+        None
+    );
+}
@@ -0,0 +1,217 @@
+//! Syncs two vault folders through a local bare repository with `git_pull`/`git_push`.
+
+use sklad_lib::attachments;
+use sklad_lib::data_manager::DataManager;
+use sklad_lib::models::Node;
+use sklad_lib::security::Key;
+use sklad_lib::sharing;
+use sklad_lib::sync::{ConflictKind, MergeSide};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+fn never_same(_: &str, _: &str, _: &str) -> bool {
+    false
+}
+
+/// A fresh temp dir holding a bare repository named `remote.git`.
+fn setup(name: &str) -> (PathBuf, String) {
+    let root = std::env::temp_dir().join(format!("sklad-git-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    let remote = root.join("remote.git");
+    let status = Command::new("git")
+        .args(["init", "-q", "--bare"])
+        .arg(&remote)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    (root, remote.to_string_lossy().into_owned())
+}
+
+/// A device whose vault in `root/name` has git enabled with `remote`.
+fn device(root: &Path, name: &str, remote: &str) -> DataManager {
+    let dir = root.join(name);
+    let data_manager = DataManager::with_dir(dir.clone());
+    let mut settings = data_manager.load_settings();
+    settings.git_enabled = true;
+    settings.git_remote = Some(remote.to_string());
+    data_manager.save_settings(&settings).unwrap();
+
+    // Picks up the git settings
+    let data_manager = DataManager::with_dir(dir);
+    data_manager.enable_git(Some(remote)).unwrap();
+    data_manager
+}
+
+fn pull(data_manager: &mut DataManager) -> sklad_lib::sync::MergeReport {
+    let report = data_manager.git_pull(&never_same).unwrap();
+    data_manager.unlock();
+    report
+}
+
+/// Changes the value of the snippet `id` and saves.
+fn edit(data_manager: &DataManager, id: &str, value: &str) {
+    let mut nodes = data_manager.load_data();
+    DataManager::find_node_by_id_mut(&mut nodes, id)
+        .unwrap()
+        .value = Some(value.to_string());
+    data_manager.save_data(&nodes).unwrap();
+    // Later changes must get a later timestamp to win
+    std::thread::sleep(Duration::from_millis(5));
+}
+
+fn value(data_manager: &DataManager, id: &str) -> Option<String> {
+    DataManager::find_node_by_id(&data_manager.load_data(), id).and_then(|node| node.value)
+}
+
+/// Pushes a vault with two snippets from `a` and pulls it into `b`.
+fn shared_start(name: &str) -> (DataManager, DataManager, [String; 2]) {
+    let (root, remote) = setup(name);
+    let a = device(&root, "a", &remote);
+    let mut nodes = a.load_data();
+    let one = Node::new_snippet("One", None, "1".into(), false);
+    let two = Node::new_snippet("Two", None, "2".into(), false);
+    let ids = [one.id.clone(), two.id.clone()];
+    nodes.extend([one, two]);
+    a.save_data(&nodes).unwrap();
+    a.git_push().unwrap();
+
+    let mut b = device(&root, "b", &remote);
+    pull(&mut b);
+    (a, b, ids)
+}
+
+#[test]
+fn pull_brings_pushed_vault() {
+    let (a, b, ids) = shared_start("push-pull");
+    assert_eq!(value(&b, &ids[0]).as_deref(), Some("1"));
+    assert_eq!(value(&b, &ids[1]).as_deref(), Some("2"));
+    assert_eq!(a.load_data().len(), b.load_data().len());
+}
+
+#[test]
+fn changes_to_different_nodes_are_merged() {
+    let (mut a, mut b, [one, two]) = shared_start("merge");
+    edit(&a, &one, "1 from a");
+    a.git_push().unwrap();
+    edit(&b, &two, "2 from b");
+
+    let report = pull(&mut b);
+    assert!(report.conflicts.is_empty());
+    assert_eq!(value(&b, &one).as_deref(), Some("1 from a"));
+    assert_eq!(value(&b, &two).as_deref(), Some("2 from b"));
+
+    b.git_push().unwrap();
+    pull(&mut a);
+    assert_eq!(value(&a, &two).as_deref(), Some("2 from b"));
+}
+
+#[test]
+fn changes_to_the_same_node_are_reported() {
+    let (a, mut b, [one, _]) = shared_start("conflict");
+    edit(&a, &one, "1 from a");
+    a.git_push().unwrap();
+    edit(&b, &one, "1 from b");
+
+    let report = pull(&mut b);
+    assert_eq!(report.conflicts.len(), 1);
+    let conflict = &report.conflicts[0];
+    assert_eq!(conflict.id, one);
+    assert_eq!(conflict.kind, ConflictKind::BothChanged);
+    // The later change wins
+    assert_eq!(conflict.kept, MergeSide::Local);
+    assert_eq!(value(&b, &one).as_deref(), Some("1 from b"));
+}
+
+/// Adds a snippet to folder `parent` and saves.
+fn add_to(data_manager: &DataManager, parent: &str, label: &str) -> String {
+    let mut nodes = data_manager.load_data();
+    let snippet = Node::new_snippet(label, Some(parent.to_string()), label.into(), false);
+    let id = snippet.id.clone();
+    DataManager::find_node_by_id_mut(&mut nodes, parent)
+        .unwrap()
+        .children
+        .get_or_insert_with(Vec::new)
+        .push(snippet);
+    data_manager.save_data(&nodes).unwrap();
+    id
+}
+
+#[test]
+fn adds_to_the_same_folder_are_merged() {
+    let (root, remote) = setup("folder");
+    let a = device(&root, "a", &remote);
+    let mut nodes = a.load_data();
+    let folder = Node::new_folder("Folder", None);
+    let folder_id = folder.id.clone();
+    nodes.push(folder);
+    a.save_data(&nodes).unwrap();
+    a.git_push().unwrap();
+    let mut b = device(&root, "b", &remote);
+    pull(&mut b);
+
+    let from_a = add_to(&a, &folder_id, "From a");
+    a.git_push().unwrap();
+    let from_b = add_to(&b, &folder_id, "From b");
+
+    let report = pull(&mut b);
+    assert!(report.conflicts.is_empty());
+    let nodes = b.load_data();
+    let children: Vec<_> = DataManager::find_node_by_id(&nodes, &folder_id)
+        .unwrap()
+        .children
+        .unwrap()
+        .into_iter()
+        .map(|child| child.id)
+        .collect();
+    assert_eq!(children, [from_b, from_a]);
+}
+
+/// Saves a new attachment large enough for a blob file, returning it with its content.
+fn attach_blob(data_manager: &DataManager, key: &Key) -> (Node, Vec<u8>) {
+    let file = data_manager.file_path.with_file_name("large.bin");
+    let content: Vec<u8> = (0..100 * 1024).map(|i| (i % 251) as u8).collect();
+    fs::write(&file, &content).unwrap();
+
+    let mut nodes = data_manager.load_data();
+    let attachment = attachments::attach(data_manager, &file, None, key).unwrap();
+    assert!(attachment.attachment.as_ref().unwrap().blob);
+    nodes.push(attachment.clone());
+    data_manager.save_data(&nodes).unwrap();
+    (attachment, content)
+}
+
+fn read_attachment(data_manager: &DataManager, id: &str, key: &Key) -> Vec<u8> {
+    let node = DataManager::find_node_by_id(&data_manager.load_data(), id).unwrap();
+    attachments::read(data_manager, &node, key).unwrap()
+}
+
+#[test]
+fn attachment_blobs_are_pushed_and_pulled() {
+    let (root, remote) = setup("blobs");
+    let a = device(&root, "a", &remote);
+    let key = sharing::random_key();
+    let (attachment, content) = attach_blob(&a, &key);
+    let nodes = a.load_data();
+    // Later saves keep working too
+    edit(&a, &nodes[0].id, "changed");
+    a.git_push().unwrap();
+
+    let mut b = device(&root, "b", &remote);
+    pull(&mut b);
+    assert_eq!(read_attachment(&b, &attachment.id, &key), content);
+}
+
+#[test]
+fn attachment_blobs_survive_a_merge() {
+    let (a, mut b, [_, two]) = shared_start("blob-merge");
+    let key = sharing::random_key();
+    let (attachment, content) = attach_blob(&a, &key);
+    a.git_push().unwrap();
+    edit(&b, &two, "2 from b");
+
+    pull(&mut b);
+    assert_eq!(read_attachment(&b, &attachment.id, &key), content);
+}
@@ -0,0 +1,59 @@
+//! Saves made while the vault is locked don't hide changes someone else made.
+
+use sklad_lib::data_manager::DataManager;
+use sklad_lib::models::Node;
+use sklad_lib::security::Key;
+use sklad_lib::sharing;
+
+/// A vault saved once while unlocked, so it has a MAC and a verified copy.
+fn signed_vault(name: &str) -> (std::path::PathBuf, Key) {
+    let dir = std::env::temp_dir().join(format!("sklad-integrity-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let key = sharing::random_key();
+    let mut data_manager = DataManager::with_dir(dir.clone());
+    data_manager.set_integrity_key(Some(key));
+    let mut nodes = data_manager.load_data();
+    nodes.push(Node::new_snippet("Notes", None, "hello".into(), false));
+    data_manager.save_data(&nodes).unwrap();
+    (dir, key)
+}
+
+fn save_while_locked(dir: &std::path::Path) {
+    let data_manager = DataManager::with_dir(dir.to_path_buf());
+    let mut nodes = data_manager.load_data();
+    nodes.push(Node::new_snippet(
+        "Added while locked",
+        None,
+        "x".into(),
+        false,
+    ));
+    data_manager.save_data(&nodes).unwrap();
+}
+
+fn unlock(dir: &std::path::Path, key: Key) -> DataManager {
+    let mut data_manager = DataManager::with_dir(dir.to_path_buf());
+    data_manager.set_integrity_key(Some(key));
+    data_manager
+}
+
+#[test]
+fn own_locked_saves_are_not_reported() {
+    let (dir, key) = signed_vault("own");
+    save_while_locked(&dir);
+    save_while_locked(&dir);
+    assert!(unlock(&dir, key).verify_integrity().unwrap().is_none());
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn locked_saves_over_tampering_are_reported() {
+    let (dir, key) = signed_vault("tampered");
+    let path = dir.join("sklad.json");
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(&path, content.replace("\"hello\"", "\"evil\"")).unwrap();
+
+    save_while_locked(&dir);
+    let report = unlock(&dir, key).verify_integrity().unwrap();
+    assert!(report.is_some_and(|report| !report.changes.is_empty()));
+    let _ = std::fs::remove_dir_all(dir);
+}
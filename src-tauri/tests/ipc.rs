@@ -0,0 +1,121 @@
+//! Runs the IPC server on a socket in a temp dir and talks to it through the client.
+
+#![cfg(unix)]
+
+use sklad_lib::ipc::{self, ClientInfo, Handler, Request, Response, Server};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Echoes the path of `get` requests and approves clients as told.
+struct StubHandler {
+    allow: AtomicBool,
+    approvals: AtomicUsize,
+    handled: AtomicUsize,
+}
+
+impl StubHandler {
+    fn new(allow: bool) -> Arc<Self> {
+        Arc::new(Self {
+            allow: AtomicBool::new(allow),
+            approvals: AtomicUsize::new(0),
+            handled: AtomicUsize::new(0),
+        })
+    }
+}
+
+impl Handler for StubHandler {
+    fn approve(&self, _client: &ClientInfo) -> bool {
+        self.approvals.fetch_add(1, Ordering::SeqCst);
+        self.allow.load(Ordering::SeqCst)
+    }
+
+    fn handle(&self, request: Request) -> Response {
+        self.handled.fetch_add(1, Ordering::SeqCst);
+        match request {
+            Request::Get { path } => Response::Value { value: path },
+            Request::Status => Response::Status {
+                unlocked: true,
+                master_password_enabled: true,
+            },
+            _ => Response::Done,
+        }
+    }
+}
+
+/// A socket path in a fresh temp dir, unique per test.
+fn socket_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sklad-ipc-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join("sklad.sock")
+}
+
+fn get(path: &str) -> Request {
+    Request::Get {
+        path: path.to_string(),
+    }
+}
+
+#[test]
+fn too_long_request_is_dropped() {
+    let handler = StubHandler::new(true);
+    let server = Server::start(socket_path("long"), handler.clone()).unwrap();
+
+    assert!(ipc::send(server.endpoint(), &get(&"a".repeat(70 * 1024))).is_err());
+    assert_eq!(handler.approvals.load(Ordering::SeqCst), 0);
+    assert_eq!(handler.handled.load(Ordering::SeqCst), 0);
+
+    // The server keeps serving other connections
+    let response = ipc::send(server.endpoint(), &Request::Status).unwrap();
+    assert!(matches!(response, Response::Status { unlocked: true, .. }));
+}
+
+#[test]
+fn unapproved_get_is_refused() {
+    let handler = StubHandler::new(false);
+    let server = Server::start(socket_path("denied"), handler.clone()).unwrap();
+
+    let response = ipc::send(server.endpoint(), &get("Work/API key")).unwrap();
+    assert!(matches!(response, Response::Error { message } if message == "Access denied"));
+    assert_eq!(handler.approvals.load(Ordering::SeqCst), 1);
+    assert_eq!(handler.handled.load(Ordering::SeqCst), 0);
+
+    // Status reveals nothing and needs no approval
+    let response = ipc::send(server.endpoint(), &Request::Status).unwrap();
+    assert!(matches!(response, Response::Status { .. }));
+    assert_eq!(handler.approvals.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn approved_get_is_answered() {
+    let handler = StubHandler::new(true);
+    let server = Server::start(socket_path("approved"), handler.clone()).unwrap();
+
+    let response = ipc::send(server.endpoint(), &get("Work/API key")).unwrap();
+    assert!(matches!(response, Response::Value { value } if value == "Work/API key"));
+
+    // An approval only holds for its own connection
+    ipc::send(server.endpoint(), &get("Work/API key")).unwrap();
+    assert_eq!(handler.approvals.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn interpreters_are_told_apart() {
+    let client = |path: &str| ClientInfo {
+        pid: Some(1),
+        executable: Some(PathBuf::from(path)),
+    };
+    for interpreter in [
+        "/usr/bin/python3",
+        "/usr/bin/python3.12",
+        "/usr/local/bin/node",
+        "node.exe",
+        "/bin/bash",
+    ] {
+        assert!(client(interpreter).is_interpreter(), "{}", interpreter);
+    }
+    assert!(!client("/usr/local/bin/deploy-tool").is_interpreter());
+    assert!(!client("/usr/bin/python-helper").is_interpreter());
+    assert!(!ClientInfo::default().is_interpreter());
+}
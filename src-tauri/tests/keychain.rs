@@ -0,0 +1,111 @@
+//! Remembering the vault key with an in-memory stand-in for the platform secret store.
+
+use sklad_lib::data_manager::DataManager;
+use sklad_lib::keychain::{self, SecretStore};
+use sklad_lib::models::AppSettings;
+use sklad_lib::security::Key;
+use sklad_lib::sharing;
+use sklad_lib::unlock_guard::{self, UnlockAttempts};
+use sklad_lib::vault;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+#[derive(Default)]
+struct MemoryStore(Mutex<HashMap<String, Vec<u8>>>);
+
+impl SecretStore for MemoryStore {
+    fn get(&self, vault: &str) -> Result<Option<Vec<u8>>, String> {
+        Ok(self.0.lock().unwrap().get(vault).cloned())
+    }
+
+    fn set(&self, vault: &str, secret: &[u8]) -> Result<(), String> {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(vault.to_string(), secret.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, vault: &str) -> Result<(), String> {
+        self.0.lock().unwrap().remove(vault);
+        Ok(())
+    }
+}
+
+/// A vault in a fresh temp dir with a master password, and its key.
+fn vault_with_password(name: &str) -> (DataManager, AppSettings, Key) {
+    let dir = std::env::temp_dir().join(format!("sklad-keychain-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let data_manager = DataManager::with_dir(dir);
+    let key = sharing::random_key();
+    let (wrapped_key, salt) = vault::wrap_data_key(&key, "correct horse").unwrap();
+
+    let mut settings = data_manager.load_settings();
+    settings.security.master_password_enabled = true;
+    settings.security.wrapped_key = Some(wrapped_key);
+    settings.security.derivation_salt = Some(salt);
+    settings.security.key_check = Some(vault::key_check(&key).unwrap());
+    data_manager.save_settings(&settings).unwrap();
+    (data_manager, settings, key)
+}
+
+fn failures(data_manager: &DataManager) -> UnlockAttempts {
+    UnlockAttempts::load(data_manager.vault_dir())
+}
+
+#[test]
+fn remember_unlock_and_forget() {
+    let store = MemoryStore::default();
+    let (data_manager, mut settings, key) = vault_with_password("remember");
+    let vault = data_manager.vault_id().to_string();
+    settings.security.remembered_key = Some(keychain::remember(&store, &vault, &key).unwrap());
+
+    let recalled =
+        unlock_guard::recall_vault_key(&data_manager, &settings, &store, "keychain").unwrap();
+    assert_eq!(recalled, Some(key));
+    assert_eq!(failures(&data_manager), UnlockAttempts::default());
+
+    keychain::forget(&store, &vault).unwrap();
+    let recalled =
+        unlock_guard::recall_vault_key(&data_manager, &settings, &store, "keychain").unwrap();
+    assert_eq!(recalled, None);
+    let attempts = failures(&data_manager);
+    assert_eq!(attempts.failures, 1);
+    assert_eq!(attempts.log[0].source, "keychain");
+}
+
+#[test]
+fn outdated_key_is_refused_and_counted() {
+    let store = MemoryStore::default();
+    let (data_manager, mut settings, _) = vault_with_password("outdated");
+    let vault = data_manager.vault_id().to_string();
+    let old_key = sharing::random_key();
+    let wrapped = keychain::remember(&store, &vault, &old_key).unwrap();
+    settings.security.remembered_key = Some(wrapped.clone());
+
+    // The store still gives the key back, only the key check tells it is not the vault's
+    assert_eq!(
+        keychain::recall(&store, &vault, &wrapped).unwrap(),
+        Some(old_key)
+    );
+    let recalled =
+        unlock_guard::recall_vault_key(&data_manager, &settings, &store, "keychain").unwrap();
+    assert_eq!(recalled, None);
+    assert_eq!(failures(&data_manager).failures, 1);
+}
+
+#[test]
+fn keychain_unlocks_share_the_backoff() {
+    let store = MemoryStore::default();
+    let (data_manager, mut settings, key) = vault_with_password("backoff");
+    let vault = data_manager.vault_id().to_string();
+    settings.security.remembered_key = Some(keychain::remember(&store, &vault, &key).unwrap());
+    keychain::forget(&store, &vault).unwrap();
+
+    for _ in 0..3 {
+        let recalled = unlock_guard::recall_vault_key(&data_manager, &settings, &store, "keychain");
+        assert_eq!(recalled, Ok(None));
+    }
+    let blocked = unlock_guard::recall_vault_key(&data_manager, &settings, &store, "keychain");
+    assert!(blocked.unwrap_err().starts_with("Too many failed attempts"));
+}
@@ -0,0 +1,54 @@
+//! Settings saved from a window that loaded them before the key changed.
+
+use sklad_lib::data_manager::DataManager;
+use sklad_lib::sharing;
+use sklad_lib::unlock_guard;
+use sklad_lib::vault;
+
+/// What the `save_settings` command does with the window's copy.
+fn save_from_window(data_manager: &DataManager, window: &sklad_lib::models::AppSettings) {
+    let mut settings = data_manager.load_settings();
+    settings.set_preferences(window);
+    data_manager.save_settings(&settings).unwrap();
+}
+
+#[test]
+fn stale_settings_keep_the_new_password() {
+    let dir = std::env::temp_dir().join(format!("sklad-settings-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let data_manager = DataManager::with_dir(dir.clone());
+    let key = sharing::random_key();
+    let (wrapped_key, salt) = vault::wrap_data_key(&key, "old password").unwrap();
+    let mut settings = data_manager.load_settings();
+    settings.security.master_password_enabled = true;
+    settings.security.wrapped_key = Some(wrapped_key);
+    settings.security.derivation_salt = Some(salt);
+    settings.security.key_check = Some(vault::key_check(&key).unwrap());
+    settings.ipc_trusted_clients = vec!["/usr/bin/a".into(), "/usr/bin/b".into()];
+    data_manager.save_settings(&settings).unwrap();
+
+    // The window loads the settings, then the password changes
+    let mut window = data_manager.load_settings();
+    let (wrapped_key, salt) = vault::wrap_data_key(&key, "new password").unwrap();
+    let mut settings = data_manager.load_settings();
+    settings.security.wrapped_key = Some(wrapped_key);
+    settings.security.derivation_salt = Some(salt);
+    data_manager.save_settings(&settings).unwrap();
+
+    window.theme = "light".into();
+    window.security.lock_timeout = 60_000;
+    window.security.wrapped_key = None;
+    window.ipc_trusted_clients = vec!["/usr/bin/b".into(), "/usr/bin/c".into()];
+    save_from_window(&data_manager, &window);
+
+    let settings = data_manager.load_settings();
+    assert_eq!(settings.theme, "light");
+    assert_eq!(settings.security.lock_timeout, 60_000);
+    assert_eq!(settings.ipc_trusted_clients, ["/usr/bin/b"]);
+    let unlocked =
+        unlock_guard::derive_vault_key(&data_manager, &settings, "new password", None, "app")
+            .unwrap();
+    assert_eq!(unlocked, Some(key));
+
+    let _ = std::fs::remove_dir_all(dir);
+}
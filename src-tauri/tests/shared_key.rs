@@ -0,0 +1,58 @@
+//! A shared vault only takes a new key that was rotated from the one it had.
+
+use sklad_lib::data_manager::DataManager;
+use sklad_lib::security::Key;
+use sklad_lib::sharing;
+
+fn wrapped_for_me(data_manager: &DataManager, key: &Key) -> Vec<sharing::Recipient> {
+    let identity = data_manager.identity().unwrap();
+    vec![sharing::wrap(key, "me", &identity.public_key()).unwrap()]
+}
+
+#[test]
+fn keys_are_only_taken_when_rotated_from_the_known_one() {
+    let dir = std::env::temp_dir().join(format!("sklad-shared-key-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let data_manager = DataManager::with_dir(dir.clone());
+    let mut settings = data_manager.load_settings();
+    settings.security.master_password_enabled = true;
+    settings.security.shared = true;
+    data_manager.save_settings(&settings).unwrap();
+
+    let first = sharing::random_key();
+    data_manager
+        .save_shared(&[], &wrapped_for_me(&data_manager, &first))
+        .unwrap();
+    assert_eq!(data_manager.shared_key().unwrap(), Some(first));
+
+    // Two rotations while this device wasn't looking
+    let second = sharing::random_key();
+    let third = sharing::random_key();
+    for (previous, key) in [(&first, &second), (&second, &third)] {
+        let rotation = sharing::rotation(previous, key).unwrap();
+        data_manager
+            .rotate_shared(&[], &wrapped_for_me(&data_manager, key), rotation)
+            .unwrap();
+    }
+    assert_eq!(data_manager.shared_key().unwrap(), Some(third));
+
+    // A key wrapped by someone without the current one
+    let forged = sharing::random_key();
+    data_manager
+        .save_shared(&[], &wrapped_for_me(&data_manager, &forged))
+        .unwrap();
+    assert!(data_manager.shared_key().is_err());
+    let rotation = sharing::rotation(&sharing::random_key(), &forged).unwrap();
+    data_manager
+        .rotate_shared(&[], &wrapped_for_me(&data_manager, &forged), rotation)
+        .unwrap();
+    assert!(data_manager.shared_key().is_err());
+
+    // The rotated key still opens the vault once put back
+    data_manager
+        .save_shared(&[], &wrapped_for_me(&data_manager, &third))
+        .unwrap();
+    assert_eq!(data_manager.shared_key().unwrap(), Some(third));
+
+    let _ = std::fs::remove_dir_all(dir);
+}
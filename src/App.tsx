@@ -3,13 +3,23 @@ import { listen } from "@tauri-apps/api/event";
 import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
 import { Sidebar } from "@/components/Sidebar";
 import { SnippetEditor, SnippetEditorRef } from "@/components/SnippetEditor";
+import { AttachmentView } from "@/components/AttachmentView";
 import { VaultLock } from "@/components/VaultLock";
 import { CommandPalette } from "@/components/CommandPalette";
 import { ThemeToggle } from "@/components/ThemeToggle";
 import { useRef } from "react";
 import { Settings } from "@/components/Settings";
 import { UnsavedChangesModal } from "@/components/UnsavedChangesModal";
+import { IpcApprovalModal } from "@/components/IpcApprovalModal";
+import { TamperWarningModal } from "@/components/TamperWarningModal";
 import { api } from "@/lib/api";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
 import {
   findNodeById,
   updateNodeInTree,
@@ -19,7 +29,7 @@ import {
   insertNodeAtPosition,
   isDescendantOf,
 } from "@/lib/treeUtils";
-import { Node, AppSettings } from "@/types";
+import { Node, AppSettings, IpcApprovalRequest, FailedUnlock, TamperReport } from "@/types";
 import { Container, Search, Lock, Unlock } from "lucide-react";
 
 
@@ -41,6 +51,8 @@ function App() {
   const [pendingNodeSelection, setPendingNodeSelection] = useState<Node | null>(null);
   const [showUnsavedModal, setShowUnsavedModal] = useState(false);
   const [pendingClose, setPendingClose] = useState(false);
+  const [ipcApprovals, setIpcApprovals] = useState<IpcApprovalRequest[]>([]);
+  const [tamperReport, setTamperReport] = useState<TamperReport | null>(null);
 
   useEffect(() => {
     initializeApp();
@@ -54,9 +66,71 @@ function App() {
       loadNodes();
     });
 
+    // Tray "Unlock…" entry
+    const unlistenVaultUnlock = listen("request-vault-unlock", () => {
+      setShowLockModal(true);
+    });
+
+    // Tray "Lock vault" entry
+    const unlistenVaultLocked = listen("vault-locked", () => {
+      setIsUnlocked(false);
+      setNodes((prev) => purgeSecretValues(prev));
+      setSelectedNode((prev) =>
+        prev?.isSecret ? { ...prev, value: "" } : prev
+      );
+      loadNodes();
+    });
+
+    // A save was refused because another instance or the CLI wrote the vault first
+    const unlistenConflict = listen("data-conflict", async () => {
+      await loadNodes();
+      window.alert(
+        "Your last change was not saved because the vault was changed by another program. The latest version has been loaded."
+      );
+    });
+
+    // Someone entered wrong passwords since the vault was last unlocked
+    const unlistenFailedUnlocks = listen<FailedUnlock[]>("failed-unlocks", (event) => {
+      const last = event.payload[event.payload.length - 1];
+      window.alert(
+        `${event.payload.length} failed unlock attempt(s) since the last unlock. The last one was on ${new Date(last.at).toLocaleString()} (${last.source}).`
+      );
+    });
+
+    // Too many failed unlocks deleted the secrets
+    const unlistenVaultReset = listen("vault-reset", () => {
+      setIsUnlocked(false);
+      setShowLockModal(false);
+      initializeApp();
+    });
+
+    // Another vault became active, from the tray or the settings
+    const unlistenVaultSwitched = listen("vault-switched", () => {
+      setSelectedNode((prev) => (prev?.id === "settings" ? prev : null));
+      initializeApp();
+    });
+
+    // A script asked for vault contents through the local socket
+    const unlistenIpcApproval = listen<IpcApprovalRequest>("ipc-approval-request", (event) => {
+      setIpcApprovals((prev) => [...prev, event.payload]);
+    });
+
+    // The vault file failed its integrity check after an unlock
+    const unlistenTampered = listen<TamperReport>("vault-tampered", (event) => {
+      setTamperReport(event.payload);
+    });
+
     return () => {
       unlistenUnlock.then((fn) => fn());
+      unlistenTampered.then((fn) => fn());
+      unlistenConflict.then((fn) => fn());
+      unlistenIpcApproval.then((fn) => fn());
       unlistenUpdate.then((fn) => fn());
+      unlistenVaultUnlock.then((fn) => fn());
+      unlistenVaultLocked.then((fn) => fn());
+      unlistenVaultSwitched.then((fn) => fn());
+      unlistenFailedUnlocks.then((fn) => fn());
+      unlistenVaultReset.then((fn) => fn());
     };
   }, []);
 
@@ -153,10 +227,33 @@ function App() {
     loadNodes();
   };
 
+  const handleSwitchVault = async (id: string) => {
+    if (selectedNode?.type === 'snippet' && snippetEditorRef.current?.isDirty()) {
+      if (settings?.autoSave) {
+        await snippetEditorRef.current.save();
+      } else if (!window.confirm("Discard unsaved changes and switch vaults?")) {
+        return;
+      }
+    }
+    try {
+      await api.switchVault(id);
+    } catch (e) {
+      console.error("Failed to switch vault", e);
+    }
+  };
+
   const handleAddNode = async (
     parentId: string | null,
-    type: "folder" | "snippet"
+    type: "folder" | "snippet" | "attachment" | "totp"
   ) => {
+    if (type === "attachment") {
+      await handleAddAttachment(parentId);
+      return;
+    }
+    if (type === "totp") {
+      await handleAddTotp(parentId);
+      return;
+    }
     const newNode: Node = {
       id: crypto.randomUUID(),
       type,
@@ -173,6 +270,43 @@ function App() {
     await api.saveData(newNodes);
   };
 
+  // The backend reads and encrypts the file, the node is saved like any other
+  const handleAddAttachment = async (parentId: string | null) => {
+    if (!isUnlocked) {
+      setShowLockModal(true);
+      return;
+    }
+    const path = window.prompt("Path of the file to attach (up to 10 MB):");
+    if (!path?.trim()) return;
+    try {
+      const newNode = await api.addAttachment(path.trim(), parentId);
+      const newNodes = addNodeToParent(nodes, parentId, newNode);
+      setNodes(newNodes);
+      setSelectedNode(newNode);
+      await api.saveData(newNodes);
+    } catch (e) {
+      window.alert(`Failed to attach the file: ${e}`);
+    }
+  };
+
+  const handleAddTotp = async (parentId: string | null) => {
+    if (!isUnlocked) {
+      setShowLockModal(true);
+      return;
+    }
+    const uri = window.prompt("otpauth:// URI or base32 secret of the one-time code:");
+    if (!uri?.trim()) return;
+    try {
+      const newNode = await api.addTotp(uri.trim(), parentId);
+      const newNodes = addNodeToParent(nodes, parentId, newNode);
+      setNodes(newNodes);
+      setSelectedNode(newNode);
+      await api.saveData(newNodes);
+    } catch (e) {
+      window.alert(`Failed to add the one-time code: ${e}`);
+    }
+  };
+
   const handleDeleteNode = async (nodeId: string) => {
     const newNodes = removeNodeFromTree(nodes, nodeId);
     setNodes(newNodes);
@@ -340,6 +474,19 @@ function App() {
             <h1 className="text-sm font-semibold tracking-wide text-foreground/80">
               {selectedNode?.id === 'settings' ? 'SKLAD SETTINGS' : 'SNIPPETS'}
             </h1>
+            {settings.vaults && settings.vaults.length > 0 && (
+              <Select value={settings.vaultId} onValueChange={handleSwitchVault}>
+                <SelectTrigger className="h-7 w-40 text-xs" title="Switch vault">
+                  <SelectValue />
+                </SelectTrigger>
+                <SelectContent>
+                  <SelectItem value={settings.vaultId ?? "default"}>{settings.vaultName}</SelectItem>
+                  {settings.vaults.map((vault) => (
+                    <SelectItem key={vault.id} value={vault.id}>{vault.name}</SelectItem>
+                  ))}
+                </SelectContent>
+              </Select>
+            )}
           </div>
           <div className="flex items-center gap-2">
             {isUnlocked ? (
@@ -382,6 +529,7 @@ function App() {
         <div className={`flex-1 ${selectedNode?.id === 'settings' ? 'overflow-auto' : 'overflow-hidden'}`}>
           {selectedNode?.id === 'settings' ? (
             <Settings
+              key={settings.vaultId}
               settings={settings}
               onSetupTrigger={() => setShowSetupModal(true)}
               onSettingsUpdate={async (newSettings) => {
@@ -395,6 +543,13 @@ function App() {
                 setIsUnlocked(false);
               }}
             />
+          ) : selectedNode?.type === "attachment" ? (
+            <AttachmentView
+              node={selectedNode}
+              key={selectedNode.id}
+              isUnlocked={isUnlocked}
+              onUnlockTrigger={() => setShowLockModal(true)}
+            />
           ) : selectedNode ? (
             <SnippetEditor
               ref={snippetEditorRef}
@@ -423,7 +578,10 @@ function App() {
           mode="modal"
           onUnlock={async () => {
             setShowLockModal(false);
-            await loadNodes();
+            // Unlocking can migrate the key and recovery sets a new one, so the old
+            // settings must not be saved back over them
+            const [, appSettings] = await Promise.all([loadNodes(), api.getSettings()]);
+            setSettings(appSettings);
             if (pendingCopyAction) {
               try {
                 await api.copySnippet(pendingCopyAction.id);
@@ -448,6 +606,11 @@ function App() {
             setShowLockModal(false);
           }}
           isInit={false}
+          shared={settings.security.shared}
+          keyfilePath={settings.security.keyfilePath}
+          remembered={!!settings.security.rememberedKey}
+          hasRecoveryKey={!!settings.security.recoveryWrappedKey}
+          minPasswordScore={settings.security.minPasswordScore}
         />
       )}
 
@@ -455,6 +618,7 @@ function App() {
         <VaultLock
           mode="modal"
           isInit={true}
+          minPasswordScore={settings.security.minPasswordScore}
           onUnlock={() => {
             setShowSetupModal(false);
             initializeApp(); // Refresh settings
@@ -469,6 +633,45 @@ function App() {
         />
       )}
 
+      <IpcApprovalModal
+        request={ipcApprovals[0] ?? null}
+        onRespond={async (approved, remember) => {
+          const [request] = ipcApprovals;
+          if (!request) return;
+          setIpcApprovals((prev) => prev.slice(1));
+          try {
+            await api.respondIpcApproval(request.requestId, approved, remember);
+            if (remember) {
+              // The trusted client list changed on the backend
+              setSettings(await api.getSettings());
+            }
+          } catch (e) {
+            console.error("Failed to answer IPC approval:", e);
+          }
+        }}
+      />
+
+      <TamperWarningModal
+        report={tamperReport}
+        onTrust={async () => {
+          try {
+            await api.acceptVaultChanges();
+            setTamperReport(null);
+          } catch (e) {
+            window.alert(`Failed to trust the changes: ${e}`);
+          }
+        }}
+        onRestore={async () => {
+          try {
+            await api.restoreVerifiedVault();
+            setTamperReport(null);
+            await loadNodes();
+          } catch (e) {
+            window.alert(`Failed to restore the vault: ${e}`);
+          }
+        }}
+      />
+
       <UnsavedChangesModal
         open={showUnsavedModal}
         onSave={handleUnsavedSave}
@@ -1,5 +1,6 @@
 import * as React from "react";
 import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
+import { listen } from "@tauri-apps/api/event";
 import {
     Command,
     CommandEmpty,
@@ -16,6 +17,7 @@ import { api } from "@/lib/api";
 export function SearchWindow() {
     const [nodes, setNodes] = React.useState<Node[]>([]);
     const [searchValue, setSearchValue] = React.useState("");
+    const [folderId, setFolderId] = React.useState<string | null>(null);
     const inputRef = React.useRef<HTMLInputElement>(null);
 
     React.useEffect(() => {
@@ -32,6 +34,7 @@ export function SearchWindow() {
         const unlistenFocus = window.onFocusChanged(({ payload: focused }) => {
             if (!focused) {
                 window.hide();
+                setFolderId(null);
             } else {
                 setSearchValue("");
                 // Reload nodes so global search always has the latest changes
@@ -50,27 +53,37 @@ export function SearchWindow() {
         };
         document.addEventListener('keydown', handleKeyDown);
 
+        // Sent by the tray "More…" entries
+        const unlistenFolder = listen<string | null>("search-folder", (event) => {
+            setFolderId(event.payload);
+        });
+
         return () => {
             unlistenFocus.then(f => f());
+            unlistenFolder.then(f => f());
             document.removeEventListener('keydown', handleKeyDown);
         }
     }, []);
 
     // Flatten nodes for search
-    const flattenNodes = (list: Node[], path: string[] = []): (Node & { path: string[] })[] => {
-        let result: (Node & { path: string[] })[] = [];
+    type FlatNode = Node & { path: string[]; ancestorIds: string[] };
+    const flattenNodes = (list: Node[], path: string[] = [], ancestorIds: string[] = []): FlatNode[] => {
+        let result: FlatNode[] = [];
         for (const node of list) {
             const nodePath = [...path, node.label];
-            result.push({ ...node, path: nodePath });
+            result.push({ ...node, path: nodePath, ancestorIds });
             if (node.children) {
-                result = result.concat(flattenNodes(node.children, nodePath));
+                result = result.concat(flattenNodes(node.children, nodePath, [...ancestorIds, node.id]));
             }
         }
         return result;
     };
 
     const flatNodes = React.useMemo(() => flattenNodes(nodes), [nodes]);
-    const snippets = flatNodes.filter(n => n.type === "snippet");
+    const snippets = flatNodes.filter(n =>
+        n.type === "snippet" && (!folderId || n.ancestorIds.includes(folderId))
+    );
+    const folderLabel = folderId ? flatNodes.find(n => n.id === folderId)?.label : undefined;
 
     const handleSelect = async (node: Node) => {
         try {
@@ -113,7 +126,7 @@ export function SearchWindow() {
                     </CommandEmpty>
 
                     {snippets.length > 0 && (
-                        <CommandGroup heading="Snippets" className="px-1">
+                        <CommandGroup heading={folderLabel ? `Snippets in ${folderLabel}` : "Snippets"} className="px-1">
                             {snippets.map((node) => (
                                 <CommandItem
                                     key={node.id}
@@ -0,0 +1,114 @@
+import { useState } from "react";
+import { Node } from "@/types";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Paperclip, Download, Copy, FileCheck, Unlock } from "lucide-react";
+import { api } from "@/lib/api";
+
+interface AttachmentViewProps {
+    node: Node;
+    isUnlocked: boolean;
+    onUnlockTrigger?: () => void;
+}
+
+function formatSize(bytes: number): string {
+    if (bytes < 1024) return `${bytes} B`;
+    if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+    return `${(bytes / 1024 / 1024).toFixed(1)} MB`;
+}
+
+/** Shows an attachment node, whose file is saved or copied rather than edited. */
+export function AttachmentView({ node, isUnlocked, onUnlockTrigger }: AttachmentViewProps) {
+    const [savePath, setSavePath] = useState("");
+    const [status, setStatus] = useState<string | null>(null);
+    const attachment = node.attachment;
+
+    const run = async (action: () => Promise<string>) => {
+        try {
+            setStatus(await action());
+        } catch (error) {
+            setStatus(String(error));
+        }
+    };
+
+    return (
+        <div className="p-6 h-full flex flex-col gap-5 animate-fade-in">
+            <div className="flex items-center gap-3">
+                <div className="p-3 rounded-xl bg-muted/30 border border-border/50">
+                    <Paperclip className="w-6 h-6 text-primary" />
+                </div>
+                <div className="min-w-0">
+                    <h2 className="text-lg font-semibold truncate">{node.label}</h2>
+                    {attachment && (
+                        <p className="text-xs text-muted-foreground font-mono truncate">
+                            {attachment.fileName} · {formatSize(attachment.size)}
+                        </p>
+                    )}
+                </div>
+            </div>
+
+            {!isUnlocked ? (
+                <div className="flex flex-col items-start gap-3 p-4 rounded-lg border border-border/50 bg-muted/20">
+                    <p className="text-sm text-muted-foreground">
+                        The file is encrypted. Unlock the vault to save or copy it.
+                    </p>
+                    <Button variant="outline" size="sm" onClick={onUnlockTrigger} className="gap-2">
+                        <Unlock className="w-4 h-4 text-primary" />
+                        Unlock Now
+                    </Button>
+                </div>
+            ) : (
+                <div className="flex flex-col gap-4">
+                    <div className="flex items-center gap-2">
+                        <Input
+                            className="flex-1 font-mono text-xs"
+                            placeholder={`/path/to/${attachment?.fileName ?? "file"}`}
+                            value={savePath}
+                            onChange={(e) => setSavePath(e.target.value)}
+                        />
+                        <Button
+                            variant="outline"
+                            size="sm"
+                            disabled={!savePath.trim()}
+                            className="gap-2"
+                            onClick={() => run(async () => {
+                                await api.saveAttachment(node.id, savePath.trim());
+                                return "Saved, readable only by you.";
+                            })}
+                        >
+                            <Download className="w-4 h-4" />
+                            Save to File
+                        </Button>
+                    </div>
+                    <div className="flex items-center gap-2">
+                        <Button
+                            variant="outline"
+                            size="sm"
+                            className="gap-2"
+                            onClick={() => run(async () => {
+                                await api.copyAttachment(node.id);
+                                return "Copied as base64.";
+                            })}
+                        >
+                            <Copy className="w-4 h-4" />
+                            Copy as Base64
+                        </Button>
+                        <Button
+                            variant="outline"
+                            size="sm"
+                            className="gap-2"
+                            onClick={() => run(async () => {
+                                const path = await api.copyAttachmentPath(node.id);
+                                return `Copied ${path}. The decrypted file is deleted when the clipboard is cleared.`;
+                            })}
+                        >
+                            <FileCheck className="w-4 h-4" />
+                            Copy Path to Decrypted File
+                        </Button>
+                    </div>
+                    {status && <p className="text-xs text-muted-foreground break-all">{status}</p>}
+                </div>
+            )}
+        </div>
+    );
+}
@@ -0,0 +1,61 @@
+import {
+    Dialog,
+    DialogContent,
+    DialogDescription,
+    DialogFooter,
+    DialogHeader,
+    DialogTitle,
+} from "@/components/ui/dialog";
+import { Button } from "@/components/ui/button";
+import { Terminal } from "lucide-react";
+import { IpcApprovalRequest } from "@/types";
+
+interface IpcApprovalModalProps {
+    request: IpcApprovalRequest | null;
+    onRespond: (approved: boolean, remember: boolean) => void;
+}
+
+export function IpcApprovalModal({ request, onRespond }: IpcApprovalModalProps) {
+    return (
+        <Dialog open={request !== null} onOpenChange={(open) => !open && onRespond(false, false)}>
+            <DialogContent className="sm:max-w-[480px]">
+                <DialogHeader>
+                    <div className="flex items-center gap-2 text-primary mb-2">
+                        <Terminal className="h-5 w-5" />
+                        <DialogTitle>Allow Access to Sklad?</DialogTitle>
+                    </div>
+                    <DialogDescription>
+                        A program wants to read snippets from your vault through the local socket.
+                    </DialogDescription>
+                </DialogHeader>
+                {request && (
+                    <div className="rounded-lg bg-muted/40 border border-border/50 p-3 font-mono text-xs break-all">
+                        {request.client}
+                        {request.pid !== null && (
+                            <span className="text-muted-foreground"> (pid {request.pid})</span>
+                        )}
+                    </div>
+                )}
+                {request && !request.canRemember && (
+                    <p className="text-xs text-muted-foreground">
+                        This program runs scripts, so it can only be allowed once: every script it runs
+                        would share an approval.
+                    </p>
+                )}
+                <DialogFooter className="flex gap-2 sm:gap-0 mt-4">
+                    <Button variant="outline" onClick={() => onRespond(false, false)}>
+                        Deny
+                    </Button>
+                    {request?.canRemember && (
+                        <Button variant="outline" onClick={() => onRespond(true, true)}>
+                            Always Allow
+                        </Button>
+                    )}
+                    <Button onClick={() => onRespond(true, false)} className="bg-primary hover:bg-primary/90">
+                        Allow Once
+                    </Button>
+                </DialogFooter>
+            </DialogContent>
+        </Dialog>
+    );
+}
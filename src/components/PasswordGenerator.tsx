@@ -0,0 +1,190 @@
+import { useEffect, useState } from "react";
+import {
+    Dialog,
+    DialogContent,
+    DialogDescription,
+    DialogFooter,
+    DialogHeader,
+    DialogTitle,
+} from "@/components/ui/dialog";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Label } from "@/components/ui/label";
+import { Switch } from "@/components/ui/switch";
+import { RefreshCw, Wand2 } from "lucide-react";
+import { GeneratedPassword, PasswordPolicy } from "@/types";
+import { api } from "@/lib/api";
+
+interface PasswordGeneratorProps {
+    open: boolean;
+    /** Saved secrets get their new value from the backend, which keeps the old one in their history */
+    regenerate: boolean;
+    onClose: () => void;
+    onUse: (password: string) => void;
+    onRegenerate: (policy: PasswordPolicy) => Promise<void>;
+}
+
+const DEFAULT_POLICY: PasswordPolicy = {
+    length: 20,
+    lowercase: true,
+    uppercase: true,
+    digits: true,
+    symbols: true,
+    excludeAmbiguous: false,
+    passphrase: false,
+    words: 5,
+    separator: "-",
+};
+
+const CHARACTER_CLASSES: { key: "lowercase" | "uppercase" | "digits" | "symbols"; label: string }[] = [
+    { key: "lowercase", label: "a-z" },
+    { key: "uppercase", label: "A-Z" },
+    { key: "digits", label: "0-9" },
+    { key: "symbols", label: "!#$%" },
+];
+
+export function PasswordGenerator({ open, regenerate, onClose, onUse, onRegenerate }: PasswordGeneratorProps) {
+    const [policy, setPolicy] = useState<PasswordPolicy>(DEFAULT_POLICY);
+    const [generated, setGenerated] = useState<GeneratedPassword | null>(null);
+    const [error, setError] = useState<string | null>(null);
+
+    const refresh = () => {
+        api.generatePassword(policy)
+            .then((result) => {
+                setGenerated(result);
+                setError(null);
+            })
+            .catch((e) => {
+                setGenerated(null);
+                setError(String(e));
+            });
+    };
+
+    useEffect(() => {
+        if (open) refresh();
+    }, [open, policy]);
+
+    const update = (changes: Partial<PasswordPolicy>) => setPolicy((prev) => ({ ...prev, ...changes }));
+
+    const handleConfirm = async () => {
+        try {
+            if (regenerate) {
+                await onRegenerate(policy);
+            } else if (generated) {
+                onUse(generated.password);
+            }
+            onClose();
+        } catch (e) {
+            setError(String(e));
+        }
+    };
+
+    return (
+        <Dialog open={open} onOpenChange={(isOpen) => !isOpen && onClose()}>
+            <DialogContent className="sm:max-w-[480px]">
+                <DialogHeader>
+                    <div className="flex items-center gap-2 mb-2">
+                        <Wand2 className="h-5 w-5 text-primary" />
+                        <DialogTitle>{regenerate ? "Regenerate Secret" : "Generate Password"}</DialogTitle>
+                    </div>
+                    <DialogDescription>
+                        {regenerate
+                            ? "The secret gets a new random value. The current one is kept, encrypted, in its history."
+                            : "A random password or passphrase from a secure random number generator."}
+                    </DialogDescription>
+                </DialogHeader>
+
+                <div className="space-y-4">
+                    <div className="flex items-center space-x-3">
+                        <Switch
+                            id="generator-passphrase"
+                            checked={policy.passphrase}
+                            onCheckedChange={(passphrase) => update({ passphrase })}
+                        />
+                        <Label htmlFor="generator-passphrase">Passphrase of words</Label>
+                    </div>
+
+                    {policy.passphrase ? (
+                        <div className="flex items-center gap-3">
+                            <Label className="w-20">Words</Label>
+                            <Input
+                                type="number"
+                                min={3}
+                                max={20}
+                                className="w-24"
+                                value={policy.words}
+                                onChange={(e) => update({ words: Number(e.target.value) })}
+                            />
+                            <Label className="w-20">Separator</Label>
+                            <Input
+                                className="w-16 font-mono"
+                                value={policy.separator}
+                                onChange={(e) => update({ separator: e.target.value })}
+                            />
+                        </div>
+                    ) : (
+                        <>
+                            <div className="flex items-center gap-3">
+                                <Label className="w-20">Length</Label>
+                                <Input
+                                    type="number"
+                                    min={4}
+                                    max={256}
+                                    className="w-24"
+                                    value={policy.length}
+                                    onChange={(e) => update({ length: Number(e.target.value) })}
+                                />
+                            </div>
+                            <div className="flex flex-wrap items-center gap-4">
+                                {CHARACTER_CLASSES.map(({ key, label }) => (
+                                    <div key={key} className="flex items-center space-x-2">
+                                        <Switch
+                                            id={`generator-${key}`}
+                                            checked={policy[key]}
+                                            onCheckedChange={(checked) => update({ [key]: checked })}
+                                        />
+                                        <Label htmlFor={`generator-${key}`} className="font-mono">{label}</Label>
+                                    </div>
+                                ))}
+                            </div>
+                            <div className="flex items-center space-x-3">
+                                <Switch
+                                    id="generator-ambiguous"
+                                    checked={policy.excludeAmbiguous}
+                                    onCheckedChange={(excludeAmbiguous) => update({ excludeAmbiguous })}
+                                />
+                                <Label htmlFor="generator-ambiguous">Leave out look-alikes (0 O o I l 1)</Label>
+                            </div>
+                        </>
+                    )}
+
+                    <div className="rounded-lg bg-muted/40 border border-border/50 p-3 space-y-1">
+                        {!regenerate && generated && (
+                            <div className="flex items-center gap-2">
+                                <span className="flex-1 font-mono text-sm break-all">{generated.password}</span>
+                                <Button variant="ghost" size="icon" className="h-7 w-7" onClick={refresh} title="Generate Another">
+                                    <RefreshCw className="w-4 h-4" />
+                                </Button>
+                            </div>
+                        )}
+                        {generated && (
+                            <p className="text-xs text-muted-foreground">
+                                About {Math.round(generated.entropyBits)} bits of entropy
+                            </p>
+                        )}
+                        {error && <p className="text-xs text-destructive">{error}</p>}
+                    </div>
+                </div>
+
+                <DialogFooter className="flex gap-2 sm:gap-0 mt-4">
+                    <Button variant="outline" onClick={onClose}>
+                        Cancel
+                    </Button>
+                    <Button onClick={handleConfirm} disabled={!generated} className="bg-primary hover:bg-primary/90">
+                        {regenerate ? "Regenerate" : "Use Password"}
+                    </Button>
+                </DialogFooter>
+            </DialogContent>
+        </Dialog>
+    );
+}
@@ -0,0 +1,54 @@
+import { useState } from "react";
+import { api } from "../lib/api";
+import { Button } from "@/components/ui/button";
+import { KeyRound } from "lucide-react";
+
+interface RecoveryKeyPanelProps {
+    recoveryKey: string;
+    onDone: () => void;
+}
+
+/** Shows a fresh recovery key once, with the option to save the emergency kit. */
+export function RecoveryKeyPanel({ recoveryKey, onDone }: RecoveryKeyPanelProps) {
+    const [kitPath, setKitPath] = useState("");
+    const [status, setStatus] = useState<string | null>(null);
+
+    const handleSaveKit = async () => {
+        try {
+            await api.saveEmergencyKit(recoveryKey, kitPath.trim());
+            setStatus("Saved. Print it, then delete the file.");
+        } catch (error) {
+            setStatus(String(error));
+        }
+    };
+
+    return (
+        <div className="flex flex-col gap-3 p-3 rounded-lg border border-primary/30 bg-primary/5 animate-fade-in">
+            <div className="flex items-center gap-2 text-sm font-semibold">
+                <KeyRound className="w-4 h-4 text-primary" />
+                Your Recovery Key
+            </div>
+            <p className="text-xs text-muted-foreground">
+                It opens the secrets if the master password is lost. It is shown only now; write it down or save the emergency kit.
+            </p>
+            <code className="block text-center text-sm font-mono tracking-wide p-2 rounded-md bg-background/70 border border-border/50 select-all break-all">
+                {recoveryKey}
+            </code>
+            <div className="flex items-center gap-2">
+                <input
+                    className="flex-1 px-3 py-1.5 text-xs font-mono bg-background/50 border border-border/50 rounded-md focus:outline-none focus:ring-1 focus:ring-primary focus:border-primary/50 transition-all placeholder:text-muted-foreground/30"
+                    placeholder="/path/to/emergency-kit.html"
+                    value={kitPath}
+                    onChange={(e) => setKitPath(e.target.value)}
+                />
+                <Button type="button" variant="outline" size="sm" onClick={handleSaveKit} disabled={!kitPath.trim()} className="h-8 text-xs">
+                    Save Emergency Kit
+                </Button>
+            </div>
+            {status && <p className="text-xs text-muted-foreground">{status}</p>}
+            <Button type="button" onClick={onDone} className="w-full h-10 font-semibold">
+                I Have Saved It
+            </Button>
+        </div>
+    );
+}
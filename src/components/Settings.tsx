@@ -1,8 +1,8 @@
 import { useState, useEffect } from "react";
-import { AppSettings } from "@/types";
+import { AppSettings, AuditReport, MergeReport, Recipient, SortMode, VaultInfo } from "@/types";
 import { api } from "@/lib/api";
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "@/components/ui/card";
-import { Shield, Lock, Settings as SettingsIcon, Database, ExternalLink, FileJson, AlertCircle, Bell, Power, Github } from "lucide-react";
+import { Shield, Lock, Settings as SettingsIcon, Database, ExternalLink, FileJson, AlertCircle, Bell, Power, Github, Terminal, FolderSync, GitMerge, GitBranch, Download, Upload, Layers, Plus, Pencil, Trash2, Users } from "lucide-react";
 import { Button } from "@/components/ui/button";
 import { Switch } from "@/components/ui/switch";
 import { Label } from "@/components/ui/label";
@@ -16,6 +16,8 @@ import {
     SelectTrigger,
     SelectValue,
 } from "@/components/ui/select";
+import { RecoveryKeyPanel } from "./RecoveryKeyPanel";
+import { StrengthMeter } from "./StrengthMeter";
 
 interface SettingsProps {
     settings: AppSettings;
@@ -28,22 +30,235 @@ export function Settings({ settings, onResetTrigger, onSetupTrigger, onSettingsU
     const [snippetsPath, setSnippetsPath] = useState<string>("");
     const [confirmReset, setConfirmReset] = useState(false);
     const [appVersion, setAppVersion] = useState("");
+    const [ipcEndpoint, setIpcEndpoint] = useState<string | null>(null);
+    const [vaultPathDraft, setVaultPathDraft] = useState(settings.vaultPath ?? "");
+    const [vaultPathError, setVaultPathError] = useState<string | null>(null);
+    const [syncConflicts, setSyncConflicts] = useState<string[]>([]);
+    const [mergeReport, setMergeReport] = useState<MergeReport | null>(null);
+    const [gitRemoteDraft, setGitRemoteDraft] = useState(settings.gitRemote ?? "");
+    const [gitStatus, setGitStatus] = useState<string | null>(null);
+    const [gitBusy, setGitBusy] = useState(false);
+    const [vaults, setVaults] = useState<VaultInfo[]>([]);
+    const [newVaultName, setNewVaultName] = useState("");
+    const [vaultError, setVaultError] = useState<string | null>(null);
+    const [identity, setIdentity] = useState("");
+    const [recipients, setRecipients] = useState<Recipient[]>([]);
+    const [sharedDraft, setSharedDraft] = useState({ name: "", memberName: "", folder: "" });
+    const [memberDraft, setMemberDraft] = useState({ name: "", publicKey: "" });
+    const [sharingError, setSharingError] = useState<string | null>(null);
+    const [keyfileDraft, setKeyfileDraft] = useState(settings.security.keyfilePath ?? "");
+    const [keyfilePassword, setKeyfilePassword] = useState("");
+    const [currentPassword, setCurrentPassword] = useState("");
+    const [newPassword, setNewPassword] = useState("");
+    const [recoveryPassword, setRecoveryPassword] = useState("");
+    const [newRecoveryKey, setNewRecoveryKey] = useState<string | null>(null);
+    const [unlockFactorError, setUnlockFactorError] = useState<string | null>(null);
+    const [breachListDraft, setBreachListDraft] = useState(settings.security.breachListPath ?? "");
+    const [auditReport, setAuditReport] = useState<AuditReport | null>(null);
+    const [auditError, setAuditError] = useState<string | null>(null);
+    const [auditing, setAuditing] = useState(false);
+
+    useEffect(() => {
+        api.listVaults().then(setVaults).catch(console.error);
+    }, [settings.vaultId, settings.vaults?.length]);
+
+    useEffect(() => {
+        api.getIdentity().then(setIdentity).catch(console.error);
+    }, []);
+
+    useEffect(() => {
+        if (settings.security.shared) {
+            api.listRecipients().then(setRecipients).catch(console.error);
+        }
+    }, [settings.vaultId, settings.security.shared]);
+
+    useEffect(() => {
+        api.getIpcEndpoint().then(setIpcEndpoint).catch(console.error);
+    }, [settings.ipcEnabled]);
 
     useEffect(() => {
         import('@tauri-apps/api/app').then(app => {
             app.getVersion().then(setAppVersion);
         });
+    }, []);
 
+    useEffect(() => {
         const fetchPath = async () => {
             try {
                 const path = await api.getSnippetsPath();
                 setSnippetsPath(path);
+                setSyncConflicts(await api.getSyncConflicts());
             } catch (error) {
                 console.error("Failed to fetch snippets path", error);
             }
         };
         fetchPath();
-    }, []);
+    }, [settings.vaultPath]);
+
+    const handleVaultPathApply = async () => {
+        const vaultPath = vaultPathDraft.trim() || null;
+        try {
+            // Saved here first so a missing folder is reported before the settings change
+            await api.saveSettings({ ...settings, vaultPath });
+            setVaultPathError(null);
+            onSettingsUpdate({ ...settings, vaultPath });
+        } catch (error) {
+            setVaultPathError(String(error));
+        }
+    };
+
+    const handleGitSettings = async (gitEnabled: boolean, gitRemote: string | null) => {
+        try {
+            // Saved here first so that git errors are shown before the settings change
+            await api.saveSettings({ ...settings, gitEnabled, gitRemote });
+            setGitStatus(null);
+            onSettingsUpdate({ ...settings, gitEnabled, gitRemote });
+        } catch (error) {
+            setGitStatus(String(error));
+        }
+    };
+
+    const handleGit = async (action: "pull" | "push") => {
+        setGitBusy(true);
+        try {
+            if (action === "pull") {
+                const report = await api.gitPull();
+                setMergeReport(report.conflicts.length > 0 ? report : null);
+                setGitStatus(report.mergedFiles.length > 0 ? "Pulled remote changes." : "Already up to date.");
+            } else {
+                await api.gitPush();
+                setGitStatus("Pushed.");
+            }
+        } catch (error) {
+            setGitStatus(String(error));
+        } finally {
+            setGitBusy(false);
+        }
+    };
+
+    const handleMerge = async () => {
+        try {
+            setMergeReport(await api.mergeSyncConflicts());
+            setSyncConflicts(await api.getSyncConflicts());
+        } catch (error) {
+            console.error("Failed to merge sync conflicts", error);
+        }
+    };
+
+    // Vault changes are saved by the backend, so the settings are reloaded from there
+    const handleVaultAction = async (action: () => Promise<unknown>) => {
+        try {
+            await action();
+            setVaultError(null);
+            setVaults(await api.listVaults());
+            onSettingsUpdate(await api.getSettings());
+        } catch (error) {
+            setVaultError(String(error));
+        }
+    };
+
+    const handleCreateVault = () =>
+        handleVaultAction(async () => {
+            await api.createVault(newVaultName);
+            setNewVaultName("");
+        });
+
+    const handleRenameVault = (vault: VaultInfo) => {
+        const name = window.prompt("Vault name", vault.name);
+        if (name && name !== vault.name) {
+            handleVaultAction(() => api.renameVault(vault.id, name));
+        }
+    };
+
+    const handleRemoveVault = (vault: VaultInfo) => {
+        if (window.confirm(`Remove "${vault.name}" from the list? Its files are kept on disk.`)) {
+            handleVaultAction(() => api.removeVault(vault.id));
+        }
+    };
+
+    const handleAddSharedVault = async () => {
+        try {
+            await api.addSharedVault(sharedDraft.name, sharedDraft.memberName, sharedDraft.folder);
+            setSharedDraft({ name: "", memberName: "", folder: "" });
+            setSharingError(null);
+            setVaults(await api.listVaults());
+            onSettingsUpdate(await api.getSettings());
+        } catch (error) {
+            setSharingError(String(error));
+        }
+    };
+
+    const handleRecipientAction = async (action: () => Promise<void>) => {
+        try {
+            await action();
+            setSharingError(null);
+            setRecipients(await api.listRecipients());
+        } catch (error) {
+            setSharingError(String(error));
+        }
+    };
+
+    const handleAddRecipient = () =>
+        handleRecipientAction(async () => {
+            await api.addRecipient(memberDraft.name, memberDraft.publicKey);
+            setMemberDraft({ name: "", publicKey: "" });
+        });
+
+    const handleRemoveRecipient = (recipient: Recipient) => {
+        if (window.confirm(`Remove ${recipient.name}? The vault gets a new key that only the remaining members can open.`)) {
+            handleRecipientAction(() => api.removeRecipient(recipient.publicKey));
+        }
+    };
+
+    // Unlock factors are saved by the backend, so the settings are reloaded from there
+    const handleUnlockFactor = async (action: () => Promise<void>) => {
+        try {
+            await action();
+            setUnlockFactorError(null);
+            onSettingsUpdate(await api.getSettings());
+        } catch (error) {
+            setUnlockFactorError(String(error));
+        }
+    };
+
+    const handleKeyfile = (keyfile: string | null) =>
+        handleUnlockFactor(async () => {
+            await api.setKeyfile(keyfilePassword, keyfile);
+            setKeyfilePassword("");
+            setKeyfileDraft(keyfile ?? "");
+        });
+
+    const handleChangePassword = () =>
+        handleUnlockFactor(async () => {
+            await api.changeMasterPassword(currentPassword, newPassword);
+            setCurrentPassword("");
+            setNewPassword("");
+        });
+
+    const handleCreateRecoveryKey = () =>
+        handleUnlockFactor(async () => {
+            setNewRecoveryKey(await api.createRecoveryKey(recoveryPassword));
+            setRecoveryPassword("");
+        });
+
+    const handleBreachListBlur = () => {
+        const breachListPath = breachListDraft.trim() || null;
+        if (breachListPath === (settings.security.breachListPath ?? null)) return;
+        onSettingsUpdate({ ...settings, security: { ...settings.security, breachListPath } });
+    };
+
+    const handleAudit = async () => {
+        setAuditing(true);
+        setAuditError(null);
+        try {
+            setAuditReport(await api.auditVault());
+        } catch (error) {
+            setAuditReport(null);
+            setAuditError(String(error));
+        } finally {
+            setAuditing(false);
+        }
+    };
 
     const handleAutoStartChange = async (enabled: boolean) => {
         try {
@@ -95,6 +310,167 @@ export function Settings({ settings, onResetTrigger, onSetupTrigger, onSettingsU
                 </div>
             </div>
 
+            <Card className="glass border-border/50">
+                <CardHeader>
+                    <div className="flex items-center gap-2">
+                        <Layers className="w-5 h-5 text-primary" />
+                        <CardTitle className="text-lg">Vaults</CardTitle>
+                    </div>
+                    <CardDescription>
+                        Separate vaults, each with its own file, master password and lock timeout. The settings on this page apply to the active vault.
+                    </CardDescription>
+                </CardHeader>
+                <CardContent className="space-y-4">
+                    {vaults.map((vault) => (
+                        <div key={vault.id} className="flex items-center justify-between gap-4 p-3 rounded-xl bg-muted/30 border border-border/50">
+                            <div className="space-y-0.5 min-w-0">
+                                <p className="text-sm font-semibold truncate">{vault.name}</p>
+                                <p className="text-[11px] text-muted-foreground">
+                                    {vault.active ? "Active" : vault.mounted ? "Shown in tray" : "Inactive"}
+                                    {vault.masterPasswordEnabled && (vault.unlocked ? " · Unlocked" : " · Locked")}
+                                </p>
+                            </div>
+                            <div className="flex items-center gap-2 shrink-0">
+                                {!vault.active && (
+                                    <>
+                                        <Label htmlFor={`mount-${vault.id}`} className="text-[11px] text-muted-foreground">Tray</Label>
+                                        <Switch
+                                            id={`mount-${vault.id}`}
+                                            checked={vault.mounted}
+                                            onCheckedChange={(checked) => handleVaultAction(() => api.setVaultMounted(vault.id, checked))}
+                                        />
+                                        <Button variant="outline" size="sm" onClick={() => handleVaultAction(() => api.switchVault(vault.id))} className="h-8 text-xs">
+                                            Switch
+                                        </Button>
+                                    </>
+                                )}
+                                <Button variant="ghost" size="icon" onClick={() => handleRenameVault(vault)} className="h-8 w-8" title="Rename">
+                                    <Pencil className="w-3 h-3" />
+                                </Button>
+                                {!vault.active && (
+                                    <Button variant="ghost" size="icon" onClick={() => handleRemoveVault(vault)} className="h-8 w-8 text-destructive" title="Remove">
+                                        <Trash2 className="w-3 h-3" />
+                                    </Button>
+                                )}
+                            </div>
+                        </div>
+                    ))}
+                    <div className="flex items-center gap-2">
+                        <input
+                            className="flex-1 px-3 py-1.5 text-xs bg-background/50 border border-border/50 rounded-md focus:outline-none focus:ring-1 focus:ring-primary focus:border-primary/50 transition-all placeholder:text-muted-foreground/30"
+                            placeholder="New vault name, e.g. Work"
+                            value={newVaultName}
+                            onChange={(e) => setNewVaultName(e.target.value)}
+                            onKeyDown={(e) => e.key === "Enter" && newVaultName.trim() && handleCreateVault()}
+                        />
+                        <Button variant="outline" size="sm" onClick={handleCreateVault} disabled={!newVaultName.trim()} className="h-8 text-xs gap-2">
+                            <Plus className="w-3 h-3" />
+                            Add Vault
+                        </Button>
+                    </div>
+                    {vaultError && (
+                        <p className="text-xs text-destructive">{vaultError}</p>
+                    )}
+                </CardContent>
+            </Card>
+
+            <Card className="glass border-border/50">
+                <CardHeader>
+                    <div className="flex items-center gap-2">
+                        <Users className="w-5 h-5 text-primary" />
+                        <CardTitle className="text-lg">Shared Vaults</CardTitle>
+                    </div>
+                    <CardDescription>
+                        A vault file in a synced folder that each member opens with their own key, without a shared password.
+                    </CardDescription>
+                </CardHeader>
+                <CardContent className="space-y-4">
+                    <div className="flex flex-col gap-2 p-4 rounded-xl bg-muted/30 border border-border/50">
+                        <span className="text-sm font-semibold">Your Public Key</span>
+                        <div className="p-2.5 rounded bg-background/50 border border-border/40 font-mono text-[11px] break-all text-muted-foreground select-all">
+                            {identity || "Loading key..."}
+                        </div>
+                        <p className="text-[10px] text-muted-foreground/60 italic">
+                            Send this to a member of a shared vault so they can add you.
+                        </p>
+                    </div>
+
+                    {settings.security.shared && (
+                        <div className="flex flex-col gap-3 p-4 rounded-xl bg-muted/30 border border-border/50">
+                            <span className="text-sm font-semibold">Members of {settings.vaultName}</span>
+                            {recipients.map((recipient) => (
+                                <div key={recipient.publicKey} className="flex items-center justify-between gap-4">
+                                    <div className="min-w-0">
+                                        <p className="text-sm truncate">
+                                            {recipient.name}
+                                            {recipient.publicKey === identity && <span className="text-muted-foreground"> (you)</span>}
+                                        </p>
+                                        <p className="font-mono text-[10px] text-muted-foreground truncate">{recipient.publicKey}</p>
+                                    </div>
+                                    <Button variant="ghost" size="icon" onClick={() => handleRemoveRecipient(recipient)} className="h-8 w-8 text-destructive shrink-0" title="Remove">
+                                        <Trash2 className="w-3 h-3" />
+                                    </Button>
+                                </div>
+                            ))}
+                            <div className="flex items-center gap-2">
+                                <input
+                                    className="w-32 px-3 py-1.5 text-xs bg-background/50 border border-border/50 rounded-md focus:outline-none focus:ring-1 focus:ring-primary focus:border-primary/50 transition-all placeholder:text-muted-foreground/30"
+                                    placeholder="Name"
+                                    value={memberDraft.name}
+                                    onChange={(e) => setMemberDraft({ ...memberDraft, name: e.target.value })}
+                                />
+                                <input
+                                    className="flex-1 px-3 py-1.5 text-xs font-mono bg-background/50 border border-border/50 rounded-md focus:outline-none focus:ring-1 focus:ring-primary focus:border-primary/50 transition-all placeholder:text-muted-foreground/30"
+                                    placeholder="sklad-pub-..."
+                                    value={memberDraft.publicKey}
+                                    onChange={(e) => setMemberDraft({ ...memberDraft, publicKey: e.target.value })}
+                                />
+                                <Button variant="outline" size="sm" onClick={handleAddRecipient} disabled={!memberDraft.name.trim() || !memberDraft.publicKey.trim()} className="h-8 text-xs gap-2">
+                                    <Plus className="w-3 h-3" />
+                                    Add Member
+                                </Button>
+                            </div>
+                        </div>
+                    )}
+
+                    <div className="flex flex-col gap-2 p-4 rounded-xl bg-muted/30 border border-border/50">
+                        <span className="text-sm font-semibold">Add Shared Vault</span>
+                        <p className="text-[11px] text-muted-foreground">
+                            Pick a folder with a shared vault to join it, or an empty folder to start one.
+                        </p>
+                        <div className="flex items-center gap-2">
+                            <input
+                                className="flex-1 px-3 py-1.5 text-xs bg-background/50 border border-border/50 rounded-md focus:outline-none focus:ring-1 focus:ring-primary focus:border-primary/50 transition-all placeholder:text-muted-foreground/30"
+                                placeholder="Vault name, e.g. Team"
+                                value={sharedDraft.name}
+                                onChange={(e) => setSharedDraft({ ...sharedDraft, name: e.target.value })}
+                            />
+                            <input
+                                className="flex-1 px-3 py-1.5 text-xs bg-background/50 border border-border/50 rounded-md focus:outline-none focus:ring-1 focus:ring-primary focus:border-primary/50 transition-all placeholder:text-muted-foreground/30"
+                                placeholder="Your name as a member"
+                                value={sharedDraft.memberName}
+                                onChange={(e) => setSharedDraft({ ...sharedDraft, memberName: e.target.value })}
+                            />
+                        </div>
+                        <div className="flex items-center gap-2">
+                            <input
+                                className="flex-1 px-3 py-1.5 text-xs font-mono bg-background/50 border border-border/50 rounded-md focus:outline-none focus:ring-1 focus:ring-primary focus:border-primary/50 transition-all placeholder:text-muted-foreground/30"
+                                placeholder="/path/to/synced/folder"
+                                value={sharedDraft.folder}
+                                onChange={(e) => setSharedDraft({ ...sharedDraft, folder: e.target.value })}
+                            />
+                            <Button variant="outline" size="sm" onClick={handleAddSharedVault} disabled={!sharedDraft.name.trim() || !sharedDraft.folder.trim()} className="h-8 text-xs gap-2">
+                                <Plus className="w-3 h-3" />
+                                Add
+                            </Button>
+                        </div>
+                    </div>
+                    {sharingError && (
+                        <p className="text-xs text-destructive">{sharingError}</p>
+                    )}
+                </CardContent>
+            </Card>
+
             <Card className="glass border-border/50">
                 <CardHeader>
                     <div className="flex items-center gap-2">
@@ -129,6 +505,122 @@ export function Settings({ settings, onResetTrigger, onSetupTrigger, onSettingsU
                             All your snippets, folders, and settings are stored in this JSON file.
                         </p>
                     </div>
+
+                    <div className="flex flex-col gap-3 p-4 rounded-xl bg-muted/30 border border-border/50">
+                        <div className="flex items-center gap-2">
+                            <FolderSync className="w-4 h-4 text-primary/70" />
+                            <Label htmlFor="vault-path" className="text-sm font-semibold">Vault Folder</Label>
+                        </div>
+                        <div className="flex items-center gap-2">
+                            <input
+                                id="vault-path"
+                                className="flex-1 px-3 py-1.5 text-xs bg-background/50 border border-border/50 rounded-md font-mono focus:outline-none focus:ring-1 focus:ring-primary focus:border-primary/50 transition-all placeholder:text-muted-foreground/30"
+                                placeholder="Default (app data folder)"
+                                value={vaultPathDraft}
+                                onChange={(e) => setVaultPathDraft(e.target.value)}
+                                onKeyDown={(e) => e.key === "Enter" && handleVaultPathApply()}
+                            />
+                            <Button
+                                variant="outline"
+                                size="sm"
+                                onClick={handleVaultPathApply}
+                                disabled={vaultPathDraft.trim() === (settings.vaultPath ?? "")}
+                                className="h-8 text-xs"
+                            >
+                                Apply
+                            </Button>
+                        </div>
+                        {vaultPathError && (
+                            <p className="text-xs text-destructive">{vaultPathError}</p>
+                        )}
+                        <p className="text-[10px] text-muted-foreground/60 italic">
+                            Point this at a Syncthing or Dropbox folder to share the vault between machines. The current vault is copied there unless the folder already has one.
+                        </p>
+
+                        {syncConflicts.length > 0 && (
+                            <div className="flex items-center justify-between gap-4 p-3 rounded-lg bg-destructive/5 border border-destructive/30">
+                                <div className="space-y-0.5">
+                                    <p className="text-sm font-semibold">Sync conflicts found</p>
+                                    {syncConflicts.map((file) => (
+                                        <p key={file} className="text-[11px] font-mono text-muted-foreground break-all">{file}</p>
+                                    ))}
+                                </div>
+                                <Button variant="outline" size="sm" onClick={handleMerge} className="h-8 text-xs gap-2 shrink-0">
+                                    <GitMerge className="w-3 h-3" />
+                                    Merge
+                                </Button>
+                            </div>
+                        )}
+
+                        {mergeReport && (
+                            <div className="p-3 rounded-lg bg-background/50 border border-border/40 space-y-1">
+                                <p className="text-xs font-semibold">
+                                    Merged {mergeReport.mergedFiles.length} file(s), {mergeReport.conflicts.length} conflicting change(s)
+                                </p>
+                                {mergeReport.conflicts.map((conflict) => (
+                                    <p key={conflict.id} className="text-[11px] text-muted-foreground">
+                                        {conflict.label}: {conflict.kind === "both_changed" ? "changed on both machines" : "changed on one machine, deleted on the other"}, kept the {conflict.kept === "local" ? "local" : "synced"} version
+                                    </p>
+                                ))}
+                            </div>
+                        )}
+                    </div>
+
+                    <div className="flex flex-col gap-3 p-4 rounded-xl bg-muted/30 border border-border/50">
+                        <div className="flex items-center justify-between gap-4">
+                            <div className="space-y-0.5">
+                                <Label htmlFor="git-enabled" className="text-sm font-semibold flex items-center gap-2">
+                                    <GitBranch className="w-4 h-4 text-primary/70" />
+                                    Git History
+                                </Label>
+                                <p className="text-[11px] text-muted-foreground">
+                                    Commit every save to a git repository in the vault folder. Secrets are only ever committed encrypted.
+                                </p>
+                            </div>
+                            <Switch
+                                id="git-enabled"
+                                checked={settings.gitEnabled ?? false}
+                                onCheckedChange={(checked) => handleGitSettings(checked, settings.gitRemote ?? null)}
+                            />
+                        </div>
+                        {settings.gitEnabled && (
+                            <>
+                                <div className="flex items-center gap-2">
+                                    <input
+                                        className="flex-1 px-3 py-1.5 text-xs bg-background/50 border border-border/50 rounded-md font-mono focus:outline-none focus:ring-1 focus:ring-primary focus:border-primary/50 transition-all placeholder:text-muted-foreground/30"
+                                        placeholder="Remote URL, e.g. git@example.com:team/vault.git"
+                                        value={gitRemoteDraft}
+                                        onChange={(e) => setGitRemoteDraft(e.target.value)}
+                                        onKeyDown={(e) => e.key === "Enter" && handleGitSettings(true, gitRemoteDraft.trim() || null)}
+                                    />
+                                    <Button
+                                        variant="outline"
+                                        size="sm"
+                                        onClick={() => handleGitSettings(true, gitRemoteDraft.trim() || null)}
+                                        disabled={gitRemoteDraft.trim() === (settings.gitRemote ?? "")}
+                                        className="h-8 text-xs"
+                                    >
+                                        Apply
+                                    </Button>
+                                </div>
+                                {settings.gitRemote && (
+                                    <div className="flex items-center gap-2">
+                                        <Button variant="outline" size="sm" disabled={gitBusy} onClick={() => handleGit("pull")} className="h-8 text-xs gap-2">
+                                            <Download className="w-3 h-3" />
+                                            Pull
+                                        </Button>
+                                        <Button variant="outline" size="sm" disabled={gitBusy} onClick={() => handleGit("push")} className="h-8 text-xs gap-2">
+                                            <Upload className="w-3 h-3" />
+                                            Push
+                                        </Button>
+                                    </div>
+                                )}
+                            </>
+                        )}
+                        {gitStatus && (
+                            <p className="text-xs text-muted-foreground break-all">{gitStatus}</p>
+                        )}
+                    </div>
                 </CardContent>
             </Card>
 
@@ -253,6 +745,94 @@ export function Settings({ settings, onResetTrigger, onSetupTrigger, onSettingsU
                             </SelectContent>
                         </Select>
                     </div>
+                    <div className="flex items-center justify-between gap-4 p-4 rounded-xl bg-muted/30 border border-border/50">
+                        <div className="space-y-0.5">
+                            <Label htmlFor="root-sort-mode" className="text-base font-semibold">
+                                Top-level Sort Order
+                            </Label>
+                            <p className="text-sm text-muted-foreground">
+                                How top-level items are ordered in the sidebar and the tray menu.
+                            </p>
+                        </div>
+                        <Select
+                            value={settings.rootSortMode || 'manual'}
+                            onValueChange={(value: SortMode) => {
+                                onSettingsUpdate({
+                                    ...settings,
+                                    rootSortMode: value
+                                });
+                            }}
+                        >
+                            <SelectTrigger className="w-48 bg-background/50 border-border/50 h-9">
+                                <SelectValue placeholder="Select order..." />
+                            </SelectTrigger>
+                            <SelectContent>
+                                <SelectItem value="manual">Manual</SelectItem>
+                                <SelectItem value="alphabetical">Alphabetical</SelectItem>
+                                <SelectItem value="created">Date Created</SelectItem>
+                                <SelectItem value="last_used">Last Used</SelectItem>
+                                <SelectItem value="use_count">Most Used</SelectItem>
+                            </SelectContent>
+                        </Select>
+                    </div>
+                    <div className="flex items-center justify-between gap-4 p-4 rounded-xl bg-muted/30 border border-border/50">
+                        <div className="space-y-0.5">
+                            <Label htmlFor="tray-max-items" className="text-base font-semibold">
+                                Tray Items per Folder
+                            </Label>
+                            <p className="text-sm text-muted-foreground">
+                                Extra items are reachable through a "More…" entry that opens search for that folder.
+                            </p>
+                        </div>
+                        <Select
+                            value={String(settings.trayMaxItemsPerFolder ?? 100)}
+                            onValueChange={(value) => {
+                                onSettingsUpdate({
+                                    ...settings,
+                                    trayMaxItemsPerFolder: Number(value)
+                                });
+                            }}
+                        >
+                            <SelectTrigger className="w-48 bg-background/50 border-border/50 h-9">
+                                <SelectValue placeholder="Select limit..." />
+                            </SelectTrigger>
+                            <SelectContent>
+                                <SelectItem value="25">25</SelectItem>
+                                <SelectItem value="50">50</SelectItem>
+                                <SelectItem value="100">100</SelectItem>
+                                <SelectItem value="0">Unlimited</SelectItem>
+                            </SelectContent>
+                        </Select>
+                    </div>
+                    <div className="flex items-center justify-between gap-4 p-4 rounded-xl bg-muted/30 border border-border/50">
+                        <div className="space-y-0.5">
+                            <Label htmlFor="tray-max-depth" className="text-base font-semibold">
+                                Tray Folder Depth
+                            </Label>
+                            <p className="text-sm text-muted-foreground">
+                                How many levels of nested folders are expanded in the tray menu.
+                            </p>
+                        </div>
+                        <Select
+                            value={String(settings.trayMaxDepth ?? 0)}
+                            onValueChange={(value) => {
+                                onSettingsUpdate({
+                                    ...settings,
+                                    trayMaxDepth: Number(value)
+                                });
+                            }}
+                        >
+                            <SelectTrigger className="w-48 bg-background/50 border-border/50 h-9">
+                                <SelectValue placeholder="Select depth..." />
+                            </SelectTrigger>
+                            <SelectContent>
+                                <SelectItem value="1">1</SelectItem>
+                                <SelectItem value="2">2</SelectItem>
+                                <SelectItem value="3">3</SelectItem>
+                                <SelectItem value="0">Unlimited</SelectItem>
+                            </SelectContent>
+                        </Select>
+                    </div>
                     <div className="flex items-center justify-between gap-4 p-4 rounded-xl bg-muted/30 border border-border/50">
                         <div className="space-y-0.5">
                             <Label htmlFor="global-shortcut" className="text-base font-semibold">
@@ -441,6 +1021,66 @@ export function Settings({ settings, onResetTrigger, onSetupTrigger, onSettingsU
                             </div>
                         </div>
                     )}
+                    <div className="flex items-center justify-between gap-4 p-4 rounded-xl bg-muted/30 border border-border/50">
+                        <div className="space-y-0.5">
+                            <Label htmlFor="clear-clipboard" className="text-base font-semibold">
+                                Clear Copied Secrets
+                            </Label>
+                            <p className="text-sm text-muted-foreground">
+                                Wipe a copied secret from the clipboard after 30 seconds.
+                            </p>
+                        </div>
+                        <Switch
+                            id="clear-clipboard"
+                            checked={settings.security.clearClipboard}
+                            onCheckedChange={(checked) => {
+                                onSettingsUpdate({
+                                    ...settings,
+                                    security: {
+                                        ...settings.security,
+                                        clearClipboard: checked
+                                    }
+                                });
+                            }}
+                        />
+                    </div>
+                    <div className="flex flex-col gap-3 p-4 rounded-xl bg-muted/30 border border-border/50">
+                        <div className="flex items-center justify-between gap-4">
+                            <div className="space-y-0.5">
+                                <Label htmlFor="ipc-enabled" className="text-base font-semibold flex items-center gap-2">
+                                    <Terminal className="w-4 h-4" />
+                                    Script Access
+                                </Label>
+                                <p className="text-sm text-muted-foreground">
+                                    Let local programs query the unlocked vault through a socket. Each program is asked for approval first.
+                                </p>
+                            </div>
+                            <Switch
+                                id="ipc-enabled"
+                                checked={settings.ipcEnabled ?? false}
+                                onCheckedChange={(checked) => {
+                                    onSettingsUpdate({ ...settings, ipcEnabled: checked });
+                                }}
+                            />
+                        </div>
+                        {settings.ipcEnabled && ipcEndpoint && (
+                            <code className="text-xs text-muted-foreground break-all">{ipcEndpoint}</code>
+                        )}
+                        {(settings.ipcTrustedClients?.length ?? 0) > 0 && (
+                            <div className="flex items-center justify-between gap-4">
+                                <span className="text-sm text-muted-foreground">
+                                    {settings.ipcTrustedClients!.length} program(s) always allowed
+                                </span>
+                                <Button
+                                    variant="outline"
+                                    size="sm"
+                                    onClick={() => onSettingsUpdate({ ...settings, ipcTrustedClients: [] })}
+                                >
+                                    Forget All
+                                </Button>
+                            </div>
+                        )}
+                    </div>
                     <div className="flex flex-col gap-4 p-4 rounded-xl bg-muted/30 border border-border/50">
                         <div className="space-y-1">
                             <div className="flex items-center gap-2">
@@ -494,6 +1134,252 @@ export function Settings({ settings, onResetTrigger, onSetupTrigger, onSettingsU
                                 : "A master password is required to encrypt sensitive data."}
                         </p>
                     </div>
+
+                    {settings.security.masterPasswordEnabled && !settings.security.shared && (
+                        <div className="flex flex-col gap-4 p-4 rounded-xl bg-muted/30 border border-border/50">
+                            <div className="flex items-center justify-between gap-4">
+                                <div className="space-y-0.5">
+                                    <Label htmlFor="remember-key" className="text-base font-semibold">
+                                        Remember on This Device
+                                    </Label>
+                                    <p className="text-sm text-muted-foreground">
+                                        Keep the vault key in the system keyring, so unlocking takes a single click.
+                                    </p>
+                                </div>
+                                <Switch
+                                    id="remember-key"
+                                    checked={!!settings.security.rememberedKey}
+                                    onCheckedChange={(checked) => handleUnlockFactor(() => api.setRememberKey(checked))}
+                                />
+                            </div>
+                            <div className="flex flex-col gap-2">
+                                <Label className="text-base font-semibold">Change Master Password</Label>
+                                <p className="text-sm text-muted-foreground">
+                                    Only the wrapping of the vault key changes; the secrets aren't encrypted anew.
+                                </p>
+                                <div className="flex items-center gap-2">
+                                    <input
+                                        type="password"
+                                        className="flex-1 px-3 py-1.5 text-xs bg-background/50 border border-border/50 rounded-md focus:outline-none focus:ring-1 focus:ring-primary focus:border-primary/50 transition-all placeholder:text-muted-foreground/30"
+                                        placeholder="Current password"
+                                        value={currentPassword}
+                                        onChange={(e) => setCurrentPassword(e.target.value)}
+                                    />
+                                    <input
+                                        type="password"
+                                        className="flex-1 px-3 py-1.5 text-xs bg-background/50 border border-border/50 rounded-md focus:outline-none focus:ring-1 focus:ring-primary focus:border-primary/50 transition-all placeholder:text-muted-foreground/30"
+                                        placeholder="New password"
+                                        value={newPassword}
+                                        onChange={(e) => setNewPassword(e.target.value)}
+                                    />
+                                    <Button
+                                        variant="outline"
+                                        size="sm"
+                                        onClick={handleChangePassword}
+                                        disabled={!currentPassword || !newPassword}
+                                        className="h-8 text-xs"
+                                    >
+                                        Change
+                                    </Button>
+                                </div>
+                                <StrengthMeter password={newPassword} minScore={settings.security.minPasswordScore ?? 2} />
+                            </div>
+                            <div className="flex flex-col gap-2">
+                                <Label className="text-base font-semibold">Recovery Key</Label>
+                                <p className="text-sm text-muted-foreground">
+                                    {settings.security.recoveryWrappedKey
+                                        ? "A recovery key can set a new master password. Making a new one replaces it."
+                                        : "Make a key that can set a new master password if this one is forgotten."}
+                                </p>
+                                {newRecoveryKey ? (
+                                    <RecoveryKeyPanel recoveryKey={newRecoveryKey} onDone={() => setNewRecoveryKey(null)} />
+                                ) : (
+                                    <div className="flex items-center gap-2">
+                                        <input
+                                            type="password"
+                                            className="flex-1 px-3 py-1.5 text-xs bg-background/50 border border-border/50 rounded-md focus:outline-none focus:ring-1 focus:ring-primary focus:border-primary/50 transition-all placeholder:text-muted-foreground/30"
+                                            placeholder="Master password to confirm"
+                                            value={recoveryPassword}
+                                            onChange={(e) => setRecoveryPassword(e.target.value)}
+                                        />
+                                        <Button
+                                            variant="outline"
+                                            size="sm"
+                                            onClick={handleCreateRecoveryKey}
+                                            disabled={!recoveryPassword}
+                                            className="h-8 text-xs"
+                                        >
+                                            {settings.security.recoveryWrappedKey ? "Replace Recovery Key" : "Create Recovery Key"}
+                                        </Button>
+                                    </div>
+                                )}
+                            </div>
+                            <div className="flex flex-col gap-2">
+                                <Label className="text-base font-semibold">Keyfile</Label>
+                                <p className="text-sm text-muted-foreground">
+                                    {settings.security.keyfilePath
+                                        ? "Unlocking needs this file along with the master password."
+                                        : "Require a file, e.g. on a USB stick, along with the master password. Any file works; keep a backup."}
+                                </p>
+                                <input
+                                    className="w-full px-3 py-1.5 text-xs font-mono bg-background/50 border border-border/50 rounded-md focus:outline-none focus:ring-1 focus:ring-primary focus:border-primary/50 transition-all placeholder:text-muted-foreground/30"
+                                    placeholder="/path/to/keyfile"
+                                    value={keyfileDraft}
+                                    onChange={(e) => setKeyfileDraft(e.target.value)}
+                                />
+                                <div className="flex items-center gap-2">
+                                    <input
+                                        type="password"
+                                        className="flex-1 px-3 py-1.5 text-xs bg-background/50 border border-border/50 rounded-md focus:outline-none focus:ring-1 focus:ring-primary focus:border-primary/50 transition-all placeholder:text-muted-foreground/30"
+                                        placeholder="Master password to confirm"
+                                        value={keyfilePassword}
+                                        onChange={(e) => setKeyfilePassword(e.target.value)}
+                                    />
+                                    {settings.security.keyfilePath && (
+                                        <Button variant="outline" size="sm" onClick={() => handleKeyfile(null)} disabled={!keyfilePassword} className="h-8 text-xs">
+                                            Remove Keyfile
+                                        </Button>
+                                    )}
+                                    <Button
+                                        variant="outline"
+                                        size="sm"
+                                        onClick={() => handleKeyfile(keyfileDraft.trim())}
+                                        disabled={!keyfilePassword || !keyfileDraft.trim() || keyfileDraft.trim() === settings.security.keyfilePath}
+                                        className="h-8 text-xs"
+                                    >
+                                        {settings.security.keyfilePath ? "Change Keyfile" : "Require Keyfile"}
+                                    </Button>
+                                </div>
+                            </div>
+                            <div className="flex items-center justify-between gap-4">
+                                <div className="space-y-0.5">
+                                    <Label className="text-base font-semibold">Failed Unlock Limit</Label>
+                                    <p className="text-sm text-muted-foreground">
+                                        Wrong passwords are slowed down after three tries. After this many in a row:
+                                    </p>
+                                </div>
+                                <div className="flex items-center gap-2 shrink-0">
+                                    <Select
+                                        value={String(settings.security.maxUnlockAttempts ?? 0)}
+                                        onValueChange={(value) => {
+                                            onSettingsUpdate({
+                                                ...settings,
+                                                security: { ...settings.security, maxUnlockAttempts: parseInt(value) }
+                                            });
+                                        }}
+                                    >
+                                        <SelectTrigger className="w-24 bg-background/50 border-border/50 h-9">
+                                            <SelectValue />
+                                        </SelectTrigger>
+                                        <SelectContent>
+                                            <SelectItem value="0">No limit</SelectItem>
+                                            <SelectItem value="5">5</SelectItem>
+                                            <SelectItem value="10">10</SelectItem>
+                                            <SelectItem value="20">20</SelectItem>
+                                        </SelectContent>
+                                    </Select>
+                                    {(settings.security.maxUnlockAttempts ?? 0) > 0 && (
+                                        <Select
+                                            value={settings.security.lockoutAction ?? 'lockout'}
+                                            onValueChange={(value: 'lockout' | 'wipe') => {
+                                                onSettingsUpdate({
+                                                    ...settings,
+                                                    security: { ...settings.security, lockoutAction: value }
+                                                });
+                                            }}
+                                        >
+                                            <SelectTrigger className="w-40 bg-background/50 border-border/50 h-9">
+                                                <SelectValue />
+                                            </SelectTrigger>
+                                            <SelectContent>
+                                                <SelectItem value="lockout">Lock for a day</SelectItem>
+                                                <SelectItem value="wipe">Delete all secrets</SelectItem>
+                                            </SelectContent>
+                                        </Select>
+                                    )}
+                                </div>
+                            </div>
+                            <div className="flex items-center justify-between gap-4">
+                                <div className="space-y-0.5">
+                                    <Label className="text-base font-semibold">Minimum Password Strength</Label>
+                                    <p className="text-sm text-muted-foreground">
+                                        New master passwords weaker than this are refused.
+                                    </p>
+                                </div>
+                                <Select
+                                    value={String(settings.security.minPasswordScore ?? 2)}
+                                    onValueChange={(value) => {
+                                        onSettingsUpdate({
+                                            ...settings,
+                                            security: { ...settings.security, minPasswordScore: parseInt(value) }
+                                        });
+                                    }}
+                                >
+                                    <SelectTrigger className="w-36 bg-background/50 border-border/50 h-9 shrink-0">
+                                        <SelectValue />
+                                    </SelectTrigger>
+                                    <SelectContent>
+                                        <SelectItem value="0">Any</SelectItem>
+                                        <SelectItem value="1">Weak</SelectItem>
+                                        <SelectItem value="2">Fair</SelectItem>
+                                        <SelectItem value="3">Strong</SelectItem>
+                                        <SelectItem value="4">Very strong</SelectItem>
+                                    </SelectContent>
+                                </Select>
+                            </div>
+                            <div className="flex flex-col gap-2">
+                                <Label className="text-base font-semibold">Password Audit</Label>
+                                <p className="text-sm text-muted-foreground">
+                                    Look for weak and reused secrets. With a downloaded SHA-1 breach list, a file of
+                                    "HASH:COUNT" lines or a folder of range files, also for breached ones. Nothing leaves this device.
+                                </p>
+                                <div className="flex items-center gap-2">
+                                    <input
+                                        className="flex-1 px-3 py-1.5 text-xs font-mono bg-background/50 border border-border/50 rounded-md focus:outline-none focus:ring-1 focus:ring-primary focus:border-primary/50 transition-all placeholder:text-muted-foreground/30"
+                                        placeholder="/path/to/pwned-passwords (optional)"
+                                        value={breachListDraft}
+                                        onChange={(e) => setBreachListDraft(e.target.value)}
+                                        onBlur={handleBreachListBlur}
+                                    />
+                                    <Button variant="outline" size="sm" onClick={handleAudit} disabled={auditing} className="h-8 text-xs">
+                                        {auditing ? "Auditing..." : "Audit Vault"}
+                                    </Button>
+                                </div>
+                                {auditError && <p className="text-xs text-destructive">{auditError}</p>}
+                                {auditReport && (
+                                    <div className="rounded-lg bg-muted/40 border border-border/50 p-3 space-y-2 text-xs">
+                                        <p className="text-muted-foreground">
+                                            {auditReport.checked} secret(s) checked: {auditReport.weak.length} weak,{" "}
+                                            {auditReport.reused.length} reused value(s)
+                                            {auditReport.breachListChecked && `, ${auditReport.breached.length} breached`}
+                                        </p>
+                                        {auditReport.weak.map((entry) => (
+                                            <p key={`weak-${entry.id}`}>
+                                                <span className="font-mono">{entry.path}</span>
+                                                <span className="text-amber-500"> weak</span>
+                                                {entry.warning && <span className="text-muted-foreground"> - {entry.warning}</span>}
+                                            </p>
+                                        ))}
+                                        {auditReport.reused.map((group) => (
+                                            <p key={`reused-${group[0].id}`}>
+                                                <span className="text-amber-500">Same value: </span>
+                                                <span className="font-mono">{group.map((entry) => entry.path).join(", ")}</span>
+                                            </p>
+                                        ))}
+                                        {auditReport.breached.map((entry) => (
+                                            <p key={`breached-${entry.id}`}>
+                                                <span className="font-mono">{entry.path}</span>
+                                                <span className="text-destructive"> seen in breaches {entry.count.toLocaleString()} time(s)</span>
+                                            </p>
+                                        ))}
+                                    </div>
+                                )}
+                            </div>
+                            {unlockFactorError && (
+                                <p className="text-xs text-destructive">{unlockFactorError}</p>
+                            )}
+                        </div>
+                    )}
                 </CardContent>
             </Card>
         </div>
@@ -1,5 +1,5 @@
 import { Node } from "@/types";
-import { Folder, FileText, ChevronRight, Lock, Plus, Trash2, FolderPlus, FilePlus, Container, Pencil, Settings as SettingsIcon } from "lucide-react";
+import { Folder, FileText, ChevronRight, Lock, Plus, Trash2, FolderPlus, FilePlus, Container, Pencil, Paperclip, Timer, Settings as SettingsIcon } from "lucide-react";
 import { useState, useRef, useEffect } from "react";
 import { cn } from "@/lib/utils";
 import { Button } from "@/components/ui/button";
@@ -15,7 +15,7 @@ interface SidebarProps {
     nodes: Node[];
     onSelectNode: (node: Node) => void;
     selectedNodeId?: string;
-    onAddNode: (parentId: string | null, type: "folder" | "snippet") => void;
+    onAddNode: (parentId: string | null, type: "folder" | "snippet" | "attachment" | "totp") => void;
     onDeleteNode: (nodeId: string) => void;
     onRenameNode: (nodeId: string, newLabel: string) => void;
     onMoveNode?: (draggedId: string, targetId: string | null, beforeId?: string | null) => void;
@@ -26,7 +26,7 @@ interface TreeNodeProps {
     level: number;
     onSelect: (n: Node) => void;
     selectedId?: string;
-    onAddNode: (parentId: string | null, type: "folder" | "snippet") => void;
+    onAddNode: (parentId: string | null, type: "folder" | "snippet" | "attachment" | "totp") => void;
     onDeleteNode: (nodeId: string) => void;
     onRenameNode: (nodeId: string, newLabel: string) => void;
     onMoveNode?: (draggedId: string, targetId: string | null, beforeId?: string | null) => void;
@@ -225,6 +225,16 @@ const TreeNode = ({ node, level, onSelect, selectedId, onAddNode, onDeleteNode,
                         "w-4 h-4 mr-2 transition-colors shrink-0",
                         isSelected || isDragOver ? "text-primary" : "text-primary/60"
                     )} />
+                ) : node.type === "attachment" ? (
+                    <Paperclip className={cn(
+                        "w-4 h-4 mr-2 transition-colors shrink-0",
+                        isSelected ? "text-foreground" : "text-muted-foreground"
+                    )} />
+                ) : node.totp ? (
+                    <Timer className={cn(
+                        "w-4 h-4 mr-2 transition-colors shrink-0",
+                        isSelected ? "text-foreground" : "text-muted-foreground"
+                    )} />
                 ) : (
                     <FileText className={cn(
                         "w-4 h-4 mr-2 transition-colors shrink-0",
@@ -288,6 +298,24 @@ const TreeNode = ({ node, level, onSelect, selectedId, onAddNode, onDeleteNode,
                                 >
                                     <FilePlus className="w-3 h-3" />
                                 </Button>
+                                <Button
+                                    variant="ghost"
+                                    size="icon"
+                                    className="h-5 w-5 hover:bg-primary/20 hover:text-primary"
+                                    onClick={() => onAddNode(node.id, "attachment")}
+                                    title="Add Attachment"
+                                >
+                                    <Paperclip className="w-3 h-3" />
+                                </Button>
+                                <Button
+                                    variant="ghost"
+                                    size="icon"
+                                    className="h-5 w-5 hover:bg-primary/20 hover:text-primary"
+                                    onClick={() => onAddNode(node.id, "totp")}
+                                    title="Add One-Time Code"
+                                >
+                                    <Timer className="w-3 h-3" />
+                                </Button>
                             </>
                         )}
                         <Button
@@ -409,6 +437,20 @@ export function Sidebar({ nodes, onSelectNode, selectedNodeId, onAddNode, onDele
                                 <FilePlus className="w-4 h-4 mr-2 text-muted-foreground" />
                                 <span>New Snippet</span>
                             </DropdownMenuItem>
+                            <DropdownMenuItem
+                                onClick={() => onAddNode(null, "attachment")}
+                                className="cursor-pointer hover:bg-primary/10 focus:bg-primary/10"
+                            >
+                                <Paperclip className="w-4 h-4 mr-2 text-muted-foreground" />
+                                <span>New Attachment</span>
+                            </DropdownMenuItem>
+                            <DropdownMenuItem
+                                onClick={() => onAddNode(null, "totp")}
+                                className="cursor-pointer hover:bg-primary/10 focus:bg-primary/10"
+                            >
+                                <Timer className="w-4 h-4 mr-2 text-muted-foreground" />
+                                <span>New One-Time Code</span>
+                            </DropdownMenuItem>
                         </DropdownMenuContent>
                     </DropdownMenu>
                 </div>
@@ -1,13 +1,14 @@
 import { useState, useEffect, useRef, useCallback, forwardRef, useImperativeHandle } from "react";
-import { Node } from "@/types";
+import { Node, PastValue, PasswordPolicy, TotpCode } from "@/types";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
 import { Textarea } from "@/components/ui/textarea";
 import { Switch } from "@/components/ui/switch";
 import { Label } from "@/components/ui/label";
-import { Save, Copy, Lock, Unlock, FileText, Check } from "lucide-react";
+import { Save, Copy, Lock, Unlock, FileText, Check, Timer, Wand2, History, CalendarClock } from "lucide-react";
 import { api } from "@/lib/api";
 import { cn } from "@/lib/utils";
+import { PasswordGenerator } from "@/components/PasswordGenerator";
 
 export interface SnippetEditorRef {
     isDirty: () => boolean;
@@ -24,6 +25,13 @@ interface SnippetEditorProps {
     autoSave?: boolean;
 }
 
+/** "YYYY-MM-DD" of a timestamp in local time, for a date input. */
+function toDateInput(timestamp: number): string {
+    const date = new Date(timestamp);
+    const pad = (n: number) => String(n).padStart(2, "0");
+    return `${date.getFullYear()}-${pad(date.getMonth() + 1)}-${pad(date.getDate())}`;
+}
+
 export const SnippetEditor = forwardRef<SnippetEditorRef, SnippetEditorProps>(({
     node,
     onSave,
@@ -35,23 +43,27 @@ export const SnippetEditor = forwardRef<SnippetEditorRef, SnippetEditorProps>(({
     const [label, setLabel] = useState(node.label);
     const [value, setValue] = useState(node.value || "");
     const [isSecret, setIsSecret] = useState(node.isSecret || false);
+    const [expiresAt, setExpiresAt] = useState(node.expiresAt);
     const [isSaving, setIsSaving] = useState(false);
     const [copied, setCopied] = useState(false);
+    const [showGenerator, setShowGenerator] = useState(false);
 
     // Track last saved state to calculate isDirty
-    const savedState = useRef({ label: node.label, value: node.value || "", isSecret: node.isSecret || false });
+    const savedState = useRef({ label: node.label, value: node.value || "", isSecret: node.isSecret || false, expiresAt: node.expiresAt });
 
     useEffect(() => {
         setLabel(node.label);
         setValue(node.value || "");
         setIsSecret(node.isSecret || false);
-        savedState.current = { label: node.label, value: node.value || "", isSecret: node.isSecret || false };
-    }, [node.id, node.label, node.value, node.isSecret]);
+        setExpiresAt(node.expiresAt);
+        savedState.current = { label: node.label, value: node.value || "", isSecret: node.isSecret || false, expiresAt: node.expiresAt };
+    }, [node.id, node.label, node.value, node.isSecret, node.expiresAt]);
 
     const isDirty =
         label !== savedState.current.label ||
         value !== savedState.current.value ||
-        isSecret !== savedState.current.isSecret;
+        isSecret !== savedState.current.isSecret ||
+        expiresAt !== savedState.current.expiresAt;
 
     const handleSave = useCallback(async () => {
         if (isSecret && (!masterPasswordEnabled || !isUnlocked)) return;
@@ -61,32 +73,35 @@ export const SnippetEditor = forwardRef<SnippetEditorRef, SnippetEditorProps>(({
             ...node,
             label,
             isSecret,
-            value: value
+            value: value,
+            expiresAt: isSecret ? expiresAt : undefined
         };
 
         await onSave(updatedNode);
-        savedState.current = { label, value, isSecret };
+        savedState.current = { label, value, isSecret, expiresAt };
         setTimeout(() => setIsSaving(false), 500);
-    }, [node, label, value, isSecret, masterPasswordEnabled, isUnlocked, onSave]);
+    }, [node, label, value, isSecret, expiresAt, masterPasswordEnabled, isUnlocked, onSave]);
 
     // Handle autosave
     useEffect(() => {
         if (autoSave && isDirty && !isSaving) {
             handleSave();
         }
-    }, [label, value, isSecret, autoSave, isDirty, isSaving, handleSave]);
+    }, [label, value, isSecret, expiresAt, autoSave, isDirty, isSaving, handleSave]);
 
 
     useImperativeHandle(ref, () => ({
         isDirty: () => {
             return label !== savedState.current.label ||
                 value !== savedState.current.value ||
-                isSecret !== savedState.current.isSecret;
+                isSecret !== savedState.current.isSecret ||
+                expiresAt !== savedState.current.expiresAt;
         },
         save: async () => {
             if (label !== savedState.current.label ||
                 value !== savedState.current.value ||
-                isSecret !== savedState.current.isSecret) {
+                isSecret !== savedState.current.isSecret ||
+                expiresAt !== savedState.current.expiresAt) {
                 await handleSave();
             }
         },
@@ -94,8 +109,9 @@ export const SnippetEditor = forwardRef<SnippetEditorRef, SnippetEditorProps>(({
             setLabel(savedState.current.label);
             setValue(savedState.current.value);
             setIsSecret(savedState.current.isSecret);
+            setExpiresAt(savedState.current.expiresAt);
         }
-    }), [label, value, isSecret, handleSave]);
+    }), [label, value, isSecret, expiresAt, handleSave]);
 
     useEffect(() => {
         const handleKeyDown = (e: KeyboardEvent) => {
@@ -111,6 +127,44 @@ export const SnippetEditor = forwardRef<SnippetEditorRef, SnippetEditorProps>(({
         return () => window.removeEventListener('keydown', handleKeyDown);
     }, [handleSave, isSaving, isDirty, isSecret, masterPasswordEnabled, isUnlocked]);
 
+    // The code of a TOTP snippet changes every period, so it is fetched every second
+    const [totpCode, setTotpCode] = useState<TotpCode | null>(null);
+    useEffect(() => {
+        setTotpCode(null);
+        if (!node.totp || !isUnlocked) return;
+        const refresh = () => api.getTotpCode(node.id).then(setTotpCode).catch(() => setTotpCode(null));
+        refresh();
+        const timer = setInterval(refresh, 1000);
+        return () => clearInterval(timer);
+    }, [node.id, node.totp, node.value, isUnlocked]);
+
+    // A saved secret is regenerated by the backend, which keeps its old value
+    const canRegenerate = !!node.isSecret && !!node.encryptedValue && !node.totp;
+
+    const handleRegenerate = async (policy: PasswordPolicy) => {
+        const regenerated = await api.regenerateSecret(node.id, policy);
+        await onSave({ ...regenerated, label, expiresAt });
+        const newValue = regenerated.value || "";
+        setValue(newValue);
+        savedState.current = { label, value: newValue, isSecret, expiresAt };
+    };
+
+    // Earlier values are only decrypted when asked for
+    const [history, setHistory] = useState<PastValue[] | null>(null);
+    useEffect(() => setHistory(null), [node.id, isUnlocked]);
+
+    const toggleHistory = async () => {
+        if (history) {
+            setHistory(null);
+            return;
+        }
+        try {
+            setHistory((await api.getSecretHistory(node.id)).reverse());
+        } catch (e) {
+            console.error("Failed to load the history", e);
+        }
+    };
+
     const handleCopy = async () => {
         if (isSecret && (!masterPasswordEnabled || !isUnlocked)) return;
 
@@ -181,6 +235,18 @@ export const SnippetEditor = forwardRef<SnippetEditorRef, SnippetEditorProps>(({
                     />
                 </div>
                 <div className="flex items-center gap-2">
+                    {!node.totp && (
+                        <Button
+                            variant="outline"
+                            size="sm"
+                            onClick={() => setShowGenerator(true)}
+                            disabled={isSecret && (!masterPasswordEnabled || !isUnlocked)}
+                            title={canRegenerate ? "Regenerate Secret" : "Generate Password"}
+                        >
+                            <Wand2 className="w-4 h-4 mr-2" />
+                            {canRegenerate ? "Regenerate" : "Generate"}
+                        </Button>
+                    )}
                     {/* ... Copy button ... */}
                     <Button
                         variant="outline"
@@ -234,7 +300,7 @@ export const SnippetEditor = forwardRef<SnippetEditorRef, SnippetEditorProps>(({
                             setIsSecret(checked);
                         }
                     }}
-                    disabled={!masterPasswordEnabled}
+                    disabled={!masterPasswordEnabled || !!node.totp}
                     className="data-[state=checked]:bg-yellow-500"
                 />
                 <div className="flex items-center gap-2">
@@ -259,6 +325,57 @@ export const SnippetEditor = forwardRef<SnippetEditorRef, SnippetEditorProps>(({
                 </div>
             </div>
 
+            {/* Current one-time code */}
+            {totpCode && (
+                <div className="flex items-center gap-3 p-3 rounded-lg bg-muted/30 border border-border/50">
+                    <Timer className="w-4 h-4 text-primary" />
+                    <span className="font-mono text-lg tracking-widest">{totpCode.code}</span>
+                    <span className="text-xs text-muted-foreground ml-auto">{totpCode.remaining}s left</span>
+                </div>
+            )}
+
+            {/* Expiry and earlier values of secrets */}
+            {isSecret && (
+                <div className="flex items-center gap-3 p-3 rounded-lg bg-muted/30 border border-border/50">
+                    <CalendarClock className="w-4 h-4 text-muted-foreground" />
+                    <Label htmlFor="secret-expiry" className="text-sm text-muted-foreground">Rotate by</Label>
+                    <Input
+                        id="secret-expiry"
+                        type="date"
+                        className="w-44 h-8"
+                        value={expiresAt ? toDateInput(expiresAt) : ""}
+                        onChange={(e) => setExpiresAt(e.target.value ? new Date(`${e.target.value}T00:00`).getTime() : undefined)}
+                    />
+                    {expiresAt !== undefined && expiresAt <= Date.now() && (
+                        <span className="text-xs text-destructive">Expired</span>
+                    )}
+                    {!!node.history?.length && isUnlocked && (
+                        <Button variant="ghost" size="sm" className="ml-auto gap-2" onClick={toggleHistory}>
+                            <History className="w-4 h-4" />
+                            {history ? "Hide History" : `History (${node.history.length})`}
+                        </Button>
+                    )}
+                </div>
+            )}
+            {history && (
+                <div className="max-h-40 overflow-y-auto rounded-lg bg-muted/20 border border-border/50 divide-y divide-border/50 text-xs">
+                    {history.map((past) => (
+                        <div key={past.replacedAt} className="flex items-center gap-3 p-2">
+                            <span className="text-muted-foreground shrink-0">{new Date(past.replacedAt).toLocaleString()}</span>
+                            <span className="font-mono break-all">{past.value}</span>
+                        </div>
+                    ))}
+                </div>
+            )}
+
+            <PasswordGenerator
+                open={showGenerator}
+                regenerate={canRegenerate}
+                onClose={() => setShowGenerator(false)}
+                onUse={setValue}
+                onRegenerate={handleRegenerate}
+            />
+
             {/* Editor */}
             <div className="flex-1 flex flex-col min-h-0">
                 <Textarea
@@ -0,0 +1,51 @@
+import { useEffect, useState } from "react";
+import { api } from "../lib/api";
+import { Strength } from "../types";
+import { cn } from "@/lib/utils";
+
+const LABELS = ["Very weak", "Weak", "Fair", "Strong", "Very strong"];
+const COLORS = ["bg-destructive", "bg-destructive", "bg-amber-500", "bg-emerald-500", "bg-emerald-500"];
+
+interface StrengthMeterProps {
+    password: string;
+    /** Scores below this are marked as not accepted */
+    minScore?: number;
+}
+
+/** Bar of how hard a new password is to guess, with a hint to make it stronger. */
+export function StrengthMeter({ password, minScore }: StrengthMeterProps) {
+    const [strength, setStrength] = useState<Strength | null>(null);
+
+    useEffect(() => {
+        if (!password) {
+            setStrength(null);
+            return;
+        }
+        const timer = setTimeout(() => {
+            api.estimateStrength(password).then(setStrength).catch(() => setStrength(null));
+        }, 150);
+        return () => clearTimeout(timer);
+    }, [password]);
+
+    if (!strength) return null;
+    const tooWeak = minScore !== undefined && strength.score < minScore;
+    const hint = strength.warning ?? strength.suggestions[0];
+
+    return (
+        <div className="space-y-1 px-1">
+            <div className="flex gap-1">
+                {[0, 1, 2, 3].map((i) => (
+                    <div
+                        key={i}
+                        className={cn("h-1 flex-1 rounded-full", i < strength.score ? COLORS[strength.score] : "bg-muted")}
+                    />
+                ))}
+            </div>
+            <p className={cn("text-[11px]", tooWeak ? "text-destructive" : "text-muted-foreground")}>
+                {LABELS[strength.score]}
+                {tooWeak && " - too weak for this vault"}
+                {hint && `. ${hint}`}
+            </p>
+        </div>
+    );
+}
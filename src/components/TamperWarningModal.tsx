@@ -0,0 +1,77 @@
+import {
+    Dialog,
+    DialogContent,
+    DialogDescription,
+    DialogFooter,
+    DialogHeader,
+    DialogTitle,
+} from "@/components/ui/dialog";
+import { Button } from "@/components/ui/button";
+import { ShieldAlert } from "lucide-react";
+import { NodeChange, TamperReport } from "@/types";
+
+interface TamperWarningModalProps {
+    report: TamperReport | null;
+    onTrust: () => void;
+    onRestore: () => void;
+}
+
+const KIND_STYLES: Record<NodeChange["kind"], string> = {
+    added: "text-green-500",
+    removed: "text-destructive",
+    changed: "text-yellow-500",
+};
+
+export function TamperWarningModal({ report, onTrust, onRestore }: TamperWarningModalProps) {
+    return (
+        <Dialog open={report !== null}>
+            <DialogContent className="sm:max-w-[560px]">
+                <DialogHeader>
+                    <div className="flex items-center gap-2 text-destructive mb-2">
+                        <ShieldAlert className="h-5 w-5" />
+                        <DialogTitle>The Vault Was Changed Outside Sklad</DialogTitle>
+                    </div>
+                    <DialogDescription>
+                        The vault file doesn't match its signature, so something other than Sklad changed it since it was last verified on this device. Review the changes before trusting them.
+                    </DialogDescription>
+                </DialogHeader>
+                {report && (
+                    <div className="max-h-72 overflow-y-auto rounded-lg bg-muted/40 border border-border/50 divide-y divide-border/50 text-xs">
+                        {report.changes.length === 0 && (
+                            <div className="p-3 text-muted-foreground">
+                                No snippet changed, only the signature is missing or wrong.
+                            </div>
+                        )}
+                        {report.changes.map((change) => (
+                            <div key={`${change.kind}-${change.id}`} className="p-3 space-y-1">
+                                <div className="flex items-center gap-2">
+                                    <span className={`uppercase font-semibold ${KIND_STYLES[change.kind]}`}>
+                                        {change.kind}
+                                    </span>
+                                    <span className="font-mono break-all">{change.path}</span>
+                                </div>
+                                {change.fields.length > 0 && (
+                                    <div className="text-muted-foreground">{change.fields.join(", ")}</div>
+                                )}
+                                {change.before !== null && (
+                                    <pre className="font-mono whitespace-pre-wrap break-all text-destructive/80">- {change.before}</pre>
+                                )}
+                                {change.after !== null && (
+                                    <pre className="font-mono whitespace-pre-wrap break-all text-green-500/80">+ {change.after}</pre>
+                                )}
+                            </div>
+                        ))}
+                    </div>
+                )}
+                <DialogFooter className="flex gap-2 sm:gap-0 mt-4">
+                    <Button variant="outline" onClick={onTrust}>
+                        Trust Changes
+                    </Button>
+                    <Button onClick={onRestore} className="bg-primary hover:bg-primary/90">
+                        Restore Last Verified
+                    </Button>
+                </DialogFooter>
+            </DialogContent>
+        </Dialog>
+    );
+}
@@ -2,26 +2,44 @@ import { useState } from "react";
 import { api } from "../lib/api";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
+import { Switch } from "@/components/ui/switch";
 import { Card, CardContent, CardDescription, CardFooter, CardHeader, CardTitle } from "@/components/ui/card";
 import { Lock, Unlock, Container, AlertCircle, Eye, EyeOff } from "lucide-react";
 import { cn } from "@/lib/utils";
 import { Node, AppSettings } from "@/types";
+import { RecoveryKeyPanel } from "./RecoveryKeyPanel";
+import { StrengthMeter } from "./StrengthMeter";
 
 interface VaultLockProps {
     onUnlock: () => void;
     onReset: (nodes: Node[], settings: AppSettings) => void;
     onCancel?: () => void;
     isInit?: boolean;
+    /** A shared vault opens with this device's identity, without a password. */
+    shared?: boolean;
+    /** Keyfile the vault needs along with the password, if any. */
+    keyfilePath?: string | null;
+    /** Whether the key is remembered in the OS secret store. */
+    remembered?: boolean;
+    /** Whether a recovery key can replace a forgotten password. */
+    hasRecoveryKey?: boolean;
+    /** Lowest strength score a new master password may have. */
+    minPasswordScore?: number;
     mode?: "full" | "modal";
 }
 
-export function VaultLock({ onUnlock, onReset, onCancel, isInit = false, mode = 'full' }: VaultLockProps) {
+export function VaultLock({ onUnlock, onReset, onCancel, isInit = false, shared = false, keyfilePath, remembered = false, hasRecoveryKey = false, minPasswordScore = 2, mode = 'full' }: VaultLockProps) {
     const [password, setPassword] = useState("");
+    const [keyfile, setKeyfile] = useState(keyfilePath ?? "");
     const [error, setError] = useState("");
     const [loading, setLoading] = useState(false);
     const [showOptions, setShowOptions] = useState(false);
     const [confirmReset, setConfirmReset] = useState(false);
     const [showPassword, setShowPassword] = useState(false);
+    const [withRecoveryKey, setWithRecoveryKey] = useState(true);
+    const [recoveryKey, setRecoveryKey] = useState<string | null>(null);
+    const [recoveryInput, setRecoveryInput] = useState("");
+    const [newPassword, setNewPassword] = useState("");
 
     const handleSubmit = async (e: React.FormEvent) => {
         e.preventDefault();
@@ -30,18 +48,54 @@ export function VaultLock({ onUnlock, onReset, onCancel, isInit = false, mode =
 
         try {
             if (isInit) {
-                await api.initVault(password);
+                const newRecoveryKey = await api.initVault(password, undefined, withRecoveryKey);
+                if (newRecoveryKey) {
+                    // Unlocked already, but the key has to be seen before moving on
+                    setRecoveryKey(newRecoveryKey);
+                    return;
+                }
             } else {
-                const success = await api.unlockVault(password);
+                const success = await api.unlockVault(shared ? "" : password, keyfile || undefined);
                 if (!success) {
-                    setError("Invalid password. Please try again.");
+                    setError(shared
+                        ? "This device is not a member yet. Send your public key from Settings to a member."
+                        : "Invalid password. Please try again.");
                     setLoading(false);
                     return;
                 }
             }
             onUnlock();
         } catch (err) {
-            setError("Invalid password. Please try again.");
+            setError(String(err));
+        } finally {
+            setLoading(false);
+        }
+    };
+
+    const handleKeychainUnlock = async () => {
+        setLoading(true);
+        setError("");
+        try {
+            if (await api.unlockWithKeychain()) {
+                onUnlock();
+            } else {
+                setError("The remembered key is gone from the system keyring or out of date. Enter your master password.");
+            }
+        } catch (err) {
+            setError(String(err));
+        } finally {
+            setLoading(false);
+        }
+    };
+
+    const handleRecover = async () => {
+        setLoading(true);
+        setError("");
+        try {
+            await api.recoverVault(recoveryInput, newPassword);
+            onUnlock();
+        } catch (err) {
+            setError(String(err));
         } finally {
             setLoading(false);
         }
@@ -91,130 +145,205 @@ export function VaultLock({ onUnlock, onReset, onCancel, isInit = false, mode =
                         <CardDescription className="text-muted-foreground/80">
                             {isInit
                                 ? "Set a master password for your snippet"
-                                : "Enter your master password to access your snippets"}
+                                : shared
+                                    ? "This shared vault opens with this device's identity"
+                                    : "Enter your master password to access your snippets"}
                         </CardDescription>
                     </div>
                 </CardHeader>
 
-                <form onSubmit={handleSubmit}>
-                    <CardContent className="space-y-4">
-                        <div className="relative group/input">
-                            <Input
-                                id="password"
-                                type={showPassword ? "text" : "password"}
-                                placeholder="Master Password"
-                                value={password}
-                                onChange={(e) => setPassword(e.target.value)}
-                                autoFocus
-                                className="h-11 pr-10 bg-background/50 border-border/60 focus:border-primary focus:ring-primary/30 transition-all placeholder:text-muted-foreground/50"
-                            />
-                            <button
-                                type="button"
-                                onClick={() => setShowPassword(!showPassword)}
-                                className="absolute right-3 top-1/2 -translate-y-1/2 text-muted-foreground/40 hover:text-primary transition-colors focus:outline-none"
-                            >
-                                {showPassword ? <EyeOff className="w-4 h-4" /> : <Eye className="w-4 h-4" />}
-                            </button>
-                        </div>
-
-                        {error && (
-                            <div className="flex items-center gap-2 text-sm text-destructive bg-destructive/10 border border-destructive/20 rounded-lg px-3 py-2 animate-fade-in">
-                                <AlertCircle className="w-4 h-4 shrink-0" />
-                                <span>{error}</span>
-                            </div>
-                        )}
+                {recoveryKey ? (
+                    <CardContent>
+                        <RecoveryKeyPanel recoveryKey={recoveryKey} onDone={onUnlock} />
                     </CardContent>
+                ) : (
+                    <form onSubmit={handleSubmit}>
+                        <CardContent className="space-y-4">
+                            {!shared && (
+                                <div className="relative group/input">
+                                    <Input
+                                        id="password"
+                                        type={showPassword ? "text" : "password"}
+                                        placeholder="Master Password"
+                                        value={password}
+                                        onChange={(e) => setPassword(e.target.value)}
+                                        autoFocus
+                                        className="h-11 pr-10 bg-background/50 border-border/60 focus:border-primary focus:ring-primary/30 transition-all placeholder:text-muted-foreground/50"
+                                    />
+                                    <button
+                                        type="button"
+                                        onClick={() => setShowPassword(!showPassword)}
+                                        className="absolute right-3 top-1/2 -translate-y-1/2 text-muted-foreground/40 hover:text-primary transition-colors focus:outline-none"
+                                    >
+                                        {showPassword ? <EyeOff className="w-4 h-4" /> : <Eye className="w-4 h-4" />}
+                                    </button>
+                                </div>
+                            )}
 
-                    <CardFooter className="flex flex-col gap-4">
-                        <div className="flex flex-col gap-2 w-full">
-                            <Button
-                                type="submit"
-                                className="w-full h-11 font-semibold bg-primary hover:bg-primary/90 text-primary-foreground shadow-lg hover:shadow-xl transition-all hover:glow-sm"
-                                disabled={loading || !password}
-                            >
-                                {loading && !showOptions ? (
-                                    <span className="flex items-center gap-2">
-                                        <div className="w-4 h-4 border-2 border-primary-foreground/30 border-t-primary-foreground rounded-full animate-spin" />
-                                        Processing...
-                                    </span>
-                                ) : (
-                                    <span className="flex items-center gap-2">
-                                        {isInit ? <Unlock className="w-4 h-4" /> : <Lock className="w-4 h-4" />}
-                                        {isInit ? "Initialize" : "Unlock"}
-                                    </span>
-                                )}
-                            </Button>
+                            {isInit && <StrengthMeter password={password} minScore={minPasswordScore} />}
+
+                            {!isInit && keyfilePath && (
+                                <Input
+                                    id="keyfile"
+                                    placeholder="Keyfile path"
+                                    value={keyfile}
+                                    onChange={(e) => setKeyfile(e.target.value)}
+                                    className="h-9 text-xs font-mono bg-background/50 border-border/60 focus:border-primary focus:ring-primary/30 transition-all placeholder:text-muted-foreground/50"
+                                />
+                            )}
+
+                            {isInit && (
+                                <div className="flex items-center justify-between gap-3 px-1">
+                                    <label htmlFor="with-recovery-key" className="text-xs text-muted-foreground">
+                                        Create a recovery key for a forgotten password
+                                    </label>
+                                    <Switch id="with-recovery-key" checked={withRecoveryKey} onCheckedChange={setWithRecoveryKey} />
+                                </div>
+                            )}
 
-                            {mode === 'modal' && onCancel && (
+                            {error && (
+                                <div className="flex items-center gap-2 text-sm text-destructive bg-destructive/10 border border-destructive/20 rounded-lg px-3 py-2 animate-fade-in">
+                                    <AlertCircle className="w-4 h-4 shrink-0" />
+                                    <span>{error}</span>
+                                </div>
+                            )}
+                        </CardContent>
+
+                        <CardFooter className="flex flex-col gap-4">
+                            <div className="flex flex-col gap-2 w-full">
                                 <Button
-                                    type="button"
-                                    variant="outline"
-                                    onClick={onCancel}
-                                    className="w-full h-10 text-xs text-muted-foreground hover:text-foreground"
+                                    type="submit"
+                                    className="w-full h-11 font-semibold bg-primary hover:bg-primary/90 text-primary-foreground shadow-lg hover:shadow-xl transition-all hover:glow-sm"
+                                    disabled={loading || (!shared && !password)}
                                 >
-                                    Cancel
+                                    {loading && !showOptions ? (
+                                        <span className="flex items-center gap-2">
+                                            <div className="w-4 h-4 border-2 border-primary-foreground/30 border-t-primary-foreground rounded-full animate-spin" />
+                                            Processing...
+                                        </span>
+                                    ) : (
+                                        <span className="flex items-center gap-2">
+                                            {isInit ? <Unlock className="w-4 h-4" /> : <Lock className="w-4 h-4" />}
+                                            {isInit ? "Initialize" : "Unlock"}
+                                        </span>
+                                    )}
                                 </Button>
-                            )}
-                        </div>
 
-                        {!isInit && !showOptions && (
-                            <div className="w-full flex flex-col gap-4">
-                                {mode === 'full' && (
+                                {!isInit && remembered && (
                                     <Button
                                         type="button"
-                                        variant="ghost"
-                                        className="w-full h-11 text-xs gap-3 hover:bg-primary/5 active:bg-primary/10 transition-all border border-dashed border-border/40 hover:border-primary/20 hover:text-foreground font-medium"
-                                        onClick={onUnlock}
+                                        variant="outline"
+                                        onClick={handleKeychainUnlock}
                                         disabled={loading}
+                                        className="w-full h-10 text-xs gap-2"
                                     >
-                                        <Unlock className="w-4 h-4 text-muted-foreground/60" />
-                                        <span>Access Public Snippets (No Password)</span>
+                                        <Unlock className="w-4 h-4" />
+                                        Unlock with System Keyring
                                     </Button>
                                 )}
 
-                                <button
-                                    type="button"
-                                    onClick={() => setShowOptions(true)}
-                                    className="text-[10px] text-muted-foreground/60 hover:text-primary transition-colors font-medium underline-offset-4 hover:underline mx-auto"
-                                >
-                                    Forgot Password?
-                                </button>
+                                {mode === 'modal' && onCancel && (
+                                    <Button
+                                        type="button"
+                                        variant="outline"
+                                        onClick={onCancel}
+                                        className="w-full h-10 text-xs text-muted-foreground hover:text-foreground"
+                                    >
+                                        Cancel
+                                    </Button>
+                                )}
                             </div>
-                        )}
 
-                        {showOptions && (
-                            <div className="w-full space-y-3 pt-2 border-t border-border/40 animate-in fade-in slide-in-from-top-2 duration-300">
-                                <div className="text-[10px] uppercase tracking-wider font-bold text-muted-foreground/50 px-1">
-                                    Emergency Recovery
+                            {!isInit && !showOptions && (
+                                <div className="w-full flex flex-col gap-4">
+                                    {mode === 'full' && (
+                                        <Button
+                                            type="button"
+                                            variant="ghost"
+                                            className="w-full h-11 text-xs gap-3 hover:bg-primary/5 active:bg-primary/10 transition-all border border-dashed border-border/40 hover:border-primary/20 hover:text-foreground font-medium"
+                                            onClick={onUnlock}
+                                            disabled={loading}
+                                        >
+                                            <Unlock className="w-4 h-4 text-muted-foreground/60" />
+                                            <span>Access Public Snippets (No Password)</span>
+                                        </Button>
+                                    )}
+
+                                    {!shared && (
+                                        <button
+                                            type="button"
+                                            onClick={() => setShowOptions(true)}
+                                            className="text-[10px] text-muted-foreground/60 hover:text-primary transition-colors font-medium underline-offset-4 hover:underline mx-auto"
+                                        >
+                                            Forgot Password?
+                                        </button>
+                                    )}
                                 </div>
+                            )}
 
-                                <Button
-                                    type="button"
-                                    variant="ghost"
-                                    className={cn(
-                                        "w-full h-10 text-xs justify-start gap-3 transition-colors",
-                                        confirmReset ? "bg-destructive/10 text-destructive hover:bg-destructive/15" : "hover:bg-destructive/10 text-muted-foreground hover:text-destructive"
+                            {showOptions && (
+                                <div className="w-full space-y-3 pt-2 border-t border-border/40 animate-in fade-in slide-in-from-top-2 duration-300">
+                                    <div className="text-[10px] uppercase tracking-wider font-bold text-muted-foreground/50 px-1">
+                                        Emergency Recovery
+                                    </div>
+
+                                    {hasRecoveryKey && (
+                                        <div className="space-y-2">
+                                            <Input
+                                                placeholder="Recovery key"
+                                                value={recoveryInput}
+                                                onChange={(e) => setRecoveryInput(e.target.value)}
+                                                className="h-9 text-xs font-mono bg-background/50 border-border/60 focus:border-primary focus:ring-primary/30 transition-all placeholder:text-muted-foreground/50"
+                                            />
+                                            <Input
+                                                type="password"
+                                                placeholder="New master password"
+                                                value={newPassword}
+                                                onChange={(e) => setNewPassword(e.target.value)}
+                                                className="h-9 text-xs bg-background/50 border-border/60 focus:border-primary focus:ring-primary/30 transition-all placeholder:text-muted-foreground/50"
+                                            />
+                                            <StrengthMeter password={newPassword} minScore={minPasswordScore} />
+                                            <Button
+                                                type="button"
+                                                variant="outline"
+                                                className="w-full h-10 text-xs gap-3"
+                                                onClick={handleRecover}
+                                                disabled={loading || !recoveryInput.trim() || !newPassword}
+                                            >
+                                                <Unlock className="w-3.5 h-3.5" />
+                                                Recover with Recovery Key
+                                            </Button>
+                                        </div>
                                     )}
-                                    onClick={() => confirmReset ? handleReset() : setConfirmReset(true)}
-                                    disabled={loading}
-                                >
-                                    <AlertCircle className="w-3.5 h-3.5" />
-                                    <span>
-                                        {confirmReset ? "Click again to PERMANENTLY DELETE SECRETS" : "Reset Vault (Deletes All Secrets)"}
-                                    </span>
-                                </Button>
 
-                                <button
-                                    type="button"
-                                    onClick={() => { setShowOptions(false); setConfirmReset(false); }}
-                                    className="w-full text-[10px] text-muted-foreground/40 hover:text-muted-foreground transition-colors pt-1"
-                                >
-                                    Cancel
-                                </button>
-                            </div>
-                        )}
-                    </CardFooter>
-                </form>
+                                    <Button
+                                        type="button"
+                                        variant="ghost"
+                                        className={cn(
+                                            "w-full h-10 text-xs justify-start gap-3 transition-colors",
+                                            confirmReset ? "bg-destructive/10 text-destructive hover:bg-destructive/15" : "hover:bg-destructive/10 text-muted-foreground hover:text-destructive"
+                                        )}
+                                        onClick={() => confirmReset ? handleReset() : setConfirmReset(true)}
+                                        disabled={loading}
+                                    >
+                                        <AlertCircle className="w-3.5 h-3.5" />
+                                        <span>
+                                            {confirmReset ? "Click again to PERMANENTLY DELETE SECRETS" : "Reset Vault (Deletes All Secrets)"}
+                                        </span>
+                                    </Button>
+
+                                    <button
+                                        type="button"
+                                        onClick={() => { setShowOptions(false); setConfirmReset(false); }}
+                                        className="w-full text-[10px] text-muted-foreground/40 hover:text-muted-foreground transition-colors pt-1"
+                                    >
+                                        Cancel
+                                    </button>
+                                </div>
+                            )}
+                        </CardFooter>
+                    </form>
+                )}
             </Card>
 
             {/* Version indicator */}
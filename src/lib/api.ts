@@ -1,16 +1,66 @@
 import { invoke } from "@tauri-apps/api/core";
-import { Node, AppSettings } from "../types";
+import { Node, AppSettings, ImportFormat, ConflictStrategy, ImportPreview, ExportFormat, MergeReport, VaultInfo, Recipient, TotpCode, PasswordPolicy, GeneratedPassword, PastValue, Strength, AuditReport } from "../types";
 
 export const api = {
     getData: (): Promise<Node[]> => invoke("get_data"),
 
     saveData: (nodes: Node[]): Promise<void> => invoke("save_data", { nodes }),
 
+    acceptVaultChanges: (): Promise<void> => invoke("accept_vault_changes"),
+
+    restoreVerifiedVault: (): Promise<void> => invoke("restore_verified_vault"),
+
     copySnippet: (id: string): Promise<void> => invoke("copy_snippet", { id }),
 
-    initVault: (password: string): Promise<void> => invoke("init_vault", { password }),
+    generatePassword: (policy: PasswordPolicy): Promise<GeneratedPassword> =>
+        invoke("generate_password", { policy }),
+
+    regenerateSecret: (id: string, policy: PasswordPolicy): Promise<Node> =>
+        invoke("regenerate_secret", { id, policy }),
+
+    estimateStrength: (password: string): Promise<Strength> => invoke("estimate_strength", { password }),
+
+    auditVault: (): Promise<AuditReport> => invoke("audit_vault"),
+
+    getSecretHistory: (id: string): Promise<PastValue[]> => invoke("get_secret_history", { id }),
+
+    addTotp: (uri: string, parentId: string | null): Promise<Node> =>
+        invoke("add_totp", { uri, parentId }),
+
+    getTotpCode: (id: string): Promise<TotpCode> => invoke("get_totp_code", { id }),
+
+    addAttachment: (path: string, parentId: string | null): Promise<Node> =>
+        invoke("add_attachment", { path, parentId }),
+
+    saveAttachment: (id: string, path: string): Promise<void> =>
+        invoke("save_attachment", { id, path }),
+
+    copyAttachment: (id: string): Promise<void> => invoke("copy_attachment", { id }),
+
+    copyAttachmentPath: (id: string): Promise<string> => invoke("copy_attachment_path", { id }),
+
+    initVault: (password: string, keyfile?: string, withRecoveryKey = false): Promise<string | null> =>
+        invoke("init_vault", { password, keyfile, withRecoveryKey }),
+
+    unlockVault: (password: string, keyfile?: string): Promise<boolean> => invoke("unlock_vault", { password, keyfile }),
+
+    unlockWithKeychain: (): Promise<boolean> => invoke("unlock_with_keychain"),
+
+    setRememberKey: (remember: boolean): Promise<void> => invoke("set_remember_key", { remember }),
+
+    setKeyfile: (password: string, keyfile: string | null): Promise<void> =>
+        invoke("set_keyfile", { password, keyfile }),
+
+    changeMasterPassword: (password: string, newPassword: string): Promise<void> =>
+        invoke("change_master_password", { password, newPassword }),
+
+    createRecoveryKey: (password: string): Promise<string> => invoke("create_recovery_key", { password }),
+
+    saveEmergencyKit: (recoveryKey: string, path: string): Promise<void> =>
+        invoke("save_emergency_kit", { recoveryKey, path }),
 
-    unlockVault: (password: string): Promise<boolean> => invoke("unlock_vault", { password }),
+    recoverVault: (recoveryKey: string, newPassword: string): Promise<void> =>
+        invoke("recover_vault", { recoveryKey, newPassword }),
 
     lockVault: (): Promise<void> => invoke("lock_vault"),
 
@@ -25,5 +75,66 @@ export const api = {
     resetVault: (): Promise<[Node[], AppSettings]> => invoke("reset_vault"),
 
     isVaultUnlocked: (): Promise<boolean> => invoke("is_vault_unlocked"),
-};
 
+    importData: (
+        path: string,
+        options: {
+            format?: ImportFormat;
+            conflictStrategy?: ConflictStrategy;
+            passphrase?: string;
+            dryRun: boolean;
+        }
+    ): Promise<ImportPreview> => invoke("import_data", { path, ...options }),
+
+    exportData: (
+        path: string,
+        format: ExportFormat,
+        options: { passphrase?: string; includeSecrets?: boolean; masterPassword?: string } = {}
+    ): Promise<void> =>
+        invoke("export_data", {
+            path,
+            format,
+            includeSecrets: options.includeSecrets ?? false,
+            passphrase: options.passphrase,
+            masterPassword: options.masterPassword,
+        }),
+
+    respondIpcApproval: (requestId: number, approved: boolean, remember: boolean): Promise<void> =>
+        invoke("respond_ipc_approval", { requestId, approved, remember }),
+
+    getIpcEndpoint: (): Promise<string | null> => invoke("get_ipc_endpoint"),
+
+    getSyncConflicts: (): Promise<string[]> => invoke("get_sync_conflicts"),
+
+    mergeSyncConflicts: (): Promise<MergeReport> => invoke("merge_sync_conflicts"),
+
+    gitPull: (): Promise<MergeReport> => invoke("git_pull"),
+
+    gitPush: (): Promise<void> => invoke("git_push"),
+
+    listVaults: (): Promise<VaultInfo[]> => invoke("list_vaults"),
+
+    createVault: (name: string): Promise<string> => invoke("create_vault", { name }),
+
+    renameVault: (id: string, name: string): Promise<void> => invoke("rename_vault", { id, name }),
+
+    removeVault: (id: string): Promise<void> => invoke("remove_vault", { id }),
+
+    switchVault: (id: string): Promise<void> => invoke("switch_vault", { id }),
+
+    setVaultMounted: (id: string, mounted: boolean): Promise<void> =>
+        invoke("set_vault_mounted", { id, mounted }),
+
+    getIdentity: (): Promise<string> => invoke("get_identity"),
+
+    addSharedVault: (name: string, memberName: string, folder: string): Promise<string> =>
+        invoke("add_shared_vault", { name, memberName, folder }),
+
+    listRecipients: (): Promise<Recipient[]> => invoke("list_recipients"),
+
+    addRecipient: (name: string, publicKey: string): Promise<void> =>
+        invoke("add_recipient", { name, publicKey }),
+
+    removeRecipient: (publicKey: string): Promise<void> =>
+        invoke("remove_recipient", { publicKey }),
+};
@@ -1,4 +1,6 @@
-export type NodeType = 'folder' | 'snippet';
+export type NodeType = 'folder' | 'snippet' | 'separator' | 'attachment';
+
+export type SortMode = 'manual' | 'alphabetical' | 'created' | 'last_used' | 'use_count';
 
 export interface Node {
     id: string;             // UUID v4
@@ -6,14 +8,86 @@ export interface Node {
     label: string;          // Name of the folder or snippet
     parentId: string | null; // null for root
     createdAt: number;      // Timestamp
+    showInTray?: boolean;   // Hidden from the tray menu when false
+    icon?: string;          // Emoji or bundled tray icon name (e.g. "dot-red")
 
     // Fields for Folder
     children?: Node[];      // Recursive children
+    sortMode?: SortMode;    // Order applied to children (default manual)
 
     // Fields for Snippet
     value?: string;         // Plain text (if public)
     encryptedValue?: string;// Hex string (if private)
     isSecret?: boolean;     // Requires unlock to copy?
+    lastUsedAt?: number;    // Timestamp of the last copy
+    useCount?: number;      // Number of copies
+    totp?: {                // One-time code snippet, whose value is an otpauth:// URI
+        period: number;     // Seconds each code is valid for
+    };
+    history?: HistoryEntry[];// Earlier values of a secret, oldest first
+    expiresAt?: number;     // Timestamp of when a secret is due for rotation
+
+    // Fields for Attachment, whose file is in encryptedValue or a blob file
+    attachment?: {
+        fileName: string;
+        size: number;       // Bytes
+        blob?: boolean;     // Stored next to sklad.json instead of inline
+    };
+
+    revision?: number;      // Bumped on every change, used to merge sync conflicts
+    modifiedAt?: number;    // Timestamp of the last change
+}
+
+export interface HistoryEntry {
+    encryptedValue: string;
+    replacedAt: number;     // Timestamp of when the value was replaced
+}
+
+export interface PastValue {
+    value: string;
+    replacedAt: number;
+}
+
+export interface PasswordPolicy {
+    length: number;
+    lowercase: boolean;
+    uppercase: boolean;
+    digits: boolean;
+    symbols: boolean;
+    excludeAmbiguous: boolean;
+    passphrase: boolean;    // Random words instead of characters
+    words: number;
+    separator: string;
+}
+
+export interface GeneratedPassword {
+    password: string;
+    entropyBits: number;
+}
+
+export interface Strength {
+    score: number;          // 0 (guessable) to 4 (very strong)
+    guessesLog10: number;
+    warning?: string | null;
+    suggestions: string[];
+}
+
+export interface AuditEntry {
+    id: string;
+    path: string;
+}
+
+export interface AuditReport {
+    checked: number;
+    weak: (AuditEntry & { score: number; warning?: string | null })[];
+    reused: AuditEntry[][];     // Groups of secrets with the same value
+    breached: (AuditEntry & { count: number })[];
+    breachListChecked: boolean;
+}
+
+export interface TotpCode {
+    code: string;
+    remaining: number;      // Seconds until the code changes
 }
 
 export interface AppSettings {
@@ -22,6 +96,15 @@ export interface AppSettings {
         lockTimeout: number;
         clearClipboard: boolean;
         masterPasswordEnabled: boolean;
+        shared?: boolean;           // Key wrapped for each member instead of a password
+        keyfilePath?: string | null;    // Keyfile needed along with the password
+        rememberedKey?: string | null;  // Vault key wrapped with a key from the OS secret store
+        recoveryWrappedKey?: string | null; // Vault key wrapped with the recovery key
+        maxUnlockAttempts?: number;     // Failed unlocks in a row before lockoutAction, 0 = no limit
+        lockoutAction?: 'lockout' | 'wipe';
+        minPasswordScore?: number;      // Lowest strength score accepted for a master password
+        breachListPath?: string | null; // SHA-1 breach list file or folder of range files
+        ciphertextVersion?: number;     // 2 once no secret is left in the older ciphertext format
     };
     notificationsEnabled: boolean;
     launchAtStartup: boolean;
@@ -30,4 +113,112 @@ export interface AppSettings {
     globalCreateShortcut: string;
     trayClickAction?: 'copy_last' | 'open_app';
     trayMenuRootPosition?: 'top' | 'bottom';
+    trayMaxDepth?: number;          // 0 = unlimited
+    trayMaxItemsPerFolder?: number; // 0 = unlimited
+    rootSortMode?: SortMode;
+    ipcEnabled?: boolean;           // Local socket for scripts
+    ipcTrustedClients?: string[];   // Executables approved with "always allow"
+    vaultPath?: string | null;      // Folder holding sklad.json (default: app data dir)
+    gitEnabled?: boolean;           // Commit every save to git
+    gitRemote?: string | null;      // Remote for pull and push
+    vaultId?: string;               // Active vault, which the fields above belong to
+    vaultName?: string;
+    vaults?: VaultProfile[];        // The other vaults
+    mountedVaults?: string[];       // Vaults listed in the tray below the active one
+}
+
+// Per-vault settings of an inactive vault
+export interface VaultProfile {
+    id: string;
+    name: string;
+    security: AppSettings['security'];
+    vaultPath?: string | null;
+    gitEnabled?: boolean;
+    gitRemote?: string | null;
+}
+
+export interface VaultInfo {
+    id: string;
+    name: string;
+    active: boolean;
+    mounted: boolean;
+    masterPasswordEnabled: boolean;
+    unlocked: boolean;
+}
+
+// A wrong password entered since the last successful unlock
+export interface FailedUnlock {
+    at: number;
+    source: string;     // "app", "cli" or "export"
+}
+
+// A member of a shared vault
+export interface Recipient {
+    name: string;
+    publicKey: string;
+}
+
+export type ImportFormat =
+    | 'keepass_xml'
+    | 'keepassxc_csv'
+    | 'bitwarden_json'
+    | 'onepassword_csv'
+    | 'espanso'
+    | 'vscode_snippets'
+    | 'text_folder'
+    | 'sklad_bundle';
+
+export type ExportFormat =
+    | 'sklad_bundle'   // Encrypted, includes secrets and settings
+    | 'json'
+    | 'csv'
+    | 'markdown'
+    | 'espanso'
+    | 'vscode_snippets';
+
+export type ConflictStrategy = 'skip' | 'overwrite' | 'keep_both';
+
+export interface ImportPreview {
+    format: ImportFormat;
+    entries: {
+        path: string[];
+        label: string;
+        isSecret: boolean;
+        conflict: boolean;     // Label (or, for bundles, node id) already exists
+    }[];
+    snippetCount: number;
+    secretCount: number;
+    conflictCount: number;
+}
+
+export interface IpcApprovalRequest {
+    requestId: number;
+    client: string;         // Executable path, or "pid N" when unknown
+    pid: number | null;
+    canRemember: boolean;   // False for interpreters like python3, which run any script
+}
+
+/** A node that differs from the vault as last verified on this device. */
+export interface NodeChange {
+    id: string;
+    path: string;           // Labels from the root down, "Folder/Sub/Label"
+    kind: "added" | "removed" | "changed";
+    fields: string[];       // What changed: "label", "location", "value", "secret", ...
+    before: string | null;  // Plain values, for changed public snippets
+    after: string | null;
+}
+
+/** The vault file failed its integrity check. */
+export interface TamperReport {
+    changes: NodeChange[];
+}
+
+export interface MergeReport {
+    mergedFiles: string[];
+    conflicts: {
+        id: string;
+        label: string;
+        kind: 'both_changed' | 'changed_and_deleted';
+        kept: 'local' | 'remote';
+    }[];
 }